@@ -61,7 +61,7 @@ fn main() {
 
     let app = GraphicsTreeApp::new("plant_growth_example".into())
         .with_heightmap_terrain(20.0, [0.0, 0.0, 0.0], 50, 2.0, "assets/Noise_Texture.png".into(), GRASS_COLOUR, ROCK_COLOUR, GRASS_SLOPE_THRESHOLD, GRASS_BLEND_AMOUNT)
-        .set_branch_presets(branch_types, branch_conditions)
+        .set_branch_presets(branch_types, branch_conditions).expect("the branch presets are valid")
         .set_shadow_cell_data(0.5, 3)
         .set_plant_death_rate(0.1)
         .set_time_step(5.0)
@@ -54,7 +54,7 @@ fn main() {
 
 
     let app = GraphicsTreeApp::new("plant_growth_example".into())
-        .set_branch_presets(branch_types, branch_conditions)
+        .set_branch_presets(branch_types, branch_conditions).expect("the branch presets are valid")
         .set_shadow_cell_data(0.5, 3)
         .set_plant_death_rate(0.1)
         .set_time_step(5.0)
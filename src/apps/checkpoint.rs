@@ -0,0 +1,437 @@
+//! Saving and loading the full state of a simulation, so that long runs can be paused, resumed and shared
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+use bevy_ecs::{
+    prelude::*,
+    entity::{EntityMap, MapEntities, MapEntitiesError},
+};
+use serde::{Serialize, Deserialize};
+use super::super::{
+    branches::{
+        branch::*,
+        branch_node::*,
+        branch_prototypes::*,
+    },
+    plants::{
+        plant::*,
+        plant_selection::PlantSpeciesSampler,
+        plant_statistics::{ForestStatistics, create_forest_statistics},
+    },
+    roots::root::*,
+    environment::{
+        params::*,
+        light_cells::{LightCells, ColumnLight},
+        soil_cells::SoilCells,
+        soil_grid::SoilGrid,
+        climate::ClimateDriver,
+        sun_path::SunPath,
+        sky_dome::SkyDome,
+        ground_light::GroundLight,
+        terrain::{TerrainTag, TerrainCollider, TerrainMeshData, spawn_terrain_from_mesh_data},
+    },
+};
+#[cfg(feature = "vulkan_graphics")]
+use super::super::graphics::branch_mesh_gen::MeshUpdateQueue;
+
+
+const CHECKPOINT_MAGIC: [u8; 4] = *b"SSCP";
+/// increase this whenever the saved data changes, old checkpoints will then fail to load rather than loading incorrectly
+const CHECKPOINT_VERSION: u32 = 1;
+
+
+type SavedPlant = (Entity, PlantBounds, PlantData, PlantGrowthControlFactors, PlantPlasticityParameters, Option<PlantRoots>, Option<PlantSpecies>);
+type SavedBranch = (Entity, BranchBounds, BranchData, BranchGrowthData, BranchConnectionData, BranchPrototypeRef);
+type SavedNode = (Entity, BranchNodeData, BranchNodeConnectionData, BranchNodeGrowthData);
+type SavedRoot = (Entity, RootData, RootConnectionData);
+type SavedRootNode = (Entity, RootNodeData);
+type SavedTerrain = (TerrainMeshData, f32);
+
+
+/// An error from saving or loading a checkpoint
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    /// the file does not start with the checkpoint header
+    NotACheckpoint,
+    /// the checkpoint was made by a different version of the format
+    UnsupportedVersion(u32),
+    /// a saved entity refers to an entity that was not saved
+    MissingEntity(Entity),
+    /// the checkpoint was saved with a different light model to the one the app was built with
+    LightModelMismatch {
+        saved: String,
+        current: String,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "checkpoint io error: {}", err),
+            CheckpointError::Encoding(err) => write!(f, "checkpoint encoding error: {}", err),
+            CheckpointError::NotACheckpoint => write!(f, "the data is not a checkpoint"),
+            CheckpointError::UnsupportedVersion(version) => write!(f, "checkpoint version {} is not supported, expected version {}", version, CHECKPOINT_VERSION),
+            CheckpointError::MissingEntity(entity) => write!(f, "checkpoint refers to entity {:?} which was not saved", entity),
+            CheckpointError::LightModelMismatch{saved, current} => write!(f, "checkpoint was saved with the light model `{}`, but the app uses `{}`", saved, current),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for CheckpointError {
+    fn from(err: bincode::Error) -> Self {
+        CheckpointError::Encoding(err)
+    }
+}
+
+impl From<MapEntitiesError> for CheckpointError {
+    fn from(err: MapEntitiesError) -> Self {
+        match err {
+            MapEntitiesError::EntityNotFound(entity) => CheckpointError::MissingEntity(entity),
+        }
+    }
+}
+
+
+/// writes the plants, branches, nodes, roots, terrain and simulation resources of the world to the writer
+pub fn save_checkpoint(world: &mut World, elapsed_years: f32, writer: &mut impl Write) -> Result<(), CheckpointError> {
+    let mut plant_query = world.query_filtered::<(Entity, &PlantBounds, &PlantData, &PlantGrowthControlFactors, &PlantPlasticityParameters, Option<&PlantRoots>, Option<&PlantSpecies>), With<PlantTag>>();
+    let mut branch_query = world.query_filtered::<(Entity, &BranchBounds, &BranchData, &BranchGrowthData, &BranchConnectionData, &BranchPrototypeRef), With<BranchTag>>();
+    let mut node_query = world.query_filtered::<(Entity, &BranchNodeData, &BranchNodeConnectionData, &BranchNodeGrowthData), With<BranchNodeTag>>();
+    let mut root_query = world.query_filtered::<(Entity, &RootData, &RootConnectionData), With<RootTag>>();
+    let mut root_node_query = world.query_filtered::<(Entity, &RootNodeData), With<RootNodeTag>>();
+    let mut terrain_query = world.query_filtered::<(&TerrainMeshData, &TerrainCollider), With<TerrainTag>>();
+
+    let world: &World = world;
+
+    writer.write_all(&CHECKPOINT_MAGIC)?;
+    writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut *writer, &light_model_name(world))?;
+
+    bincode::serialize_into(&mut *writer, &elapsed_years)?;
+
+    // entities
+    bincode::serialize_into(&mut *writer, &plant_query.iter(world).collect::<Vec<_>>())?;
+    bincode::serialize_into(&mut *writer, &branch_query.iter(world).collect::<Vec<_>>())?;
+    bincode::serialize_into(&mut *writer, &node_query.iter(world).collect::<Vec<_>>())?;
+    bincode::serialize_into(&mut *writer, &root_query.iter(world).collect::<Vec<_>>())?;
+    bincode::serialize_into(&mut *writer, &root_node_query.iter(world).collect::<Vec<_>>())?;
+    bincode::serialize_into(&mut *writer, &terrain_query.iter(world).map(|(mesh_data, collider)| (mesh_data, collider.max_height)).collect::<Vec<_>>())?;
+
+    // resources
+    bincode::serialize_into(&mut *writer, &world.get_resource::<GravityResources>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<PhysicalAgeStep>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<MoistureAndTemp>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<PlantDeathRate>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<LightCells>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<BranchPrototypes>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<BranchPrototypesSampler>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<PlantSpeciesSampler>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<SimulationRng>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<SoilCells>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<RootGrowthSettings>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<SoilGrid>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<ClimateDriver>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<ColumnLight>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<SunPath>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<SkyDome>())?;
+    bincode::serialize_into(&mut *writer, &world.get_resource::<GroundLight>())?;
+
+    Ok(())
+}
+
+
+/// replaces everything in the world with the state saved in the checkpoint, returning the number of years that had passed when it was saved
+///
+/// The world is only changed if the whole checkpoint is read successfully.
+/// Entities are given new ids, and all references between them are updated to match
+pub fn load_checkpoint(world: &mut World, reader: &mut impl Read) -> Result<f32, CheckpointError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != CHECKPOINT_MAGIC {return Err(CheckpointError::NotACheckpoint);}
+
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != CHECKPOINT_VERSION {return Err(CheckpointError::UnsupportedVersion(version));}
+
+    // the light systems in the schedule need the light model they were built with
+    let saved_light_model: Option<String> = bincode::deserialize_from(&mut *reader)?;
+    let current_light_model = light_model_name(world);
+    if saved_light_model.as_deref() != current_light_model {
+        return Err(CheckpointError::LightModelMismatch {
+            saved: saved_light_model.unwrap_or_else(|| "none".into()),
+            current: current_light_model.unwrap_or("none").into(),
+        });
+    }
+
+    let elapsed_years: f32 = bincode::deserialize_from(&mut *reader)?;
+
+    let mut plants: Vec<SavedPlant> = bincode::deserialize_from(&mut *reader)?;
+    let mut branches: Vec<SavedBranch> = bincode::deserialize_from(&mut *reader)?;
+    let mut nodes: Vec<SavedNode> = bincode::deserialize_from(&mut *reader)?;
+    let mut roots: Vec<SavedRoot> = bincode::deserialize_from(&mut *reader)?;
+    let mut root_nodes: Vec<SavedRootNode> = bincode::deserialize_from(&mut *reader)?;
+    let terrain: Vec<SavedTerrain> = bincode::deserialize_from(&mut *reader)?;
+
+    let gravity: Option<GravityResources> = bincode::deserialize_from(&mut *reader)?;
+    let age_step: Option<PhysicalAgeStep> = bincode::deserialize_from(&mut *reader)?;
+    let moisture_and_temp: Option<MoistureAndTemp> = bincode::deserialize_from(&mut *reader)?;
+    let death_rate: Option<PlantDeathRate> = bincode::deserialize_from(&mut *reader)?;
+    let light_cells: Option<LightCells> = bincode::deserialize_from(&mut *reader)?;
+    let prototypes: Option<BranchPrototypes> = bincode::deserialize_from(&mut *reader)?;
+    let prototypes_sampler: Option<BranchPrototypesSampler> = bincode::deserialize_from(&mut *reader)?;
+    let species_sampler: Option<PlantSpeciesSampler> = bincode::deserialize_from(&mut *reader)?;
+    let rng: Option<SimulationRng> = bincode::deserialize_from(&mut *reader)?;
+    let soil_cells: Option<SoilCells> = bincode::deserialize_from(&mut *reader)?;
+    let root_settings: Option<RootGrowthSettings> = bincode::deserialize_from(&mut *reader)?;
+    let soil_grid: Option<SoilGrid> = bincode::deserialize_from(&mut *reader)?;
+    let climate: Option<ClimateDriver> = bincode::deserialize_from(&mut *reader)?;
+    let column_light: Option<ColumnLight> = bincode::deserialize_from(&mut *reader)?;
+    let sun_path: Option<SunPath> = bincode::deserialize_from(&mut *reader)?;
+    let sky_dome: Option<SkyDome> = bincode::deserialize_from(&mut *reader)?;
+    let ground_light: Option<GroundLight> = bincode::deserialize_from(&mut *reader)?;
+
+
+    let saved_ids: Vec<Entity> = plants.iter().map(|plant| plant.0)
+        .chain(branches.iter().map(|branch| branch.0))
+        .chain(nodes.iter().map(|node| node.0))
+        .chain(roots.iter().map(|root| root.0))
+        .chain(root_nodes.iter().map(|node| node.0))
+        .collect();
+
+    // check every reference is to a saved entity before touching the world, so a bad checkpoint leaves it as it was
+    let mut saved_map = EntityMap::default();
+    for id in saved_ids.iter() {
+        saved_map.insert(*id, *id);
+    }
+    map_saved_entities(&mut plants, &mut branches, &mut nodes, &mut roots, &mut root_nodes, &saved_map)?;
+
+    // statistics are not saved, but the rows from before the checkpoint are kept
+    let statistics = world.remove_resource::<ForestStatistics>();
+    world.clear_all();
+
+    let mut entity_map = EntityMap::default();
+    for id in saved_ids.iter() {
+        entity_map.insert(*id, world.spawn_empty().id());
+    }
+    map_saved_entities(&mut plants, &mut branches, &mut nodes, &mut roots, &mut root_nodes, &entity_map)?;
+
+    #[cfg(feature = "vulkan_graphics")]
+    let root_ids: Vec<Entity> = plants.iter().filter_map(|plant| plant.2.root_node).collect();
+
+    for (id, bounds, data, growth_factors, plasticity_params, plant_roots, species) in plants {
+        world.entity_mut(id).insert(PlantBundle {
+            tag: PlantTag,
+            bounds,
+            data,
+            growth_factors,
+            plasticity_params,
+        });
+        if let Some(plant_roots) = plant_roots {
+            world.entity_mut(id).insert(plant_roots);
+        }
+        if let Some(species) = species {
+            world.entity_mut(id).insert(species);
+        }
+    }
+    for (id, bounds, data, growth_data, connections, prototype) in branches {
+        world.entity_mut(id).insert(BranchBundle {
+            bounds,
+            data,
+            growth_data,
+            connections,
+            prototype,
+            ..Default::default()
+        });
+    }
+    for (id, data, connections, growth_data) in nodes {
+        world.entity_mut(id).insert(BranchNodeBundle {
+            tag: BranchNodeTag,
+            data,
+            connections,
+            growth_data,
+        });
+    }
+
+    for (id, data, connections) in roots {
+        world.entity_mut(id).insert(RootBundle {
+            tag: RootTag,
+            data,
+            connections,
+        });
+    }
+    for (id, data) in root_nodes {
+        world.entity_mut(id).insert(RootNodeBundle {
+            tag: RootNodeTag,
+            data,
+        });
+    }
+
+    for (mesh_data, max_height) in terrain {
+        spawn_terrain_from_mesh_data(mesh_data, max_height, world);
+    }
+
+    // every branch mesh needs to be rebuilt, starting from the plant roots
+    #[cfg(feature = "vulkan_graphics")]
+    {
+        world.spawn(MeshUpdateQueue::new_from_many(root_ids, 5));
+    }
+
+    if let Some(resource) = gravity {world.insert_resource(resource);}
+    if let Some(resource) = age_step {world.insert_resource(resource);}
+    if let Some(resource) = moisture_and_temp {world.insert_resource(resource);}
+    if let Some(resource) = death_rate {world.insert_resource(resource);}
+    if let Some(resource) = light_cells {world.insert_resource(resource);}
+    if let Some(resource) = prototypes {world.insert_resource(resource);}
+    if let Some(resource) = prototypes_sampler {world.insert_resource(resource);}
+    if let Some(resource) = species_sampler {world.insert_resource(resource);}
+    if let Some(resource) = rng {world.insert_resource(resource);}
+    if let Some(resource) = soil_cells {world.insert_resource(resource);}
+    if let Some(resource) = root_settings {world.insert_resource(resource);}
+    if let Some(resource) = soil_grid {world.insert_resource(resource);}
+    if let Some(resource) = climate {world.insert_resource(resource);}
+    if let Some(resource) = column_light {world.insert_resource(resource);}
+    if let Some(resource) = sun_path {world.insert_resource(resource);}
+    if let Some(resource) = sky_dome {world.insert_resource(resource);}
+    if let Some(resource) = ground_light {world.insert_resource(resource);}
+    create_forest_statistics(world, statistics, elapsed_years);
+
+    Ok(elapsed_years)
+}
+
+
+/// returns the name of the light model in the world, or none if there isn't one
+fn light_model_name(world: &World) -> Option<&'static str> {
+    if world.contains_resource::<SunPath>() {Some("sun path")}
+    else if world.contains_resource::<SkyDome>() {Some("sky dome")}
+    else if world.contains_resource::<ColumnLight>() {Some("column")}
+    else {None}
+}
+
+/// applies the entity map to the ids of the saved entities and every reference they have to other entities
+fn map_saved_entities(
+    plants: &mut [SavedPlant],
+    branches: &mut [SavedBranch],
+    nodes: &mut [SavedNode],
+    roots: &mut [SavedRoot],
+    root_nodes: &mut [SavedRootNode],
+    entity_map: &EntityMap,
+) -> Result<(), CheckpointError> {
+    for plant in plants.iter_mut() {
+        plant.0 = entity_map.get(plant.0)?;
+        plant.2.map_entities(entity_map)?;
+        if let Some(plant_roots) = plant.5.as_mut() {
+            plant_roots.map_entities(entity_map)?;
+        }
+    }
+    for branch in branches.iter_mut() {
+        branch.0 = entity_map.get(branch.0)?;
+        branch.2.map_entities(entity_map)?;
+        branch.3.map_entities(entity_map)?;
+        branch.4.map_entities(entity_map)?;
+    }
+    for node in nodes.iter_mut() {
+        node.0 = entity_map.get(node.0)?;
+        node.2.map_entities(entity_map)?;
+    }
+    for root in roots.iter_mut() {
+        root.0 = entity_map.get(root.0)?;
+        root.1.map_entities(entity_map)?;
+        root.2.map_entities(entity_map)?;
+    }
+    for node in root_nodes.iter_mut() {
+        node.0 = entity_map.get(node.0)?;
+    }
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::*;
+    use super::super::{data_output, output_tests::build_test_world};
+
+    #[test]
+    fn round_trip_test() {
+        let mut world = build_test_world();
+        world.insert_resource(PlantDeathRate::new(0.5));
+        let mut data: Vec<u8> = Vec::new();
+        save_checkpoint(&mut world, 12.5, &mut data).unwrap();
+
+        // spawn some entities first so the loaded entities get different ids
+        let mut new_world = World::new();
+        for _ in 0..10 {new_world.spawn(BranchNodeBundle::default());}
+        let elapsed_years = load_checkpoint(&mut new_world, &mut data.as_slice()).unwrap();
+
+        assert_eq!(elapsed_years, 12.5);
+        assert_eq!(data_output(&mut new_world), data_output(&mut world));
+        assert_eq!(new_world.resource::<PlantDeathRate>().v_max_decrease, 0.5);
+    }
+
+    #[test]
+    fn entity_remap_test() {
+        let mut world = build_test_world();
+        let mut data: Vec<u8> = Vec::new();
+        save_checkpoint(&mut world, 0.0, &mut data).unwrap();
+
+        let mut new_world = World::new();
+        for _ in 0..10 {new_world.spawn_empty();}
+        load_checkpoint(&mut new_world, &mut data.as_slice()).unwrap();
+
+        // every branch parent node should be a node in the new world
+        let parent_nodes: Vec<Entity> = new_world.query::<&BranchData>().iter(&new_world).filter_map(|branch| branch.parent_node).collect();
+        assert_eq!(parent_nodes.len(), 1);
+        assert!(new_world.get::<BranchNodeData>(parent_nodes[0]).is_some());
+    }
+
+    #[test]
+    fn bad_header_test() {
+        let mut world = World::new();
+        let mut data: Vec<u8> = Vec::new();
+        save_checkpoint(&mut world, 0.0, &mut data).unwrap();
+
+        let mut bad_magic = data.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(load_checkpoint(&mut world, &mut bad_magic.as_slice()), Err(CheckpointError::NotACheckpoint)));
+
+        let mut bad_version = data.clone();
+        bad_version[4] = 99;
+        assert!(matches!(load_checkpoint(&mut world, &mut bad_version.as_slice()), Err(CheckpointError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    fn light_model_mismatch_test() {
+        let mut world = build_test_world();
+        world.insert_resource(SkyDome::new(2, 4));
+        let mut data: Vec<u8> = Vec::new();
+        save_checkpoint(&mut world, 0.0, &mut data).unwrap();
+
+        let mut column_world = World::new();
+        column_world.insert_resource(ColumnLight);
+        match load_checkpoint(&mut column_world, &mut data.as_slice()) {
+            Err(CheckpointError::LightModelMismatch{saved, current}) => assert_eq!((saved.as_str(), current.as_str()), ("sky dome", "column")),
+            other => panic!("expected a light model mismatch, got {:?}", other),
+        }
+
+        match load_checkpoint(&mut World::new(), &mut data.as_slice()) {
+            Err(CheckpointError::LightModelMismatch{saved, current}) => assert_eq!((saved.as_str(), current.as_str()), ("sky dome", "none")),
+            other => panic!("expected a light model mismatch, got {:?}", other),
+        }
+
+        let mut sky_dome_world = World::new();
+        sky_dome_world.insert_resource(SkyDome::new(2, 4));
+        assert!(load_checkpoint(&mut sky_dome_world, &mut data.as_slice()).is_ok());
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    fs::File,
+    io::{self, Write, BufWriter},
+    path::Path,
+};
+use serde_json::{json, Value};
+use super::super::{PlantMesh, PlantDetails, TerrainMesh};
+use super::{BARK_MATERIAL, TERRAIN_MATERIAL};
+
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_VERSION: u32 = 2;
+const JSON_CHUNK_TYPE: u32 = 0x4E4F534A;
+const BIN_CHUNK_TYPE: u32 = 0x004E4942;
+
+// gltf enums
+const FLOAT_COMPONENT: u32 = 5126;
+const UNSIGNED_INT_COMPONENT: u32 = 5125;
+const ARRAY_BUFFER_TARGET: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER_TARGET: u32 = 34963;
+
+
+/// writes the plants and terrain to a binary gltf file
+///
+/// - Each plant is a node positioned at the plant's position, with a mesh primitive for each of its branches
+/// - The species index, age, climate adaption and vigor of each plant are stored in the node's extras
+/// - The terrain is a separate node named "terrain"
+pub(crate) fn write_glb(path: &Path, plants: &[(PlantMesh, PlantDetails)], terrain: Option<&TerrainMesh>) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_glb_data(&mut file, plants, terrain)?;
+    file.flush()
+}
+
+
+/// builds up the json document and binary buffer of a gltf file
+struct GltfBuilder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl GltfBuilder {
+    fn new() -> Self {
+        GltfBuilder {buffer: Vec::new(), buffer_views: Vec::new(), accessors: Vec::new()}
+    }
+
+    /// adds the bytes to the buffer as a new buffer view and returns the index of the view
+    fn add_buffer_view(&mut self, bytes: Vec<u8>, target: u32) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend(bytes);
+        // every component is 4 bytes, but pad anyway so views always stay aligned
+        while !self.buffer.len().is_multiple_of(4) {self.buffer.push(0);}
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": self.buffer.len() - offset,
+            "target": target,
+        }));
+        self.buffer_views.len() - 1
+    }
+
+    /// adds a vec3 accessor for the given vectors and returns the index of the accessor
+    fn add_vec3s(&mut self, vectors: &[[f32; 3]], with_bounds: bool) -> usize {
+        let bytes: Vec<u8> = vectors.iter().flatten().flat_map(|x| x.to_le_bytes()).collect();
+        let view = self.add_buffer_view(bytes, ARRAY_BUFFER_TARGET);
+
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT_COMPONENT,
+            "count": vectors.len(),
+            "type": "VEC3",
+        });
+
+        // positions need bounds to be valid gltf
+        if with_bounds {
+            let mut min = [f32::MAX; 3];
+            let mut max = [f32::MIN; 3];
+            for vector in vectors.iter() {
+                for i in 0..3 {
+                    min[i] = min[i].min(vector[i]);
+                    max[i] = max[i].max(vector[i]);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// adds the indices as a buffer view, returns the index of the view
+    fn add_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|x| x.to_le_bytes()).collect();
+        self.add_buffer_view(bytes, ELEMENT_ARRAY_BUFFER_TARGET)
+    }
+
+    /// adds an accessor for a range of indices in an index buffer view, returns the index of the accessor
+    fn add_index_range(&mut self, view: usize, start: usize, count: usize) -> usize {
+        self.accessors.push(json!({
+            "bufferView": view,
+            "byteOffset": start * 4,
+            "componentType": UNSIGNED_INT_COMPONENT,
+            "count": count,
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+}
+
+
+/// writes the glb data to the given writer
+fn write_glb_data(
+    writer: &mut impl Write,
+    plants: &[(PlantMesh, PlantDetails)],
+    terrain: Option<&TerrainMesh>,
+) -> io::Result<()> {
+    let mut builder = GltfBuilder::new();
+    let mut nodes: Vec<Value> = Vec::new();
+    let mut meshes: Vec<Value> = Vec::new();
+
+    for (i, (plant, details)) in plants.iter().enumerate() {
+        let mut node = json!({
+            "name": format!("plant_{}", i),
+            "translation": plant.position,
+            "extras": {
+                "species": details.species,
+                "age": details.age,
+                "climate_adaption": details.climate_adaption,
+                "vigor": details.vigor,
+            },
+        });
+
+        // the node is moved to the plant position, so the vertices need to be relative to it
+        let local_vertices: Vec<[f32; 3]> = plant.vertices.iter()
+            .map(|vertex| [vertex[0] - plant.position[0], vertex[1] - plant.position[1], vertex[2] - plant.position[2]])
+            .collect();
+
+        let primitive_branches: Vec<_> = plant.branches.iter().filter(|range| !range.is_empty()).collect();
+
+        // a gltf mesh needs at least one primitive
+        if !primitive_branches.is_empty() {
+            let position_accessor = builder.add_vec3s(&local_vertices, true);
+            let normal_accessor = builder.add_vec3s(&plant.normals, false);
+            let index_view = builder.add_indices(&plant.indices);
+
+            let primitives: Vec<Value> = primitive_branches.iter().map(|range| {
+                let index_accessor = builder.add_index_range(index_view, range.start, range.len());
+                json!({
+                    "attributes": {"POSITION": position_accessor, "NORMAL": normal_accessor},
+                    "indices": index_accessor,
+                    "material": 0,
+                })
+            }).collect();
+
+            meshes.push(json!({"name": format!("plant_{}", i), "primitives": primitives}));
+            node["mesh"] = json!(meshes.len() - 1);
+        }
+
+        nodes.push(node);
+    }
+
+    if let Some(terrain) = terrain {
+        if !terrain.indices.is_empty() {
+            let position_accessor = builder.add_vec3s(&terrain.vertices, true);
+            let normal_accessor = builder.add_vec3s(&terrain.normals, false);
+            let index_view = builder.add_indices(&terrain.indices);
+            let index_accessor = builder.add_index_range(index_view, 0, terrain.indices.len());
+
+            meshes.push(json!({
+                "name": "terrain",
+                "primitives": [{
+                    "attributes": {"POSITION": position_accessor, "NORMAL": normal_accessor},
+                    "indices": index_accessor,
+                    "material": 1,
+                }],
+            }));
+            nodes.push(json!({"name": "terrain", "mesh": meshes.len() - 1}));
+        }
+    }
+
+    let materials: Vec<Value> = [BARK_MATERIAL, TERRAIN_MATERIAL].iter().map(|(name, colour)| json!({
+        "name": name,
+        "pbrMetallicRoughness": {
+            "baseColorFactor": [colour[0], colour[1], colour[2], 1.0],
+            "metallicFactor": 0.0,
+            "roughnessFactor": 1.0,
+        },
+    })).collect();
+
+    let mut document = json!({
+        "asset": {"version": "2.0", "generator": "synthetic silviculture"},
+        "scene": 0,
+        "scenes": [{"nodes": (0..nodes.len()).collect::<Vec<usize>>()}],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "accessors": builder.accessors,
+        "bufferViews": builder.buffer_views,
+    });
+    if !builder.buffer.is_empty() {
+        document["buffers"] = json!([{"byteLength": builder.buffer.len()}]);
+    }
+
+    // gltf doesn't allow empty arrays, so with no nodes the scene is left empty
+    for key in ["nodes", "meshes", "accessors", "bufferViews"] {
+        if document[key].as_array().map(|array| array.is_empty()).unwrap_or(false) {
+            document.as_object_mut().unwrap().remove(key);
+        }
+    }
+    if document.get("nodes").is_none() {
+        document["scenes"][0].as_object_mut().unwrap().remove("nodes");
+    }
+
+    // chunks must be 4 byte aligned, json is padded with spaces and the binary with zeros
+    let mut json_chunk = serde_json::to_vec(&document)?;
+    while !json_chunk.len().is_multiple_of(4) {json_chunk.push(b' ');}
+    let mut bin_chunk = builder.buffer;
+    while !bin_chunk.len().is_multiple_of(4) {bin_chunk.push(0);}
+
+    let mut total_length = 12 + 8 + json_chunk.len();
+    if !bin_chunk.is_empty() {total_length += 8 + bin_chunk.len();}
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json_chunk.len() as u32).to_le_bytes())?;
+    writer.write_all(&JSON_CHUNK_TYPE.to_le_bytes())?;
+    writer.write_all(&json_chunk)?;
+
+    if !bin_chunk.is_empty() {
+        writer.write_all(&(bin_chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(&BIN_CHUNK_TYPE.to_le_bytes())?;
+        writer.write_all(&bin_chunk)?;
+    }
+
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod gltf_tests {
+    use super::*;
+
+    fn test_plant() -> (PlantMesh, PlantDetails) {
+        let plant = PlantMesh {
+            position: [1.0, 0.0, 1.0],
+            vertices: vec![[1.0, 0.0, 1.0], [2.0, 0.0, 1.0], [1.0, 1.0, 1.0], [1.0, 2.0, 1.0]],
+            normals: vec![[0.0, 0.0, 1.0]; 4],
+            indices: vec![0, 1, 2, 2, 1, 3],
+            branches: vec![0..3, 3..6],
+        };
+        let details = PlantDetails {species: Some(2), age: 4.0, climate_adaption: 0.5, vigor: 1.5};
+        (plant, details)
+    }
+
+    /// splits the glb into its header and the json document
+    fn read_glb(data: &[u8]) -> ([u32; 3], Value) {
+        let read_u32 = |i: usize| u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        let header = [read_u32(0), read_u32(4), read_u32(8)];
+        let json_length = read_u32(12) as usize;
+        let document = serde_json::from_slice(&data[20..(20 + json_length)]).unwrap();
+        (header, document)
+    }
+
+    #[test]
+    fn glb_header_test() {
+        let mut data: Vec<u8> = Vec::new();
+        write_glb_data(&mut data, &[test_plant()], None).unwrap();
+        let (header, _) = read_glb(&data);
+
+        assert_eq!(header, [GLB_MAGIC, GLB_VERSION, data.len() as u32]);
+        assert_eq!(data.len() % 4, 0);
+    }
+
+    #[test]
+    fn plant_nodes_test() {
+        let mut data: Vec<u8> = Vec::new();
+        write_glb_data(&mut data, &[test_plant()], None).unwrap();
+        let (_, document) = read_glb(&data);
+
+        let node = &document["nodes"][0];
+        assert_eq!(node["translation"], json!([1.0, 0.0, 1.0]));
+        assert_eq!(node["extras"], json!({"species": 2, "age": 4.0, "climate_adaption": 0.5, "vigor": 1.5}));
+
+        // one primitive per branch
+        let primitives = document["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(primitives.len(), 2);
+
+        // vertices are relative to the plant
+        let position_accessor = primitives[0]["attributes"]["POSITION"].as_u64().unwrap() as usize;
+        assert_eq!(document["accessors"][position_accessor]["min"], json!([0.0, 0.0, 0.0]));
+        assert_eq!(document["accessors"][position_accessor]["max"], json!([1.0, 2.0, 0.0]));
+    }
+
+    #[test]
+    fn empty_scene_test() {
+        let mut data: Vec<u8> = Vec::new();
+        write_glb_data(&mut data, &[], None).unwrap();
+        let (header, document) = read_glb(&data);
+
+        assert_eq!(header[2], data.len() as u32);
+        assert_eq!(document["scenes"], json!([{}]));
+        for key in ["nodes", "meshes", "accessors", "bufferViews", "buffers"] {
+            assert!(document.get(key).is_none(), "empty `{}` should be left out", key);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    fs::File,
+    io::{self, Write, BufWriter},
+    path::Path,
+};
+use bevy_ecs::prelude::*;
+use image::{RgbImage, Rgb};
+use super::super::{LightCellData, super::{
+    environment::{
+        light_cells::{LightCells, LightModel},
+        ground_light::GroundLight,
+    },
+    maths::vector_three::Vector3,
+}};
+
+
+const LIGHT_GRID_HEADER: &str = "x,y,z,shadow_volume,light";
+/// the colours of the heatmap from no light to full light, evenly spaced
+const HEATMAP_COLOURS: [[f32; 3]; 5] = [
+    [0.0, 0.0, 0.02],
+    [0.34, 0.06, 0.43],
+    [0.73, 0.21, 0.33],
+    [0.98, 0.55, 0.04],
+    [0.99, 1.0, 0.64],
+];
+
+
+/// samples the light at a position in m with the light model of the simulation, which is chosen once when the app is built
+pub(crate) type LightSampler = fn(&World, &LightCells, Vector3) -> f32;
+
+/// A dense box of light cells, the x coordinate changes fastest, then z, then y
+#[derive(Clone, Debug)]
+pub(crate) struct LightGrid {
+    /// the lowest corner of the grid in world space
+    origin: [f32; 3],
+    cell_size: f32,
+    /// the number of cells along x, y and z
+    dimensions: [usize; 3],
+    shadow_volumes: Vec<f32>,
+    light: Vec<f32>,
+}
+
+
+/// samples the light at a position in m with the light model L, used as the LightSampler of an app using L
+pub(crate) fn sample_light<L: LightModel>(world: &World, cells: &LightCells, position: Vector3) -> f32 {
+    world.resource::<L>().get_light_at(cells, position)
+}
+
+/// returns the light reaching a position in m
+pub(crate) fn light_at(world: &World, sampler: LightSampler, position: Vector3) -> f32 {
+    sampler(world, world.resource::<LightCells>(), position)
+}
+
+/// returns every light cell and the light reaching it, ordered by y then z then x
+pub(crate) fn light_cell_output(world: &World, sampler: LightSampler) -> Vec<LightCellData> {
+    let cells = world.resource::<LightCells>();
+    let cell_size = cells.size();
+
+    let mut ids: Vec<_> = cells.cells().collect();
+    ids.sort_by_key(|(id, _)| (id.y, id.z, id.x));
+    ids.into_iter().map(|(id, shadow_volume)| {
+        let position = (Vector3::from([id.x, id.y, id.z]) + Vector3::new(0.5, 0.5, 0.5)) * cell_size;
+        LightCellData {position: position.into(), shadow_volume, light: sampler(world, cells, position)}
+    }).collect()
+}
+
+/// samples a single layer of light cells at a height in m, covering the bounds of the light cells
+///
+/// Returns none if the light cells are not bounded and there are no cells yet
+pub(crate) fn light_slice(world: &World, sampler: LightSampler, height: f32) -> Option<LightGrid> {
+    let cells = world.resource::<LightCells>();
+    let y = (height / cells.size()).floor() as i32;
+    let ((min_x, max_x), (min_z, max_z)) = cells.horizontal_bounds().or_else(|| {
+        cells.occupied_bounds().map(|(min, max)| ((min.x, max.x), (min.z, max.z)))
+    })?;
+    Some(light_grid(world, sampler, [min_x, y, min_z], [max_x, y, max_z]))
+}
+
+/// samples every light cell in the box around the occupied cells, returns none if there are no cells
+pub(crate) fn light_volume(world: &World, sampler: LightSampler) -> Option<LightGrid> {
+    let (min, max) = world.resource::<LightCells>().occupied_bounds()?;
+    Some(light_grid(world, sampler, min.into(), max.into()))
+}
+
+/// returns the light reaching the ground as a single layer grid, or none if the ground light isn't enabled
+pub(crate) fn ground_light_output(world: &World) -> Option<LightGrid> {
+    let ground_light = world.get_resource::<GroundLight>()?;
+    let (width, depth) = ground_light.dimensions();
+    Some(LightGrid {
+        origin: [ground_light.origin().0, 0.0, ground_light.origin().1],
+        cell_size: ground_light.size(),
+        dimensions: [width, 1, depth],
+        shadow_volumes: vec![0.0; width * depth],
+        light: ground_light.light().to_vec(),
+    })
+}
+
+/// writes a layer of the light grid to a png heatmap with a pixel per cell, dark is shaded and bright is lit
+///
+/// +x is to the right and +z is down, so north is at the top
+pub(crate) fn write_light_slice_png(path: &Path, grid: &LightGrid) -> io::Result<()> {
+    let [width, _, depth] = grid.dimensions;
+    let image = RgbImage::from_fn(width as u32, depth as u32, |x, z| {
+        Rgb(heatmap_colour(grid.light[z as usize * width + x as usize]))
+    });
+    image.save(path).map_err(io::Error::other)
+}
+
+/// writes every cell of the light grid to a csv file, with the centre of the cell in world space
+pub(crate) fn write_light_grid_csv(path: &Path, grid: &LightGrid) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_light_grid_csv_data(&mut file, grid)?;
+    file.flush()
+}
+
+/// writes the light grid to a legacy vtk file as structured points, with the shadow volume and light as cell data
+pub(crate) fn write_light_grid_vtk(path: &Path, grid: &LightGrid) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_light_grid_vtk_data(&mut file, grid)?;
+    file.flush()
+}
+
+
+/// samples the light in every cell between the lowest and highest cell coordinates given, including both
+fn light_grid(world: &World, sampler: LightSampler, min: [i32; 3], max: [i32; 3]) -> LightGrid {
+    let cells = world.resource::<LightCells>();
+    let cell_size = cells.size();
+    let dimensions = [0, 1, 2].map(|axis| (max[axis] - min[axis] + 1).max(0) as usize);
+
+    let mut grid = LightGrid {
+        origin: min.map(|value| value as f32 * cell_size),
+        cell_size,
+        dimensions,
+        shadow_volumes: Vec::with_capacity(dimensions.iter().product()),
+        light: Vec::with_capacity(dimensions.iter().product()),
+    };
+    for y in min[1]..=max[1] {
+        for z in min[2]..=max[2] {
+            for x in min[0]..=max[0] {
+                let position = (Vector3::from([x, y, z]) + Vector3::new(0.5, 0.5, 0.5)) * cell_size;
+                grid.shadow_volumes.push(cells.get_cell_volume([x, y, z]).unwrap_or(0.0));
+                grid.light.push(sampler(world, cells, position));
+            }
+        }
+    }
+    grid
+}
+
+
+/// returns the colour of a light value from 0 to 1, blending between the heatmap colours
+fn heatmap_colour(light: f32) -> [u8; 3] {
+    let position = light.clamp(0.0, 1.0) * (HEATMAP_COLOURS.len() - 1) as f32;
+    let index = (position.floor() as usize).min(HEATMAP_COLOURS.len() - 2);
+    let blend = position - index as f32;
+    let (low, high) = (HEATMAP_COLOURS[index], HEATMAP_COLOURS[index + 1]);
+    [0, 1, 2].map(|i| ((low[i] + (high[i] - low[i]) * blend) * 255.0).round() as u8)
+}
+
+fn write_light_grid_csv_data(writer: &mut impl Write, grid: &LightGrid) -> io::Result<()> {
+    writeln!(writer, "{}", LIGHT_GRID_HEADER)?;
+    let [width, _, depth] = grid.dimensions;
+    for (index, (shadow_volume, light)) in grid.shadow_volumes.iter().zip(grid.light.iter()).enumerate() {
+        let (x, z, y) = (index % width, (index / width) % depth, index / (width * depth));
+        let centre = |origin: f32, i: usize| origin + (i as f32 + 0.5) * grid.cell_size;
+        writeln!(
+            writer, "{},{},{},{},{}",
+            centre(grid.origin[0], x), centre(grid.origin[1], y), centre(grid.origin[2], z), shadow_volume, light,
+        )?;
+    }
+    Ok(())
+}
+
+fn write_light_grid_vtk_data(writer: &mut impl Write, grid: &LightGrid) -> io::Result<()> {
+    // vtk orders cells with x changing fastest, then y, then z
+    let [width, height, depth] = grid.dimensions;
+    let vtk_order = |values: &Vec<f32>| {
+        let mut ordered = Vec::with_capacity(values.len());
+        for z in 0..depth {
+            for y in 0..height {
+                for x in 0..width {
+                    ordered.push(values[(y * depth + z) * width + x]);
+                }
+            }
+        }
+        ordered
+    };
+
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "light cells")?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET STRUCTURED_POINTS")?;
+    writeln!(writer, "DIMENSIONS {} {} {}", width + 1, height + 1, depth + 1)?;
+    writeln!(writer, "ORIGIN {} {} {}", grid.origin[0], grid.origin[1], grid.origin[2])?;
+    writeln!(writer, "SPACING {} {} {}", grid.cell_size, grid.cell_size, grid.cell_size)?;
+    writeln!(writer, "CELL_DATA {}", width * height * depth)?;
+    for (name, values) in [("shadow_volume", &grid.shadow_volumes), ("light", &grid.light)] {
+        writeln!(writer, "SCALARS {} float 1", name)?;
+        writeln!(writer, "LOOKUP_TABLE default")?;
+        for value in vtk_order(values) {
+            writeln!(writer, "{}", value)?;
+        }
+    }
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod light_map_tests {
+    use super::*;
+
+    /// a grid two cells wide, one high and two deep, with the shade in the first cell
+    fn test_grid() -> LightGrid {
+        LightGrid {
+            origin: [-1.0, 2.0, 0.0],
+            cell_size: 0.5,
+            dimensions: [2, 1, 2],
+            shadow_volumes: vec![2.0, 0.0, 0.0, 0.0],
+            light: vec![0.25, 1.0, 0.5, 1.0],
+        }
+    }
+
+    #[test]
+    fn heatmap_colour_test() {
+        assert_eq!(heatmap_colour(0.0), [0, 0, 5]);
+        assert_eq!(heatmap_colour(1.0), [252, 255, 163]);
+        assert_eq!(heatmap_colour(2.0), heatmap_colour(1.0));
+        let brightness = |light: f32| heatmap_colour(light).iter().map(|channel| *channel as u32).sum::<u32>();
+        assert!(brightness(0.6) > brightness(0.3));
+    }
+
+    #[test]
+    fn light_grid_files_test() {
+        let mut csv: Vec<u8> = Vec::new();
+        write_light_grid_csv_data(&mut csv, &test_grid()).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines, vec![LIGHT_GRID_HEADER, "-0.75,2.25,0.25,2,0.25", "-0.25,2.25,0.25,0,1", "-0.75,2.25,0.75,0,0.5", "-0.25,2.25,0.75,0,1"]);
+
+        let mut vtk: Vec<u8> = Vec::new();
+        write_light_grid_vtk_data(&mut vtk, &test_grid()).unwrap();
+        let vtk = String::from_utf8(vtk).unwrap();
+        assert!(vtk.contains("DIMENSIONS 3 2 3\n"));
+        assert!(vtk.contains("CELL_DATA 4\n"));
+        assert!(vtk.ends_with("SCALARS light float 1\nLOOKUP_TABLE default\n0.25\n1\n0.5\n1\n"));
+    }
+}
@@ -0,0 +1,21 @@
+//! Writes plant and terrain meshes, and the light cells, to files that can be opened in other programs
+//!
+//! The light cells are sampled here too, with the light model the app was built with
+mod obj;
+mod gltf;
+mod skeleton;
+mod statistics;
+mod light_map;
+
+pub(crate) use obj::write_obj;
+pub(crate) use gltf::write_glb;
+pub(crate) use skeleton::write_skeletons;
+pub(crate) use statistics::write_statistics_csv;
+pub(crate) use light_map::{
+    LightSampler, LightGrid, sample_light, light_at, light_cell_output, light_slice, light_volume, ground_light_output,
+    write_light_slice_png, write_light_grid_csv, write_light_grid_vtk,
+};
+
+
+const BARK_MATERIAL: (&str, [f32; 3]) = ("bark", [0.36, 0.25, 0.2]);
+const TERRAIN_MATERIAL: (&str, [f32; 3]) = ("terrain", [0.3, 0.5, 0.2]);
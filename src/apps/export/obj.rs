@@ -0,0 +1,137 @@
+use std::{
+    fs::File,
+    io::{self, Write, BufWriter},
+    path::Path,
+};
+use super::super::{PlantMesh, TerrainMesh};
+
+
+use super::{BARK_MATERIAL, TERRAIN_MATERIAL};
+
+
+/// writes the plants and terrain to a wavefront obj file, with the materials in an mtl file next to it
+///
+/// Each plant is written as its own object named "plant_{index}", the terrain is named "terrain"
+pub(crate) fn write_obj(path: &Path, plants: &[PlantMesh], terrain: Option<&TerrainMesh>) -> io::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path.file_name().and_then(|name| name.to_str()).unwrap_or("materials.mtl");
+
+    let mut obj_file = BufWriter::new(File::create(path)?);
+    write_obj_data(&mut obj_file, mtl_name, plants, terrain)?;
+    obj_file.flush()?;
+
+    let mut mtl_file = BufWriter::new(File::create(&mtl_path)?);
+    write_mtl_data(&mut mtl_file)?;
+    mtl_file.flush()
+}
+
+
+/// writes the obj data to the given writer, referencing the given material library
+fn write_obj_data(
+    writer: &mut impl Write,
+    mtl_name: &str,
+    plants: &[PlantMesh],
+    terrain: Option<&TerrainMesh>,
+) -> io::Result<()> {
+    writeln!(writer, "# synthetic silviculture export")?;
+    writeln!(writer, "mtllib {}", mtl_name)?;
+
+    // obj indices start at 1 and are shared between all objects in the file
+    let mut index_offset: u32 = 1;
+
+    for (i, plant) in plants.iter().enumerate() {
+        writeln!(writer, "o plant_{}", i)?;
+        writeln!(writer, "g plant_{}", i)?;
+        write_object(writer, BARK_MATERIAL.0, &plant.vertices, &plant.normals, &plant.indices, index_offset)?;
+        index_offset += plant.vertices.len() as u32;
+    }
+
+    if let Some(terrain) = terrain {
+        writeln!(writer, "o terrain")?;
+        writeln!(writer, "g terrain")?;
+        write_object(writer, TERRAIN_MATERIAL.0, &terrain.vertices, &terrain.normals, &terrain.indices, index_offset)?;
+    }
+
+    Ok(())
+}
+
+
+/// writes the vertices, normals and faces of a single object
+fn write_object(
+    writer: &mut impl Write,
+    material: &str,
+    vertices: &[[f32; 3]],
+    normals: &[[f32; 3]],
+    indices: &[u32],
+    index_offset: u32,
+) -> io::Result<()> {
+    for vertex in vertices.iter() {
+        writeln!(writer, "v {} {} {}", vertex[0], vertex[1], vertex[2])?;
+    }
+    for normal in normals.iter() {
+        writeln!(writer, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+    }
+
+    writeln!(writer, "usemtl {}", material)?;
+    for face in indices.chunks_exact(3) {
+        let (a, b, c) = (face[0] + index_offset, face[1] + index_offset, face[2] + index_offset);
+        writeln!(writer, "f {}//{} {}//{} {}//{}", a, a, b, b, c, c)?;
+    }
+
+    Ok(())
+}
+
+
+/// writes the materials used by the obj file
+fn write_mtl_data(writer: &mut impl Write) -> io::Result<()> {
+    for (name, colour) in [BARK_MATERIAL, TERRAIN_MATERIAL] {
+        writeln!(writer, "newmtl {}", name)?;
+        writeln!(writer, "Kd {} {} {}", colour[0], colour[1], colour[2])?;
+        writeln!(writer, "Ka 0 0 0")?;
+        writeln!(writer, "Ks 0 0 0")?;
+        writeln!(writer, "d 1")?;
+        writeln!(writer, "illum 1")?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)] // the test meshes have a single branch
+mod obj_tests {
+    use super::*;
+
+    fn triangle() -> (Vec<[f32; 3]>, Vec<[f32; 3]>, Vec<u32>) {
+        (vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]], vec![[0.0, 1.0, 0.0]; 3], vec![0, 2, 1])
+    }
+
+    #[test]
+    fn object_names_test() {
+        let (vertices, normals, indices) = triangle();
+        let plant = PlantMesh {position: [0.0; 3], vertices: vertices.clone(), normals: normals.clone(), indices: indices.clone(), branches: vec![0..3]};
+        let terrain = TerrainMesh {vertices, normals, indices};
+
+        let mut data: Vec<u8> = Vec::new();
+        write_obj_data(&mut data, "test.mtl", &[plant.clone(), plant], Some(&terrain)).unwrap();
+        let text = String::from_utf8(data).unwrap();
+
+        let objects: Vec<&str> = text.lines().filter(|line| line.starts_with("o ")).collect();
+        assert_eq!(objects, vec!["o plant_0", "o plant_1", "o terrain"]);
+    }
+
+    #[test]
+    fn face_index_offset_test() {
+        let (vertices, normals, indices) = triangle();
+        let plant = PlantMesh {position: [0.0; 3], vertices, normals, indices, branches: vec![0..3]};
+
+        let mut data: Vec<u8> = Vec::new();
+        write_obj_data(&mut data, "test.mtl", &[plant.clone(), plant], None).unwrap();
+        let text = String::from_utf8(data).unwrap();
+
+        let faces: Vec<&str> = text.lines().filter(|line| line.starts_with("f ")).collect();
+        assert_eq!(faces, vec!["f 1//1 3//3 2//2", "f 4//4 6//6 5//5"]);
+    }
+}
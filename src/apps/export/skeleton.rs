@@ -0,0 +1,17 @@
+use std::{
+    fs::File,
+    io::{self, Write, BufWriter},
+    path::Path,
+};
+use serde_json::json;
+use super::super::PlantSkeleton;
+
+
+/// writes the plant skeletons to a json file, along with the number of years the simulation has run for
+///
+/// The file is an object with "years" and "plants", each plant has a position, a list of nodes and a list of edges between them
+pub(crate) fn write_skeletons(path: &Path, skeletons: &Vec<PlantSkeleton>, years: f32) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(&mut file, &json!({"years": years, "plants": skeletons}))?;
+    file.flush()
+}
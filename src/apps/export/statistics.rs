@@ -0,0 +1,69 @@
+use std::{
+    fs::File,
+    io::{self, Write, BufWriter},
+    path::Path,
+};
+use super::super::SpeciesStatistics;
+
+
+const STATISTICS_HEADER: &str = "step,years,species,name,count,mean_age,basal_area,branch_count,canopy_volume,mean_climate_adaption,deaths,births";
+
+
+/// writes the statistics to a csv file with a row per species per step
+///
+/// Plants without a known species have an empty species column, and species without a name have an empty name column
+pub(crate) fn write_statistics_csv(path: &Path, rows: &[SpeciesStatistics]) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    write_statistics_data(&mut file, rows)?;
+    file.flush()
+}
+
+
+fn write_statistics_data(writer: &mut impl Write, rows: &[SpeciesStatistics]) -> io::Result<()> {
+    writeln!(writer, "{}", STATISTICS_HEADER)?;
+    for row in rows.iter() {
+        let species = row.species.map(|index| index.to_string()).unwrap_or_default();
+        let name = row.name.as_deref().map(csv_text).unwrap_or_default();
+        writeln!(
+            writer, "{},{},{},{},{},{},{},{},{},{},{},{}",
+            row.step, row.years, species, name, row.count, row.mean_age, row.basal_area,
+            row.branch_count, row.canopy_volume, row.mean_climate_adaption, row.deaths, row.births,
+        )?;
+    }
+    Ok(())
+}
+
+/// quotes text that contains a comma, quote or new line, doubling any quotes inside it
+fn csv_text(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+
+
+#[cfg(test)]
+mod statistics_tests {
+    use super::*;
+
+    #[test]
+    fn statistics_csv_test() {
+        let row = SpeciesStatistics {
+            step: 3, years: 1.5, species: Some(1), name: Some("oak, \"english\"".to_string()), count: 2, mean_age: 1.25, basal_area: 0.5, branch_count: 7,
+            canopy_volume: 12.0, mean_climate_adaption: 0.75, deaths: 1, births: 0,
+        };
+        let unknown = SpeciesStatistics {species: None, name: None, ..row.clone()};
+
+        let mut data: Vec<u8> = Vec::new();
+        write_statistics_data(&mut data, &[row, unknown]).unwrap();
+        let lines: Vec<String> = String::from_utf8(data).unwrap().lines().map(|line| line.to_string()).collect();
+
+        assert_eq!(lines, vec![
+            STATISTICS_HEADER.to_string(),
+            "3,1.5,1,\"oak, \"\"english\"\"\",2,1.25,0.5,7,12,0.75,1,0".to_string(),
+            "3,1.5,,,2,1.25,0.5,7,12,0.75,1,0".to_string(),
+        ]);
+    }
+}
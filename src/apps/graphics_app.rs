@@ -15,6 +15,7 @@ use super::super::{
     environment::{
         terrain::*,
         light_cells::*,
+        climate::ClimateSeriesError,
         params::*,
     },
     maths::{
@@ -30,7 +31,7 @@ use super::super::{
     },
     debug::*,
 };
-use super::*;
+use super::{*, simulation_core::{SimulationCore, SimulationSet}, scenario::{Scenario, ScenarioError}};
 use egui_winit_vulkano::Gui;
 use winit::{
     event::{Event, WindowEvent, ElementState, VirtualKeyCode},
@@ -59,30 +60,16 @@ use vulkano::{
 
 pub struct GraphicsAppBuilder {
     // features
-    has_terrain: bool,
     has_gui: bool,
     output: u32,
 
     // settings
     window_title: String,
-    terrain_settings: Option<(f32, Vector3, Option<(u32, f32, String)>)>, // size, centre, verts per side, height mult, path
     terrain_graphics_settings: Option<([f32; 3], [f32; 3], f32, f32)>,
     light: Option<([f32; 3], f32)>,
     branch_render_settings: Option<(u32, bool)>,
 
-    gravity_strength: Option<f32>,
-    time_step: Option<f32>,
-    cell_settings: Option<(u32, f32)>,
-    plant_death_rate: Option<f32>,
-    environmental_params: Option<(f32, f32, f32)>, // temp at y=0, temp falloff, moisture
-
-    prototype_conditions: Option<(Vec<(f32, f32)>, f32, f32)>,
-    prototypes: Option<Vec<(f32, Vec<Vec<u32>>,  Vec<[f32; 3]>)>>,
-    start_plants: u32,
-    plant_species: Option<Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))>>,
-    has_seeding: bool,
-
-
+    core: SimulationCore,
 }
 
 
@@ -127,27 +114,20 @@ impl GraphicsTreeApp {
     /// 
     /// Please note that if no terrain is specified, plants will not be able to reproduce and initial plants will be spawned on a 50m by 50m square at y=0
     pub fn new(window_title: String) -> GraphicsAppBuilder{
+        let mut core = SimulationCore::new();
+        // the fixed update runs 10 times a second
+        core.set_time_step(DEFAULT_TIMESTEP / 10.0);
+
         GraphicsAppBuilder {
-            has_terrain: false,
             has_gui: false,
             output: 0,
 
             window_title,
-            terrain_settings: None,
             terrain_graphics_settings: None,
-            gravity_strength: None,
             light: None,
             branch_render_settings: None,
-            prototypes: None,
-            prototype_conditions: None,
-            time_step: None,
-            cell_settings: None,
-            plant_death_rate: None,
-            start_plants: 0,
-
-            environmental_params: None,
-            plant_species: None,
-            has_seeding: false
+
+            core,
         }
     }
 
@@ -201,9 +181,18 @@ impl GraphicsTreeApp {
                         }
                         WindowEvent::CloseRequested => {
                             *control_flow = ControlFlow::Exit;
+                            let (polygon_directions, flat_shaded) = {
+                                let branch_graphics_res = world.resource::<BranchGraphicsResources>();
+                                (branch_graphics_res.polygon_vectors.clone(), branch_graphics_res.flat_shaded)
+                            };
                             match self.output {
                                 OutputType::Absent => {}
-                                _ => {output_ref.replace(TreeAppOutput{data: Some(data_output(&mut world)), meshes: None});}
+                                OutputType::Data => {output_ref.replace(TreeAppOutput{data: Some(data_output(&mut world)), meshes: None});}
+                                OutputType::Meshes => {output_ref.replace(TreeAppOutput{data: None, meshes: Some(mesh_output(&mut world, &polygon_directions, flat_shaded))});}
+                                OutputType::All => {output_ref.replace(TreeAppOutput{
+                                    data: Some(data_output(&mut world)),
+                                    meshes: Some(mesh_output(&mut world, &polygon_directions, flat_shaded))
+                                });}
                             }
                         }
                         WindowEvent::KeyboardInput {
@@ -386,20 +375,18 @@ impl GraphicsTreeApp {
 
 
 impl GraphicsAppBuilder {
-    /// features
+    // features
 
     /// allows the app to draw flat terrain, overrides previous terrain settings
     pub fn with_flat_terrain(&mut self, size: f32, centre: [f32; 3], colour: [f32; 3]) -> &mut GraphicsAppBuilder {
-        self.has_terrain = true;
-        self.terrain_settings = Some((size, centre.into(), None));
+        self.core.with_flat_terrain(size, centre);
         self.terrain_graphics_settings = Some((colour, [0.0, 0.0, 0.0], 0.0, 0.0));
         self
     }
 
     /// allows the app to draw heightmap based terrain, overrides previous terrain settings
     pub fn with_heightmap_terrain(&mut self, size: f32, centre: [f32; 3], verts_per_side: u32, height_scale: f32, heightmap_path: &str, grass_colour: [f32; 3], rock_colour: [f32; 3], grass_slope_threshold: f32, grass_blend_amount: f32) -> &mut GraphicsAppBuilder{
-        self.has_terrain = true;
-        self.terrain_settings = Some((size, centre.into(), Some((verts_per_side, height_scale, heightmap_path.into()))));
+        self.core.with_heightmap_terrain(size, centre, verts_per_side, height_scale, heightmap_path);
         self.terrain_graphics_settings = Some((grass_colour, rock_colour, grass_slope_threshold, grass_blend_amount));
         self
     }
@@ -440,28 +427,59 @@ impl GraphicsAppBuilder {
         self
     }
 
-    /// simulation settings
+    // simulation settings
     
     /// sets the strength of gravity and the stength, a negative strength represents phototropism, a positive gravitropism
     /// 
     /// the default value is 1
     pub fn set_gravity(&mut self, strength: f32) -> &mut GraphicsAppBuilder {
-        self.gravity_strength = Some(strength);
+        self.core.set_gravity(strength);
         self
     }
 
     /// sets the branch presets used for the simulation, overrides the default set of branches used
-    pub fn set_branch_presets(&mut self, prototypes: Vec<(f32, Vec<Vec<u32>>, Vec<[f32; 3]>)>, conditions: (Vec<(f32, f32)>, f32, f32)) -> &mut GraphicsAppBuilder {
-        self.prototypes = Some(prototypes);
-        self.prototype_conditions = Some(conditions);
+    ///
+    /// Returns an error if there are no prototypes, one of them is invalid, or there isn't a position for each of them
+    pub fn set_branch_presets(&mut self, prototypes: Vec<PrototypePreset>, conditions: PrototypeConditions) -> Result<&mut GraphicsAppBuilder, PrototypeError> {
+        self.core.set_branch_presets(prototypes, conditions)?;
+        Ok(self)
+    }
+
+    /// sets how many of the nearest branch prototypes are blended together to make new branches, defaults to 1 which means no blending
+    /// 
+    /// Only prototypes with the same node counts as the nearest one are blended, differently shaped ones are left out rather than resampled.
+    /// Blending has no effect where the nearest prototype has a shape that none of the others share
+    pub fn set_prototype_blending(&mut self, count: u32) -> &mut GraphicsAppBuilder {
+        self.core.set_prototype_blending(count);
+
         self
     }
 
     /// sets the plant species used for the simulation, overrides default set of plant species used
     /// 
     /// Plant species are used for initial plant spawning and spawning of new plants without seeding
-    pub fn set_plant_species(&mut self, species: Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))>) -> &mut GraphicsAppBuilder {
-        self.plant_species = Some(species);
+    pub fn set_plant_species(&mut self, species: Vec<SpeciesPreset>) -> &mut GraphicsAppBuilder {
+        self.core.set_plant_species(species);
+
+        self
+    }
+
+
+    /// names the plant species in the order they were set, the names are written in the statistics
+    ///
+    /// Setting the plant species again removes the names, species presets are named after their preset
+    pub fn set_species_names(&mut self, names: Vec<String>) -> &mut GraphicsAppBuilder {
+        self.core.set_species_names(names);
+
+        self
+    }
+
+    /// sets the plant species used for the simulation to the named species presets: "oak", "birch", "pine" or "shrub"
+    /// 
+    /// Without any species or branch presets, all the species presets and their branch prototypes are used.
+    /// Panics if a name isn't one of the presets
+    pub fn set_preset_species(&mut self, names: &[&str]) -> &mut GraphicsAppBuilder {
+        self.core.set_preset_species(names);
 
         self
     }
@@ -469,7 +487,7 @@ impl GraphicsAppBuilder {
 
     /// set how many plants are used in the simulation, currently changes nothing
     pub fn set_initial_plant_num(&mut self, num: u32) -> &mut GraphicsAppBuilder {
-        self.start_plants = num;
+        self.core.set_initial_plant_num(num);
 
         self
     }
@@ -477,7 +495,7 @@ impl GraphicsAppBuilder {
 
     /// sets the size of the cell grid used to shadow distribution, the default is 1m^3. And the number of cells above or below that will be checked for shadow distribution purposes
     pub fn set_shadow_cell_data(&mut self, size: f32, check_height: u32) -> &mut GraphicsAppBuilder {
-        self.cell_settings = Some((check_height, size.abs()));
+        self.core.set_shadow_cell_data(size, check_height);
 
         self
     }
@@ -487,14 +505,15 @@ impl GraphicsAppBuilder {
     /// 
     /// This is how many years will pass per second
     pub fn set_time_step(&mut self, step: f32) -> &mut GraphicsAppBuilder {
-        self.time_step = Some(step.abs());
+        // the fixed update runs 10 times a second
+        self.core.set_time_step(step / 10.0);
 
         self
     }
 
     /// sets the rate at which plants die, defaults to 1.0
     pub fn set_plant_death_rate(&mut self, rate: f32) -> &mut GraphicsAppBuilder {
-        self.plant_death_rate = Some(rate.abs());
+        self.core.set_plant_death_rate(rate);
 
         self
     }
@@ -505,7 +524,7 @@ impl GraphicsAppBuilder {
     /// - Temperature: (Degrees Celsius at a height of y=0, rate of temperature decrease away from y=0)
     /// - Moisture: Average annual precipitation, cm
     pub fn set_environmental_parameters(&mut self, temperature: (f32, f32), moisture: f32) -> &mut GraphicsAppBuilder {
-        self.environmental_params = Some((temperature.0, temperature.1, moisture));
+        self.core.set_environmental_parameters(temperature, moisture);
 
         self
     }
@@ -514,173 +533,160 @@ impl GraphicsAppBuilder {
     /// - This is disabled by default
     /// - Has no effect without initial plants
     pub fn enable_seeding(&mut self) -> &mut GraphicsAppBuilder {
-        self.has_seeding = true;
+        self.core.enable_seeding();
 
         self
     }
 
+    /// - Enables root growth below the terrain, the roots compete for soil resources which limits the vigor of their plants
+    /// - This is disabled by default
+    pub fn enable_roots(&mut self) -> &mut GraphicsAppBuilder {
+        self.core.enable_roots();
 
+        self
+    }
 
+    /// sets the side length in m of the soil cells the roots compete for resources in, defaults to 0.5 and can't be less than 0.01
+    pub fn set_root_cell_size(&mut self, cell_size: f32) -> &mut GraphicsAppBuilder {
+        self.core.set_root_cell_size(cell_size);
 
-    /// builds and returns the app for running
-    pub fn build(&mut self) -> GraphicsTreeApp{
+        self
+    }
 
-        // transform data
-        let output = {
-            if self.output == 0 {OutputType::Absent}
-            else if self.output == 1 {OutputType::Data}
-            else if self.output == 2{OutputType::Meshes}
-            else {OutputType::All}
-        };
-        
-        let gravity_strength = self.gravity_strength.unwrap_or(DEFAULT_GRAVITY_STRENGTH);
-        let time_step = self.time_step.unwrap_or(DEFAULT_TIMESTEP) / 10.0;
-        let branch_conditions = self.prototype_conditions.clone().unwrap_or(DEFAULT_BRANCH_CONTIDITIONS);
-        let branch_types = self.prototypes.clone().unwrap_or(DEFAULT_BRANCH_TYPES);
-        let cell_settings = self.cell_settings.unwrap_or(DEFAULT_CELL_SETTINGS);
-        let plant_death_rate = self.plant_death_rate.unwrap_or(DEFAULT_PLANT_DEATH_RATE);
-        let has_plants = self.start_plants > 0;
-        let plant_species = self.plant_species.clone().unwrap_or(DEFAULT_PLANT_SPECIES);
-        let environmental_params = self.environmental_params.unwrap_or(DEFAULT_ENVIRONMENTAL_PARAMS);
-        
-        let branch_sampler = BranchPrototypesSampler::create(branch_conditions.0, SAMPLER_SIZE, branch_conditions.1, branch_conditions.2);
-        let plant_species_sampler = PlantSpeciesSampler::new(plant_species, time_step);
+    /// sets the distance between root nodes in m, defaults to 0.2, and how strongly roots turn towards soil resources, defaults to 1
+    pub fn set_root_growth(&mut self, segment_length: f32, hydrotropism: f32) -> &mut GraphicsAppBuilder {
+        self.core.set_root_growth(segment_length, hydrotropism);
 
-        ///////////////// world
-        let mut world = World::new();
+        self
+    }
 
-        ///////////////// resources
-        create_gravity_resource(&mut world, [0, -1, 0], gravity_strength);
-        create_physical_age_time_step(&mut world, time_step);
+    /// sets the soil grid, which holds the moisture and nutrients under the plants
+    ///
+    /// - Cell size: the side length of each cell in m, defaults to 2 and can't be less than 0.01
+    /// - Recharge rate: the fraction of the missing moisture restored by precipitation each year, defaults to 0.5
+    /// - Uptake rate: the cm of moisture used each year for every m^3 of plant, defaults to 0.2
+    pub fn set_soil_grid(&mut self, cell_size: f32, recharge_rate: f32, uptake_rate: f32) -> &mut GraphicsAppBuilder {
+        self.core.set_soil_grid(cell_size, recharge_rate, uptake_rate);
 
-        
-        
-        world.insert_resource(BranchPrototypes::new(branch_types));
-        world.insert_resource(LightCells::new(cell_settings.0 as i32, cell_settings.1));
-        world.insert_resource(PlantDeathRate::new(plant_death_rate));
+        self
+    }
 
+    /// makes the temperature and precipitation change over time, starting from the environmental parameters
+    ///
+    /// Each setting is a pair of (temperature, precipitation):
+    /// - Seasons: how far the temperature moves either side of the annual mean over the year, and the fraction of the precipitation that does
+    /// - Noise: the standard deviation of each year's difference from the mean, for precipitation as a fraction of the mean
+    /// - Trends: the change in the mean each year, in degrees celsius and cm
+    pub fn set_climate(&mut self, seasons: (f32, f32), noise: (f32, f32), trends: (f32, f32)) -> &mut GraphicsAppBuilder {
+        self.core.set_climate(seasons, noise, trends);
 
-        let (terrain_type, plant_spawning_bounds, terrain_collider_ref) = {
-            if self.has_terrain {     
-                let settings = self.terrain_settings.clone().unwrap();
-                if settings.2.is_none() {
-                    let plant_spawn_bounds = spawn_flat_terrain(settings.0, settings.1, &mut world);
-                    (TerrainType::Flat, plant_spawn_bounds.0, plant_spawn_bounds.1)
-                }
-                else {
-                    let subsettings = settings.2.clone().unwrap();
-                    let plant_spawn_bounds = spawn_heightmap_terrain(settings.0, subsettings.0, subsettings.1, settings.1, subsettings.2, &mut world);
-                    (TerrainType::Bumpy, plant_spawn_bounds.0, plant_spawn_bounds.1)
-                }
-            }
-            else {
-                let plant_spawn_bounds = spawn_flat_terrain(DEFAULT_TERRAIN.0, DEFAULT_TERRAIN.1, &mut world);
-                (TerrainType::Absent, plant_spawn_bounds.0, plant_spawn_bounds.1)
-            }
-        };
+        self
+    }
 
+    /// replays recorded weather from a csv file instead of using a procedural climate, the simulation starts at the beginning of the first year in the file
+    ///
+    /// The rows are all (year, temperature, precipitation) or all (year, month, temperature, precipitation),
+    /// yearly rows give the annual precipitation and monthly rows the precipitation of the month, in cm
+    ///
+    /// The file is read straight away, returning an error if it can't be read or has no samples
+    pub fn set_climate_series(&mut self, path: &str) -> Result<&mut GraphicsAppBuilder, ClimateSeriesError> {
+        self.core.set_climate_series(path)?;
 
+        Ok(self)
+    }
 
+    /// lights the plants from the path of the sun at a latitude in degrees instead of from straight above, north is positive
+    ///
+    /// The sun is sampled every two hours on the given day of the year, or on the equinoxes and solstices without one
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sun_path(&mut self, latitude: f32, day_of_year: Option<f32>) -> &mut GraphicsAppBuilder {
+        self.core.set_sun_path(latitude, day_of_year);
 
-        let mut update_schedule = Schedule::new();
+        self
+    }
 
-        // spawn initial plant(s)
+    /// lights the plants from a list of directions towards the light and their weights instead of from straight above
+    ///
+    /// +x is east, +y is up and +z is south, directions that don't point up are left out
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_light_directions(&mut self, directions: Vec<([f32; 3], f32)>) -> &mut GraphicsAppBuilder {
+        self.core.set_light_directions(directions);
 
-        let mut initial_plant_data = Vec::new();
-        let mut rng = thread_rng();
+        self
+    }
 
-        for _ in 0..self.start_plants {
+    /// lights the plants with diffuse light from the whole sky instead of from straight above,
+    /// using rings of directions between the horizon and straight up with the given number of directions in each
+    ///
+    /// More directions are smoother but slower, 3 rings of 8 is a good start
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sky_dome(&mut self, rings: u32, directions_per_ring: u32) -> &mut GraphicsAppBuilder {
+        self.core.set_sky_dome(rings, directions_per_ring);
 
-            let (x, z) = (rng.gen_range(plant_spawning_bounds.1.clone()), rng.gen_range(plant_spawning_bounds.2.clone()));
+        self
+    }
 
-            let hit = terrain_collider_ref.check_ray([x, plant_spawning_bounds.0 + 5.0, z], [0, -1, 0], None).unwrap();
+    /// samples the light reaching the terrain each step in a grid of square cells with the given side length in m, at least 0.01, using the light model of the plants
+    ///
+    /// When seeding, the ground light is the chance of a seedling surviving, which shade tolerant species are less affected by
+    pub fn set_ground_light(&mut self, cell_size: f32) -> &mut GraphicsAppBuilder {
+        self.core.set_ground_light(cell_size);
 
-            initial_plant_data.push((plant_species_sampler.get_plant(environmental_params.0 + hit.hit_position.y * environmental_params.1, environmental_params.2), hit))
-        }
+        self
+    }
 
-        let mut root_ids = Vec::new();
-        for data in initial_plant_data {
-
-            if let (Some((spawn_data, climate_adapt)), hit) = data {
-
-                let root_node_id = world.spawn(BranchNodeBundle{
-                    data: BranchNodeData{
-                        thickening_factor: spawn_data.0.thickening_factor,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                }).id();
-
-                let root_branch_id = world.spawn(BranchBundle{
-                    data: BranchData {
-                        root_node: Some(root_node_id),
-                        root_position: hit.hit_position.into(),
-                        normal: hit.hit_normal,
-                        ..Default::default()
-                    },
-                    prototype: BranchPrototypeRef(branch_sampler.get_prototype_index(spawn_data.0.apical_control, branch_conditions.2)),
-                    ..Default::default()
-                }).id();
-
-                world.spawn(PlantBundle{
-                    growth_factors: spawn_data.0,
-                    data: PlantData {
-                        root_node: Some(root_branch_id),
-                        position: hit.hit_position.into(),
-                        climate_adaption: climate_adapt,
-                        ..Default::default()
-                    },
-                    plasticity_params: spawn_data.1,
-                    ..Default::default()
-                });
-
-                root_ids.push(root_branch_id);
-            }
+    /// sets the seed used for all the random parts of the simulation,
+    /// the same seed and settings will always give the same plants
+    pub fn set_seed(&mut self, seed: u64) -> &mut GraphicsAppBuilder {
+        self.core.set_seed(seed);
+
+        self
+    }
+
+
+
+
+    /// applies all the settings in a scenario, overriding any previous settings they cover
+    ///
+    /// Returns an error if a file the scenario names can't be read
+    ///
+    /// Terrain from the scenario is drawn with the default terrain colours
+    pub fn with_scenario(&mut self, scenario: &Scenario) -> Result<&mut GraphicsAppBuilder, ScenarioError> {
+        self.core.with_scenario(scenario)?;
+        if let Some(step) = scenario.simulation.time_step {self.set_time_step(step);}
+        if scenario.terrain.is_some() {
+            self.terrain_graphics_settings = Some(DEFAULT_TERRAIN_GRAPHICS);
         }
-        world.insert_resource(branch_sampler);
+
+        Ok(self)
+    }
+
+
+
+
+    /// builds and returns the app for running
+    pub fn build(&mut self) -> GraphicsTreeApp{
+
+        // transform data
+        let output = {
+            if self.output == 0 {OutputType::Absent}
+            else if self.output == 1 {OutputType::Data}
+            else if self.output == 2{OutputType::Meshes}
+            else {OutputType::All}
+        };
         
-        // plant
-        if has_plants {
+        let terrain_type = self.core.terrain_type();
 
-            if root_ids.len() == 0 {panic!("No intial plants generated")}
-            
-            // mesh queue
-            world.spawn(MeshUpdateQueue::new_from_many(root_ids, 5));
-
-            update_schedule.add_systems((
-                update_branch_bounds,
-                update_plant_bounds,
-                update_plant_intersections,
-                update_branch_intersections,
-                calculate_branch_intersection_volumes,
-                // debug_log_branches,
-                // debug_log_cells,
-                step_plant_age,
-                calculate_branch_light_exposure,
-                calculate_growth_vigor,
-                trim_branches,
-                apply_system_buffers, // this makes sure nodes and branches have been removed
-                remove_dead_connections,
-                assign_growth_rates,
-                step_physiological_age,
-            ).chain());
-
-            update_schedule.add_systems((
-                update_branch_nodes,
-                apply_system_buffers, // this makes sure new nodes are spawned
-                determine_create_new_branches,
-                apply_system_buffers, // this makes sure new branches are spawned
-                assign_thicknesses,
-                calculate_segment_lengths_and_tropism,
-                update_branch_data_buffers,
-            ).chain().after(step_physiological_age));
-
-            if self.has_seeding {
-                update_schedule.add_system((seed_plants).after(calculate_segment_lengths_and_tropism));
-            }
-    
-        }
-        else {world.spawn(MeshUpdateQueue::new(5));}
+        ///////////////// world
+        let mut world = World::new();
+        self.core.populate_world(&mut world);
+
+        let mut update_schedule = Schedule::new();
+        self.core.add_systems(&mut update_schedule);
+        update_schedule.add_system(update_branch_data_buffers.after(SimulationSet::Development));
 
 
 
@@ -734,12 +740,6 @@ impl GraphicsAppBuilder {
         startup_schedule.add_system(init_branch_mesh_buffers_res);
 
         startup_schedule.run(&mut world);
-        world.insert_resource(plant_species_sampler);
-        world.insert_resource(MoistureAndTemp {
-            moisture: environmental_params.2,
-            temp_at_zero: environmental_params.0,
-            temp_fall_off: environmental_params.1,
-        });
 
 
         GraphicsTreeApp{
@@ -1,52 +1,585 @@
-use super::{*, super::{
-    maths::vector_three::Vector3,
+use super::{*, simulation_core::SimulationCore, scenario::{Scenario, ScenarioError}, export::{LightSampler, LightGrid}, super::environment::{
+    climate::ClimateSeriesError,
+    ground_light::GroundLight,
 }};
 use bevy_ecs::prelude::*;
+use std::{io::{self, BufReader, BufWriter, Write}, path::Path, fs::File};
 
 
 
+pub struct LoopedAppBuilder {
+    // features
+    output: u32,
+
+    // settings
+    branch_mesh_settings: Option<(u32, bool)>,
+    core: SimulationCore,
+}
+
+
+/// A tree app that runs without any graphics, stepping the simulation as fast as it can
+///
+/// Useful for running simulations on machines without a gpu
 pub struct LoopedTreeApp {
+    world: World,
     update_schedule: Schedule,
     output: OutputType,
+    branch_mesh_settings: (u32, bool),
+    light_sampler: LightSampler,
 
-    // terrain_settings: (f32, Vector3, Option<(u32, f32, String)>), // size, centre, verts per side, height mult, path
-    gravity_strength: f32,
     time_step: f32,
-    cell_settings: (u32, f32),
-    plant_death_rate: f32,
+    elapsed_years: f32,
 }
 
-impl Default for LoopedTreeApp {
-    fn default() -> Self {
-        LoopedTreeApp {
-            update_schedule: Schedule::new(),
-            output: OutputType::Absent,
-
-            // terrain_settings: DEFAULT_TERRAIN,
-            gravity_strength: DEFAULT_GRAVITY_STRENGTH,
-            time_step: DEFAULT_TIMESTEP,
-            cell_settings: DEFAULT_CELL_SETTINGS,
-            plant_death_rate: DEFAULT_PLANT_DEATH_RATE,
+
+impl LoopedTreeApp {
+    /// creates a new app builder to add functionality to
+    ///
+    /// Please note that if no terrain is specified, plants will not be able to reproduce and initial plants will be spawned on a 50m by 50m square at y=0
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> LoopedAppBuilder {
+        LoopedAppBuilder {
+            output: 0,
+            branch_mesh_settings: None,
+            core: SimulationCore::new(),
+        }
+    }
+
+    /// runs the simulation for the given number of steps and returns the output
+    pub fn run_steps(&mut self, steps: u32) -> TreeAppOutput {
+        for _ in 0..steps {
+            self.update_schedule.run(&mut self.world);
+            self.elapsed_years += self.time_step;
+        }
+
+        self.get_output()
+    }
+
+    /// runs the simulation until the given number of years have passed since it started and returns the output
+    ///
+    /// Does nothing but return the output if that many years have already passed, or if the time step is zero
+    pub fn run_until(&mut self, years: f32) -> TreeAppOutput {
+        while self.elapsed_years < years && self.time_step > 0.0 {
+            self.update_schedule.run(&mut self.world);
+            self.elapsed_years += self.time_step;
+        }
+
+        self.get_output()
+    }
+
+    /// returns the number of years that have passed in the simulation
+    pub fn elapsed_years(&self) -> f32 {
+        self.elapsed_years
+    }
+
+    /// returns the number of years each step moves the simulation forward by
+    pub fn time_step(&self) -> f32 {
+        self.time_step
+    }
+
+    /// writes the plants and terrain to a wavefront obj file at the given path, and their materials to an mtl file next to it
+    ///
+    /// The plant meshes are made using the branch mesh settings of the app
+    pub fn export_obj(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let polygon_directions = create_vector_polygon(self.branch_mesh_settings.0, None);
+        let plants = mesh_output(&mut self.world, &polygon_directions, self.branch_mesh_settings.1);
+        let terrain = terrain_output(&mut self.world);
+        export::write_obj(path.as_ref(), &plants, terrain.as_ref())
+    }
+
+    /// writes the plants and terrain to a binary gltf file at the given path
+    ///
+    /// Each plant is a node with a mesh primitive for every branch, and has its species index, age, climate adaption and vigor in its extras
+    pub fn export_glb(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let polygon_directions = create_vector_polygon(self.branch_mesh_settings.0, None);
+        let plants = detailed_mesh_output(&mut self.world, &polygon_directions, self.branch_mesh_settings.1);
+        let terrain = terrain_output(&mut self.world);
+        export::write_glb(path.as_ref(), &plants, terrain.as_ref())
+    }
+
+    /// writes the skeleton of every plant to a json file at the given path
+    pub fn export_skeletons(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let skeletons = data_output(&mut self.world);
+        export::write_skeletons(path.as_ref(), &skeletons, self.elapsed_years)
+    }
+
+    /// returns how many plants, branches and branch nodes are currently in the simulation
+    pub fn population(&mut self) -> PopulationCounts {
+        population_counts(&mut self.world)
+    }
+
+    /// returns the statistics of each species after every step, in order of steps
+    pub fn statistics(&self) -> &Vec<SpeciesStatistics> {
+        &self.world.resource::<ForestStatistics>().rows
+    }
+
+    /// writes the statistics of each species after every step to a csv file at the given path
+    pub fn export_statistics(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        export::write_statistics_csv(path.as_ref(), self.statistics())
+    }
+
+    /// returns every light cell and the light reaching it from the light model of the simulation
+    ///
+    /// The cells hold the branches from the last step, so there are none before the first step
+    pub fn light_cells(&self) -> Vec<LightCellData> {
+        export::light_cell_output(&self.world, self.light_sampler)
+    }
+
+    /// returns the light reaching a position in world space from the light model of the simulation, from 0 to 1
+    pub fn light_at(&self, position: [f32; 3]) -> f32 {
+        export::light_at(&self.world, self.light_sampler, position.into())
+    }
+
+    /// writes a horizontal slice of the light cells at a height in m to a png heatmap at the given path, with a pixel per cell
+    ///
+    /// The slice covers the terrain and the margin around it, +x is to the right and +z is down
+    pub fn export_light_slice(&self, path: impl AsRef<Path>, height: f32) -> io::Result<()> {
+        match export::light_slice(&self.world, self.light_sampler, height) {
+            Some(slice) => export::write_light_slice_png(path.as_ref(), &slice),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "the light cells have no bounds to slice")),
+        }
+    }
+
+    /// writes every light cell in the box around the branches to a legacy vtk file at the given path, as structured points
+    pub fn export_light_vtk(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        export::write_light_grid_vtk(path.as_ref(), &self.light_volume()?)
+    }
+
+    /// writes every light cell in the box around the branches to a csv file at the given path, with a row per cell
+    pub fn export_light_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        export::write_light_grid_csv(path.as_ref(), &self.light_volume()?)
+    }
+
+    /// returns the light reaching the ground at a position in world space from 0 to 1, or none if the ground light isn't enabled
+    pub fn ground_light_at(&self, x: f32, z: f32) -> Option<f32> {
+        self.world.get_resource::<GroundLight>().map(|ground_light| ground_light.get_light([x, 0.0, z]))
+    }
+
+    /// writes the light reaching the ground to a png heatmap at the given path, with a pixel per ground light cell
+    ///
+    /// +x is to the right and +z is down, fails if the ground light isn't enabled
+    pub fn export_ground_light(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        match export::ground_light_output(&self.world) {
+            Some(grid) => export::write_light_slice_png(path.as_ref(), &grid),
+            None => Err(io::Error::new(io::ErrorKind::InvalidInput, "the ground light is not enabled")),
+        }
+    }
+
+    /// saves the full state of the simulation to a checkpoint file at the given path
+    pub fn save_checkpoint(&mut self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        checkpoint::save_checkpoint(&mut self.world, self.elapsed_years, &mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// replaces the state of the simulation with the one saved in the checkpoint file at the given path
+    ///
+    /// The systems that are run still come from the settings the app was built with,
+    /// so the app should be built with the same settings as the one that saved the checkpoint.
+    /// Returns an error without changing the simulation if the checkpoint was saved with a different light model
+    pub fn load_checkpoint(&mut self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let mut file = BufReader::new(File::open(path)?);
+        self.elapsed_years = checkpoint::load_checkpoint(&mut self.world, &mut file)?;
+        Ok(())
+    }
+
+    fn light_volume(&self) -> io::Result<LightGrid> {
+        export::light_volume(&self.world, self.light_sampler).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "there are no light cells to write"))
+    }
+
+    fn get_output(&mut self) -> TreeAppOutput {
+        let polygon_directions = create_vector_polygon(self.branch_mesh_settings.0, None);
+        match self.output {
+            OutputType::Absent => TreeAppOutput::default(),
+            OutputType::Data => TreeAppOutput{data: Some(data_output(&mut self.world)), meshes: None},
+            OutputType::Meshes => TreeAppOutput{data: None, meshes: Some(mesh_output(&mut self.world, &polygon_directions, self.branch_mesh_settings.1))},
+            OutputType::All => TreeAppOutput{
+                data: Some(data_output(&mut self.world)),
+                meshes: Some(mesh_output(&mut self.world, &polygon_directions, self.branch_mesh_settings.1))
+            },
         }
     }
 }
 
 
-impl LoopedTreeApp {
-    pub fn new() -> Self {
-        LoopedTreeApp::default()
+
+impl LoopedAppBuilder {
+    // features
+
+    /// allows the app to use flat terrain, overrides previous terrain settings
+    pub fn with_flat_terrain(&mut self, size: f32, centre: [f32; 3]) -> &mut LoopedAppBuilder {
+        self.core.with_flat_terrain(size, centre);
+        self
+    }
+
+    /// allows the app to use heightmap based terrain, overrides previous terrain settings
+    pub fn with_heightmap_terrain(&mut self, size: f32, centre: [f32; 3], verts_per_side: u32, height_scale: f32, heightmap_path: &str) -> &mut LoopedAppBuilder {
+        self.core.with_heightmap_terrain(size, centre, verts_per_side, height_scale, heightmap_path);
+        self
+    }
+
+    /// sets the output type from the app, 0 1 2 or 3, any other number will default to 0
+    ///
+    /// - 0 - no output
+    /// - 1 - outputs data about branch nodes: such as node position and thickness; and connection data: which nodes are connected to each other
+    /// - 2 - meshes: creates meshes for the plants and outputs the data for them: vertices, normals, positions and indices
+    /// - 3 - data and meshes, outputs the data for option 1 and 2
+    pub fn set_output_type(&mut self, mut output: u32) -> &mut LoopedAppBuilder {
+        if output > 3 {output = 0;}
+        self.output = output;
+        self
     }
 
-    pub fn reset(&mut self) -> &mut LoopedTreeApp {
-        self.update_schedule = Schedule::new();
-        self.output = OutputType::Absent;
+    /// sets the number of polygons used to construct branch meshes and if branches are flat shaded
+    ///
+    /// Defaults to 3 faces and smooth shaded, only used for mesh output
+    pub fn set_branch_mesh_settings(&mut self, faces: u32, flat_shaded: bool) -> &mut LoopedAppBuilder {
+        self.branch_mesh_settings = Some((faces.max(3), flat_shaded));
+        self
+    }
+
+    // simulation settings
+
+    /// sets the strength of gravity and the stength, a negative strength represents phototropism, a positive gravitropism
+    ///
+    /// the default value is 1
+    pub fn set_gravity(&mut self, strength: f32) -> &mut LoopedAppBuilder {
+        self.core.set_gravity(strength);
+        self
+    }
+
+    /// sets the branch presets used for the simulation, overrides the default set of branches used
+    ///
+    /// Returns an error if there are no prototypes, one of them is invalid, or there isn't a position for each of them
+    pub fn set_branch_presets(&mut self, prototypes: Vec<PrototypePreset>, conditions: PrototypeConditions) -> Result<&mut LoopedAppBuilder, PrototypeError> {
+        self.core.set_branch_presets(prototypes, conditions)?;
+        Ok(self)
+    }
+
+    /// sets how many of the nearest branch prototypes are blended together to make new branches, defaults to 1 which means no blending
+    ///
+    /// Only prototypes with the same node counts as the nearest one are blended, differently shaped ones are left out rather than resampled.
+    /// Blending has no effect where the nearest prototype has a shape that none of the others share
+    pub fn set_prototype_blending(&mut self, count: u32) -> &mut LoopedAppBuilder {
+        self.core.set_prototype_blending(count);
+        self
+    }
 
-        // self.terrain_settings = DEFAULT_TERRAIN;
-        self.gravity_strength = DEFAULT_GRAVITY_STRENGTH;
-        self.time_step = DEFAULT_TIMESTEP;
-        self.cell_settings = DEFAULT_CELL_SETTINGS;
-        self.plant_death_rate = DEFAULT_PLANT_DEATH_RATE;
+    /// sets the plant species used for the simulation, overrides default set of plant species used
+    ///
+    /// Plant species are used for initial plant spawning and spawning of new plants without seeding
+    pub fn set_plant_species(&mut self, species: Vec<SpeciesPreset>) -> &mut LoopedAppBuilder {
+        self.core.set_plant_species(species);
+        self
+    }
+
+    /// names the plant species in the order they were set, the names are written in the statistics
+    ///
+    /// Setting the plant species again removes the names, species presets are named after their preset
+    pub fn set_species_names(&mut self, names: Vec<String>) -> &mut LoopedAppBuilder {
+        self.core.set_species_names(names);
+        self
+    }
+
+    /// sets the plant species used for the simulation to the named species presets: "oak", "birch", "pine" or "shrub"
+    ///
+    /// Without any species or branch presets, all the species presets and their branch prototypes are used.
+    /// Panics if a name isn't one of the presets
+    pub fn set_preset_species(&mut self, names: &[&str]) -> &mut LoopedAppBuilder {
+        self.core.set_preset_species(names);
+        self
+    }
+
+    /// set how many plants are spawned at the start of the simulation
+    pub fn set_initial_plant_num(&mut self, num: u32) -> &mut LoopedAppBuilder {
+        self.core.set_initial_plant_num(num);
+        self
+    }
 
+    /// sets the size of the cell grid used to shadow distribution, the default is 1m^3. And the number of cells above or below that will be checked for shadow distribution purposes
+    pub fn set_shadow_cell_data(&mut self, size: f32, check_height: u32) -> &mut LoopedAppBuilder {
+        self.core.set_shadow_cell_data(size, check_height);
         self
     }
-}
\ No newline at end of file
+
+    /// sets the physical time step used for plant aging in years per step, defaults to 1.0
+    ///
+    /// Unlike the graphics app, this is how many years pass each time the simulation is stepped
+    pub fn set_time_step(&mut self, step: f32) -> &mut LoopedAppBuilder {
+        self.core.set_time_step(step);
+        self
+    }
+
+    /// sets the rate at which plants die, defaults to 1.0
+    pub fn set_plant_death_rate(&mut self, rate: f32) -> &mut LoopedAppBuilder {
+        self.core.set_plant_death_rate(rate);
+        self
+    }
+
+    /// sets the environmental parameters used by the simulation
+    ///
+    /// - Temperature: (Degrees Celsius at a height of y=0, rate of temperature decrease away from y=0)
+    /// - Moisture: Average annual precipitation, cm
+    pub fn set_environmental_parameters(&mut self, temperature: (f32, f32), moisture: f32) -> &mut LoopedAppBuilder {
+        self.core.set_environmental_parameters(temperature, moisture);
+        self
+    }
+
+    /// - Enables plant seeding, meaning that plants will reproduce
+    /// - This is disabled by default
+    /// - Has no effect without initial plants
+    pub fn enable_seeding(&mut self) -> &mut LoopedAppBuilder {
+        self.core.enable_seeding();
+        self
+    }
+
+    /// - Enables root growth below the terrain, the roots compete for soil resources which limits the vigor of their plants
+    /// - This is disabled by default
+    pub fn enable_roots(&mut self) -> &mut LoopedAppBuilder {
+        self.core.enable_roots();
+        self
+    }
+
+    /// sets the side length in m of the soil cells the roots compete for resources in, defaults to 0.5 and can't be less than 0.01
+    pub fn set_root_cell_size(&mut self, cell_size: f32) -> &mut LoopedAppBuilder {
+        self.core.set_root_cell_size(cell_size);
+        self
+    }
+
+    /// sets the distance between root nodes in m, defaults to 0.2, and how strongly roots turn towards soil resources, defaults to 1
+    pub fn set_root_growth(&mut self, segment_length: f32, hydrotropism: f32) -> &mut LoopedAppBuilder {
+        self.core.set_root_growth(segment_length, hydrotropism);
+        self
+    }
+
+    /// sets the soil grid, which holds the moisture and nutrients under the plants
+    ///
+    /// - Cell size: the side length of each cell in m, defaults to 2 and can't be less than 0.01
+    /// - Recharge rate: the fraction of the missing moisture restored by precipitation each year, defaults to 0.5
+    /// - Uptake rate: the cm of moisture used each year for every m^3 of plant, defaults to 0.2
+    pub fn set_soil_grid(&mut self, cell_size: f32, recharge_rate: f32, uptake_rate: f32) -> &mut LoopedAppBuilder {
+        self.core.set_soil_grid(cell_size, recharge_rate, uptake_rate);
+        self
+    }
+
+    /// makes the temperature and precipitation change over time, starting from the environmental parameters
+    ///
+    /// Each setting is a pair of (temperature, precipitation):
+    /// - Seasons: how far the temperature moves either side of the annual mean over the year, and the fraction of the precipitation that does
+    /// - Noise: the standard deviation of each year's difference from the mean, for precipitation as a fraction of the mean
+    /// - Trends: the change in the mean each year, in degrees celsius and cm
+    pub fn set_climate(&mut self, seasons: (f32, f32), noise: (f32, f32), trends: (f32, f32)) -> &mut LoopedAppBuilder {
+        self.core.set_climate(seasons, noise, trends);
+        self
+    }
+
+    /// replays recorded weather from a csv file instead of using a procedural climate, the simulation starts at the beginning of the first year in the file
+    ///
+    /// The rows are all (year, temperature, precipitation) or all (year, month, temperature, precipitation),
+    /// yearly rows give the annual precipitation and monthly rows the precipitation of the month, in cm
+    ///
+    /// The file is read straight away, returning an error if it can't be read or has no samples
+    pub fn set_climate_series(&mut self, path: &str) -> Result<&mut LoopedAppBuilder, ClimateSeriesError> {
+        self.core.set_climate_series(path)?;
+        Ok(self)
+    }
+
+    /// lights the plants from the path of the sun at a latitude in degrees instead of from straight above, north is positive
+    ///
+    /// The sun is sampled every two hours on the given day of the year, or on the equinoxes and solstices without one
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sun_path(&mut self, latitude: f32, day_of_year: Option<f32>) -> &mut LoopedAppBuilder {
+        self.core.set_sun_path(latitude, day_of_year);
+        self
+    }
+
+    /// lights the plants from a list of directions towards the light and their weights instead of from straight above
+    ///
+    /// +x is east, +y is up and +z is south, directions that don't point up are left out
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_light_directions(&mut self, directions: Vec<([f32; 3], f32)>) -> &mut LoopedAppBuilder {
+        self.core.set_light_directions(directions);
+        self
+    }
+
+    /// lights the plants with diffuse light from the whole sky instead of from straight above,
+    /// using rings of directions between the horizon and straight up with the given number of directions in each
+    ///
+    /// More directions are smoother but slower, 3 rings of 8 is a good start
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sky_dome(&mut self, rings: u32, directions_per_ring: u32) -> &mut LoopedAppBuilder {
+        self.core.set_sky_dome(rings, directions_per_ring);
+        self
+    }
+
+    /// samples the light reaching the terrain each step in a grid of square cells with the given side length in m, at least 0.01, using the light model of the plants
+    ///
+    /// When seeding, the ground light is the chance of a seedling surviving, which shade tolerant species are less affected by
+    pub fn set_ground_light(&mut self, cell_size: f32) -> &mut LoopedAppBuilder {
+        self.core.set_ground_light(cell_size);
+        self
+    }
+
+    /// sets the seed used for all the random parts of the simulation,
+    /// the same seed and settings will always give the same plants
+    pub fn set_seed(&mut self, seed: u64) -> &mut LoopedAppBuilder {
+        self.core.set_seed(seed);
+        self
+    }
+
+    /// applies all the settings in a scenario, overriding any previous settings they cover
+    ///
+    /// Returns an error if a file the scenario names can't be read
+    pub fn with_scenario(&mut self, scenario: &Scenario) -> Result<&mut LoopedAppBuilder, ScenarioError> {
+        self.core.with_scenario(scenario)?;
+        if let Some(step) = scenario.simulation.time_step {self.set_time_step(step);}
+        Ok(self)
+    }
+
+    /// resets all the settings of the builder to their defaults
+    pub fn reset(&mut self) -> &mut LoopedAppBuilder {
+        *self = LoopedTreeApp::new();
+        self
+    }
+
+
+
+    /// builds and returns the app for running
+    pub fn build(&mut self) -> LoopedTreeApp {
+
+        // transform data
+        let output = {
+            if self.output == 0 {OutputType::Absent}
+            else if self.output == 1 {OutputType::Data}
+            else if self.output == 2{OutputType::Meshes}
+            else {OutputType::All}
+        };
+
+        let mut world = World::new();
+        self.core.populate_world(&mut world);
+
+        let mut update_schedule = Schedule::new();
+        self.core.add_systems(&mut update_schedule);
+
+        LoopedTreeApp {
+            world,
+            update_schedule,
+            output,
+            branch_mesh_settings: self.branch_mesh_settings.unwrap_or(DEFAULT_BRANCH_MESH_SETTINGS),
+            light_sampler: self.core.light_sampler(),
+
+            time_step: self.core.time_step(),
+            elapsed_years: 0.0,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod looped_app_tests {
+    use super::*;
+
+    /// the bits of every node and the edges of each skeleton
+    type SkeletonBits = Vec<(Vec<[u32; 5]>, Vec<(usize, usize)>)>;
+
+    fn build_seeded_app(seed: u64) -> LoopedTreeApp {
+        let prototypes = vec![
+            (0.1, vec![vec![2], vec![1, 2]], vec![[0.743, 0.371, 0.557], [0.192, 0.962, 0.192], [0.557, 0.743, 0.371], [0.236, 0.943, 0.236], [-0.535, 0.267, 0.802]]),
+            (0.2, vec![vec![1], vec![2]], vec![[0.0, 1.0, 0.0], [0.588, 0.784, 0.196], [-0.302, 0.905, 0.302]]),
+        ];
+        let species = vec![(
+            (
+                GrowthControlSettingParams {max_age: 40.0, max_vigor: 10.0, min_vigor: 0.5, apical_control: 0.62, growth_rate: 0.19, tropism_time_control: 0.38, max_branch_segment_length: 1.0, branch_segment_length_scaling_coef: 1.0, tropism_angle_weight: 0.37, branching_angle: 0.52, thickening_factor: 0.05},
+                PlasticitySettingParams {seeding_frequency: 0.5, seeding_radius: 5.0, shadow_tolerance: 1.0, flowering_age: 5.0},
+            ),
+            (18.0, 5.0, 90.0, 15.0),
+        )];
+
+        LoopedTreeApp::new()
+            .with_flat_terrain(30.0, [0.0, 0.0, 0.0])
+            .set_branch_presets(prototypes, (vec![(5.0, 5.0), (15.0, 15.0)], 20.0, 20.0)).unwrap()
+            .set_plant_species(species)
+            .set_environmental_parameters((20.0, 0.1), 100.0)
+            .set_initial_plant_num(5)
+            .set_time_step(0.5)
+            .enable_seeding()
+            .set_output_type(1)
+            .set_seed(seed)
+            .build()
+    }
+
+    #[test]
+    fn default_app_test() {
+        // without any species or prototypes the presets are used, which should give plants that grow
+        let mut app = LoopedTreeApp::new().set_initial_plant_num(5).set_seed(1).build();
+        let start = app.population();
+        app.run_steps(10);
+        let end = app.population();
+
+        assert!(end.plants > 0);
+        assert!(end.branches > start.branches && end.nodes > start.nodes);
+    }
+
+    /// the skeletons as bits, so that comparing them checks the runs are exactly the same
+    fn to_bits(skeletons: &[PlantSkeleton]) -> SkeletonBits {
+        skeletons.iter().map(|skeleton| (
+            skeleton.nodes.iter()
+                .map(|node| [node.position[0], node.position[1], node.position[2], node.thickness, node.age].map(|x| x.to_bits()))
+                .collect(),
+            skeleton.edges.clone(),
+        )).collect()
+    }
+
+    #[test]
+    fn seeded_run_test() {
+        let first = build_seeded_app(42).run_steps(40).data.unwrap();
+        let second = build_seeded_app(42).run_steps(40).data.unwrap();
+
+        assert!(!first.is_empty());
+        assert_eq!(to_bits(&first), to_bits(&second));
+    }
+
+    #[test]
+    fn resume_from_checkpoint_test() {
+        let path = std::env::temp_dir().join("looped_app_resume_test.sscp");
+
+        let uninterrupted = build_seeded_app(7).run_steps(40).data.unwrap();
+
+        let mut first_half = build_seeded_app(7);
+        first_half.run_steps(20);
+        first_half.save_checkpoint(&path).unwrap();
+
+        // the fresh app is built with a different seed, so the rest of the run only matches if the checkpoint restores everything
+        let mut resumed = build_seeded_app(8);
+        resumed.load_checkpoint(&path).unwrap();
+        let second_half = resumed.run_steps(20).data.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!uninterrupted.is_empty());
+        assert_eq!(resumed.elapsed_years(), 20.0);
+        assert_eq!(to_bits(&second_half), to_bits(&uninterrupted));
+    }
+
+    #[test]
+    fn run_until_test() {
+        let mut app = LoopedTreeApp::new().set_time_step(0.5).build();
+        app.run_until(2.0);
+        assert_eq!(app.elapsed_years(), 2.0);
+
+        // already past the target, so nothing is run
+        app.run_until(1.0);
+        assert_eq!(app.elapsed_years(), 2.0);
+        app.run_steps(3);
+        assert_eq!(app.elapsed_years(), 3.5);
+
+        // no years can pass with a zero time step, so this returns straight away
+        let mut app = LoopedTreeApp::new().set_time_step(0.0).build();
+        app.run_until(5.0);
+        assert_eq!(app.elapsed_years(), 0.0);
+    }
+}
@@ -1,9 +1,10 @@
-use std::ops::RangeInclusive;
+use std::{ops::{RangeInclusive, Range}, collections::HashMap};
 use bevy_ecs::{
     prelude::*,
     system::SystemState
 };
-use rand::{Rng, thread_rng};
+use rand::Rng;
+use serde::Serialize;
 use crate::maths::colliders::mesh_collider::MeshCollider;
 use super::{
     branches::{
@@ -11,13 +12,20 @@ use super::{
         branch_development::*,
         branch_prototypes::*,
         branch_node::*,
+        branch_mesh::*,
     },
     plants::{
         plant::*,
         plant_development::*,
+        plant_selection::PlantSpeciesSampler,
+        plant_statistics::{ForestStatistics, SpeciesStatistics},
+    },
+    environment::{
+        terrain::{TerrainTag, TerrainMeshData},
     },
     maths::{
         vector_three::Vector3,
+        matrix_three::Matrix3,
         colliders::Collider,
     },
 };
@@ -25,25 +33,36 @@ use super::{
 #[cfg(feature = "vulkan_graphics")]
 pub mod graphics_app;
 pub mod looped_app;
+pub mod simulation_core;
+pub mod scenario;
+pub mod presets;
+mod export;
+mod checkpoint;
 
+pub use checkpoint::CheckpointError;
 
-//////////////////// consts
-const SAMPLER_SIZE: (u32, u32) = (500, 500);
 
+//////////////////// consts
 const DEFAULT_GRAVITY_STRENGTH: f32 = 0.5;
 const DEFAULT_TIMESTEP: f32 = 1.0;
 const DEFAULT_CELL_SETTINGS: (u32, f32) = (5, 0.5);
+const LIGHT_CELL_MARGIN: f32 = 10.0; // how far past the terrain edge branches still cast shadow, in m
 const DEFAULT_PLANT_DEATH_RATE: f32 = 1.0;
+const DEFAULT_ROOT_SETTINGS: (f32, f32) = (0.2, 1.0); // segment length, hydrotropism
+const DEFAULT_ROOT_CELL_SIZE: f32 = 0.5;
+const DEFAULT_SOIL_SETTINGS: (f32, f32, f32) = (2.0, 0.5, 0.2); // cell size, recharge rate, uptake rate
+const MIN_CELL_SIZE: f32 = 0.01; // the smallest soil, root and ground light cells, in m
+#[cfg(feature = "vulkan_graphics")]
 const DEFAULT_LIGHT: ([f32; 3], f32) = ([0.0, -1.0, 0.0], 0.5);
 const DEFAULT_BRANCH_MESH_SETTINGS: (u32, bool) = (3, false);
+#[cfg(feature = "vulkan_graphics")]
+const DEFAULT_TERRAIN_GRAPHICS: ([f32; 3], [f32; 3], f32, f32) = ([0.3, 0.5, 0.2], [0.4, 0.4, 0.4], 0.7, 0.1); // grass, rock, slope threshold, blend
 const DEFAULT_ENVIRONMENTAL_PARAMS: (f32, f32, f32) = (10.0, 0.01, 110.0); // based on the UK
 const DEFAULT_TERRAIN: (f32, [f32; 3]) = (50.0, [0.0, 0.0, 0.0]);
-
-const DEFAULT_BRANCH_TYPES: Vec<(f32, Vec<Vec<u32>>, Vec<[f32; 3]>)> = Vec::new();
-const DEFAULT_BRANCH_CONTIDITIONS: (Vec<(f32, f32)>, f32, f32) = (Vec::new(), 1.0, 1.0);
-const DEFAULT_PLANT_SPECIES: Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))> = Vec::new();
+// the default species and branch prototypes come from the presets library
 
 
+#[cfg(feature = "vulkan_graphics")]
 enum TerrainType {
     Absent,
     Flat,
@@ -57,70 +76,391 @@ enum OutputType {
     All,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct TreeAppOutput {
-    pub data: Option<Vec<([f32; 3], Vec<([f32; 3], f32)>, Vec<(usize, usize)>)>>,
-    pub meshes: Option<()>,
+    pub data: Option<Vec<PlantSkeleton>>,
+    pub meshes: Option<Vec<PlantMesh>>,
 }
 
-impl Default for TreeAppOutput {
-    fn default() -> Self {
-        TreeAppOutput {data: None, meshes: None}
-    }
+/// The branch nodes of a single plant and how they are connected
+///
+/// Node positions are in world space, the position is the root of the plant
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlantSkeleton {
+    pub position: [f32; 3],
+    pub nodes: Vec<SkeletonNode>,
+    /// pairs of indices into nodes, going from the parent node to the child node
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// A single branch node of a plant skeleton
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SkeletonNode {
+    pub position: [f32; 3],
+    pub thickness: f32,
+    /// the index of the branch the node is on, branches are ordered from the base of the plant to the tips
+    pub branch: usize,
+    /// the physiological age of the node
+    pub age: f32,
+}
+
+/// The mesh of all the branches of a single plant
+///
+/// The vertices are in world space, the position is the root of the plant
+#[derive(Clone, Debug)]
+pub struct PlantMesh {
+    pub position: [f32; 3],
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    /// the range of indices that makes up each branch, branches are ordered from the base of the plant to the tips
+    pub branches: Vec<Range<usize>>,
 }
 
+/// The number of plants, branches and branch nodes in a simulation
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PopulationCounts {
+    pub plants: usize,
+    pub branches: usize,
+    pub nodes: usize,
+}
+
+/// A single light cell and the light reaching it
+///
+/// The position is the centre of the cell in world space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightCellData {
+    pub position: [f32; 3],
+    /// the volume of the branches in the cell
+    pub shadow_volume: f32,
+    /// the light reaching the cell from the light model of the simulation, from 0 to 1
+    pub light: f32,
+}
+
+/// Information about a plant that is exported alongside its mesh
+#[derive(Clone, Debug)]
+struct PlantDetails {
+    species: Option<usize>,
+    age: f32,
+    climate_adaption: f32,
+    vigor: f32,
+}
+
+/// The mesh of the terrain, in world space
+#[derive(Clone, Debug)]
+pub struct TerrainMesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+
+/// the node, branch and plant queries the skeletons are read with
+type SkeletonQueries = (
+    Query<'static, 'static, &'static BranchNodeData, With<BranchNodeTag>>,
+    Query<'static, 'static, &'static BranchNodeConnectionData, With<BranchNodeTag>>,
+    Query<'static, 'static, &'static BranchData, With<BranchTag>>,
+    Query<'static, 'static, &'static BranchConnectionData, With<BranchTag>>,
+    Query<'static, 'static, &'static PlantData, With<PlantBounds>>,
+);
 
+/// the node, branch and plant queries the meshes are built from
+type MeshQueries = (
+    Query<'static, 'static, &'static BranchNodeData, With<BranchNodeTag>>,
+    Query<'static, 'static, &'static BranchNodeConnectionData, With<BranchNodeTag>>,
+    Query<'static, 'static, (&'static BranchData, &'static BranchGrowthData), With<BranchTag>>,
+    Query<'static, 'static, &'static BranchConnectionData, With<BranchTag>>,
+    Query<'static, 'static, (&'static PlantData, Option<&'static PlantSpecies>), With<PlantBounds>>,
+);
+
+
+/// gets the skeleton of every plant in the world
+///
+/// The root node of a child branch is the same node as its parent node, so it is merged into the parent node
 fn data_output(
     world: &mut World,
-) -> Vec<([f32; 3], Vec<([f32; 3], f32)>, Vec<(usize, usize)>)>{
+) -> Vec<PlantSkeleton> {
 
-    let mut state: SystemState<(
-        Query<&BranchNodeData, With<BranchNodeTag>>,
-        Query<&BranchNodeConnectionData, With<BranchNodeTag>>,
-        Query<&BranchData, With<BranchTag>>,
-        Query<&BranchConnectionData, With<BranchTag>>,
-        Query<&PlantData, With<PlantBounds>>,
-    )> = SystemState::new(world);
+    let mut state: SystemState<SkeletonQueries> = SystemState::new(world);
 
     let (node_data, node_connections, branch_data, branch_connections, plant_data) = state.get(world);
 
-    // plant positions : node position and thickness : node connections
-    let mut data: Vec<([f32; 3], Vec<([f32; 3], f32)>, Vec<(usize, usize)>)> = Vec::new();
+    let mut skeletons: Vec<PlantSkeleton> = Vec::new();
     for plant in plant_data.iter() {
         if plant.root_node.is_none() {continue;}
-        
-        let position: [f32; 3] = plant.position.into();
 
-        let mut plant_data: (Vec<([f32; 3], f32)>, Vec<(usize, usize)>) = (Vec::new(), Vec::new());
+        let mut nodes: Vec<SkeletonNode> = Vec::new();
+        let mut edges: Vec<(usize, usize)> = Vec::new();
 
-        let mut current_nodes: usize = 0;
+        // the index of each node entity in the skeleton
+        let mut node_indices: HashMap<Entity, usize> = HashMap::new();
 
-        for id in get_branches_base_to_tip(&branch_connections, plant.root_node.unwrap()) {
+        for (branch_index, id) in get_branches_base_to_tip(&branch_connections, plant.root_node.unwrap()).into_iter().enumerate() {
             if let Ok(branch) = branch_data.get(id) {
                 if branch.root_node.is_none() {continue;}
+                let root_node = branch.root_node.unwrap();
 
-                let (positions, thicknesses, pairs) = get_node_data_and_connections_base_to_tip(&node_connections, &node_data, branch.root_node.unwrap());
-                let mut sub_data: Vec<([f32; 3], f32)> = Vec::new();
-                for i in 0..positions.len() {
-                    sub_data.push((positions[i].into(), thicknesses[i]));
+                let branch_rotation_matrix = {
+                    let rotation_axis = branch.normal.cross(Vector3::Y());
+                    let rotation_angle = branch.normal.angle_to(Vector3::Y());
+                    Matrix3::from_angle_and_axis(-rotation_angle, rotation_axis)
+                };
+
+                for node_id in get_nodes_base_to_tip(&node_connections, root_node) {
+                    if node_id == root_node {
+                        if let Some(parent_index) = branch.parent_node.and_then(|parent| node_indices.get(&parent).copied()) {
+                            node_indices.insert(node_id, parent_index);
+                            continue;
+                        }
+                    }
+
+                    if let Ok(node) = node_data.get(node_id) {
+                        let position = (node.position + node.tropism_offset).transform(branch_rotation_matrix) + branch.root_position;
+                        node_indices.insert(node_id, nodes.len());
+                        nodes.push(SkeletonNode {
+                            position: position.into(),
+                            thickness: node.thickness,
+                            branch: branch_index,
+                            age: node.phys_age.0,
+                        });
+                    }
                 }
-                let start_point = plant_data.0.iter().position(|&x| x == sub_data[0]).unwrap_or(0);
-                if start_point != 0 {current_nodes -= 1; sub_data.remove(0);}
-
-                let mut new_pairs: Vec<(usize, usize)> = Vec::new();
-                for pair in pairs {
-                    let one = if pair.0 == 0 {start_point} else {pair.0 + current_nodes};
-                    let two = if pair.0 == 0 {start_point} else {pair.0 + current_nodes};
-                    new_pairs.push((one, two));
+
+                for pair in get_nodes_and_connections_base_to_tip(&node_connections, root_node) {
+                    if let (Some(one), Some(two)) = (node_indices.get(&pair[0]), node_indices.get(&pair[1])) {
+                        edges.push((*one, *two));
+                    }
                 }
-                plant_data.0.append(&mut sub_data);
-                plant_data.1.append(&mut new_pairs);
-                current_nodes = plant_data.0.len() - 1;
             }
         }
-        data.push((position, plant_data.0, plant_data.1));
+
+        skeletons.push(PlantSkeleton {
+            position: plant.position.into(),
+            nodes,
+            edges,
+        });
     }
 
+    skeletons
+}
+
+
+/// creates a mesh for every plant in the world, using the given polygon directions for the branch cross sections
+fn mesh_output(
+    world: &mut World,
+    polygon_directions: &[Vector3],
+    flat_shaded: bool,
+) -> Vec<PlantMesh> {
+    detailed_mesh_output(world, polygon_directions, flat_shaded).into_iter().map(|(mesh, _)| mesh).collect()
+}
+
+
+/// the same as mesh_output, but also returns the details of each plant
+fn detailed_mesh_output(
+    world: &mut World,
+    polygon_directions: &[Vector3],
+    flat_shaded: bool,
+) -> Vec<(PlantMesh, PlantDetails)> {
+
+    let mut state: SystemState<MeshQueries> = SystemState::new(world);
+
+    let (node_data, node_connections, branch_data, branch_connections, plant_query) = state.get(world);
+
+    let mut meshes: Vec<(PlantMesh, PlantDetails)> = Vec::new();
+    for (plant, species) in plant_query.iter() {
+        if plant.root_node.is_none() {continue;}
+
+        let mut vertices: Vec<Vector3> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut branches: Vec<Range<usize>> = Vec::new();
 
-    data
-}
\ No newline at end of file
+        for id in get_branches_base_to_tip(&branch_connections, plant.root_node.unwrap()) {
+            if let Ok((branch, _)) = branch_data.get(id) {
+                if branch.root_node.is_none() {continue;}
+
+                let (positions, thicknesses, pairs) = get_node_data_and_connections_base_to_tip(&node_connections, &node_data, branch.root_node.unwrap());
+                let (mut branch_vertices, branch_indices) = create_branch_mesh_components(branch.normal, branch.root_position, positions, thicknesses, pairs, polygon_directions);
+
+                let offset = vertices.len() as u32;
+                branches.push(indices.len()..(indices.len() + branch_indices.len()));
+                indices.extend(branch_indices.iter().map(|index| index + offset));
+                vertices.append(&mut branch_vertices);
+            }
+        }
+
+        // flat shading keeps the triangles in the same order, so the branch ranges are still valid
+        let (vertices, normals, indices) = {
+            if flat_shaded {
+                flat_shade_mesh_components(&vertices, &indices)
+            } else {
+                let normals = calculate_smooth_normals(&vertices, &indices);
+                (vertices, normals, indices)
+            }
+        };
+
+        let details = PlantDetails {
+            species: species.map(|species| species.0),
+            age: plant.age,
+            climate_adaption: plant.climate_adaption,
+            vigor: branch_data.get(plant.root_node.unwrap()).map(|(_, growth_data)| growth_data.growth_vigor).unwrap_or(0.0),
+        };
+
+        meshes.push((
+            PlantMesh {
+                position: plant.position.into(),
+                vertices: vertices.into_iter().map(|vertex| vertex.into()).collect(),
+                normals: normals.into_iter().map(|normal| normal.into()).collect(),
+                indices,
+                branches,
+            },
+            details,
+        ));
+    }
+
+    meshes
+}
+
+
+/// counts the plants, branches and branch nodes in the world
+fn population_counts(
+    world: &mut World,
+) -> PopulationCounts {
+    PopulationCounts {
+        plants: world.query_filtered::<(), With<PlantTag>>().iter(world).count(),
+        branches: world.query_filtered::<(), With<BranchTag>>().iter(world).count(),
+        nodes: world.query_filtered::<(), With<BranchNodeTag>>().iter(world).count(),
+    }
+}
+
+
+/// creates a mesh for the terrain, returns none if there is no terrain
+fn terrain_output(
+    world: &mut World,
+) -> Option<TerrainMesh> {
+    let mut terrain_query = world.query_filtered::<&TerrainMeshData, With<TerrainTag>>();
+    let terrain = terrain_query.get_single(world).ok()?;
+
+    let normals = calculate_smooth_normals(&terrain.vertices, &terrain.indices);
+
+    Some(TerrainMesh {
+        vertices: terrain.vertices.iter().map(|vertex| (*vertex).into()).collect(),
+        normals: normals.into_iter().map(|normal| normal.into()).collect(),
+        indices: terrain.indices.clone(),
+    })
+}
+
+#[cfg(test)]
+mod output_tests {
+    use super::*;
+
+    /// builds a plant at (1, 0, 2) with a root branch with a fork, and a child branch coming from the fork
+    ///
+    /// root branch: 0 -> 1 -> 2, 1 -> 3
+    /// child branch: 1 -> 4
+    pub(super) fn build_test_world() -> World {
+        let mut world = World::new();
+
+        let node_positions = [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 2.0, 0.0], [1.0, 1.0, 0.0]];
+        let root_nodes: Vec<Entity> = node_positions.iter().enumerate().map(|(i, position)| {
+            world.spawn(BranchNodeBundle {
+                data: BranchNodeData {position: (*position).into(), thickness: 0.5 - i as f32 * 0.1, phys_age: (i as f32, 1.0), ..Default::default()},
+                ..Default::default()
+            }).id()
+        }).collect();
+        world.get_mut::<BranchNodeConnectionData>(root_nodes[0]).unwrap().children = vec![root_nodes[1]];
+        world.get_mut::<BranchNodeConnectionData>(root_nodes[1]).unwrap().children = vec![root_nodes[2], root_nodes[3]];
+        for i in 1..4 {
+            world.get_mut::<BranchNodeConnectionData>(root_nodes[i]).unwrap().parent = Some(root_nodes[if i == 1 {0} else {1}]);
+        }
+
+        let child_nodes: Vec<Entity> = [[0.0, 0.0, 0.0], [0.0, 0.0, 1.0]].iter().map(|position| {
+            world.spawn(BranchNodeBundle {
+                data: BranchNodeData {position: (*position).into(), thickness: 0.1, phys_age: (0.5, 1.0), ..Default::default()},
+                ..Default::default()
+            }).id()
+        }).collect();
+        world.get_mut::<BranchNodeConnectionData>(child_nodes[0]).unwrap().children = vec![child_nodes[1]];
+        world.get_mut::<BranchNodeConnectionData>(child_nodes[1]).unwrap().parent = Some(child_nodes[0]);
+
+        let child_branch = world.spawn(BranchBundle {
+            data: BranchData {root_node: Some(child_nodes[0]), parent_node: Some(root_nodes[1]), root_position: Vector3::new(1.0, 1.0, 2.0), ..Default::default()},
+            ..Default::default()
+        }).id();
+        let root_branch = world.spawn(BranchBundle {
+            data: BranchData {root_node: Some(root_nodes[0]), root_position: Vector3::new(1.0, 0.0, 2.0), ..Default::default()},
+            connections: BranchConnectionData {children: (Some(child_branch), None), ..Default::default()},
+            ..Default::default()
+        }).id();
+        world.get_mut::<BranchConnectionData>(child_branch).unwrap().parent = Some(root_branch);
+
+        world.spawn(PlantBundle {
+            data: PlantData {position: Vector3::new(1.0, 0.0, 2.0), age: 1.0, root_node: Some(root_branch), ..Default::default()},
+            ..Default::default()
+        });
+
+        world
+    }
+
+    #[test]
+    fn skeleton_nodes_test() {
+        let mut world = build_test_world();
+        let skeletons = data_output(&mut world);
+
+        assert_eq!(skeletons.len(), 1);
+        assert_eq!(skeletons[0].position, [1.0, 0.0, 2.0]);
+
+        let positions: Vec<[f32; 3]> = skeletons[0].nodes.iter().map(|node| node.position).collect();
+        assert_eq!(positions, vec![[1.0, 0.0, 2.0], [1.0, 1.0, 2.0], [1.0, 2.0, 2.0], [2.0, 1.0, 2.0], [1.0, 1.0, 3.0]]);
+
+        let branches: Vec<usize> = skeletons[0].nodes.iter().map(|node| node.branch).collect();
+        assert_eq!(branches, vec![0, 0, 0, 0, 1]);
+
+        assert_eq!(skeletons[0].nodes[2].age, 2.0);
+        assert_eq!(skeletons[0].nodes[4].thickness, 0.1);
+    }
+
+    #[test]
+    fn skeleton_edges_test() {
+        let mut world = build_test_world();
+        let skeletons = data_output(&mut world);
+
+        assert_eq!(skeletons[0].edges, vec![(0, 1), (1, 2), (1, 3), (1, 4)]);
+    }
+
+    /// checks that the branch ranges cover the indices in order with no gaps, and that every index is a vertex
+    fn check_mesh_ranges(mesh: &PlantMesh) {
+        assert_eq!(mesh.branches.first().unwrap().start, 0);
+        assert_eq!(mesh.branches.last().unwrap().end, mesh.indices.len());
+        for pair in mesh.branches.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert!(mesh.indices.iter().all(|index| (*index as usize) < mesh.vertices.len()));
+    }
+
+    #[test]
+    fn mesh_output_test() {
+        let mut world = build_test_world();
+        let meshes = mesh_output(&mut world, &create_vector_polygon(4, None), false);
+
+        // each node pair is a tube with 2 vertices and 2 triangles per polygon side, the root branch has 3 pairs and the child branch has 1
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].position, [1.0, 0.0, 2.0]);
+        assert_eq!((meshes[0].vertices.len(), meshes[0].normals.len(), meshes[0].indices.len()), (32, 32, 96));
+        assert_eq!(meshes[0].branches, vec![0..72, 72..96]);
+        check_mesh_ranges(&meshes[0]);
+    }
+
+    #[test]
+    fn flat_shaded_mesh_output_test() {
+        let mut world = build_test_world();
+        let meshes = detailed_mesh_output(&mut world, &create_vector_polygon(4, None), true);
+
+        // flat shading gives every triangle its own vertices
+        assert_eq!(meshes.len(), 1);
+        let (mesh, details) = &meshes[0];
+        assert_eq!((mesh.vertices.len(), mesh.normals.len(), mesh.indices.len()), (96, 96, 96));
+        assert_eq!(mesh.branches, vec![0..72, 72..96]);
+        check_mesh_ranges(mesh);
+        assert_eq!((details.species, details.age), (None, 1.0));
+    }
+}
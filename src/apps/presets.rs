@@ -0,0 +1,100 @@
+//! A built in library of temperate species and the branch prototypes they grow with
+//!
+//! These are used when a builder isn't given any species or prototypes, and species can be picked from them by name
+use super::super::{
+    branches::{prototype_generator::PrototypeGenerator, branch_prototypes::{PrototypePreset, PrototypeConditions}},
+    plants::plant::{GrowthControlSettingParams, PlasticitySettingParams, SpeciesPreset},
+};
+
+
+/// the names of the species presets, in the order all_species returns them
+pub const SPECIES_NAMES: [&str; 4] = ["oak", "birch", "pine", "shrub"];
+
+
+/// returns the species preset with the given name, along with its climate envelope
+///
+/// The climate envelope is (ideal temperature, temperature std dev, ideal moisture, moisture std dev).
+/// Max age and flowering age are physiological ages, which pass at the species' growth rate
+pub fn species(name: &str) -> Option<SpeciesPreset> {
+    let preset = match name {
+        // broad and long lived, with weak apical control so the crown spreads
+        "oak" => (
+            GrowthControlSettingParams {max_age: 20.0, max_vigor: 4.0, min_vigor: 0.4, apical_control: 0.35, growth_rate: 0.3, tropism_time_control: 0.4, max_branch_segment_length: 1.0, branch_segment_length_scaling_coef: 1.0, tropism_angle_weight: 0.3, branching_angle: 0.7, thickening_factor: 0.07},
+            PlasticitySettingParams {seeding_frequency: 0.08, seeding_radius: 8.0, shadow_tolerance: 0.8, flowering_age: 3.0},
+            (10.0, 5.0, 100.0, 30.0),
+        ),
+        // a fast growing, short lived pioneer that seeds far and often
+        "birch" => (
+            GrowthControlSettingParams {max_age: 10.0, max_vigor: 3.0, min_vigor: 0.4, apical_control: 0.55, growth_rate: 0.45, tropism_time_control: 0.35, max_branch_segment_length: 0.9, branch_segment_length_scaling_coef: 1.0, tropism_angle_weight: 0.45, branching_angle: 0.45, thickening_factor: 0.04},
+            PlasticitySettingParams {seeding_frequency: 0.1, seeding_radius: 15.0, shadow_tolerance: 0.5, flowering_age: 1.5},
+            (8.0, 6.0, 90.0, 35.0),
+        ),
+        // a conifer with a strong leader and wide angled branches, happier in the cold
+        "pine" => (
+            GrowthControlSettingParams {max_age: 15.0, max_vigor: 3.5, min_vigor: 0.4, apical_control: 0.85, growth_rate: 0.35, tropism_time_control: 0.3, max_branch_segment_length: 0.8, branch_segment_length_scaling_coef: 1.0, tropism_angle_weight: 0.6, branching_angle: 0.9, thickening_factor: 0.05},
+            PlasticitySettingParams {seeding_frequency: 0.1, seeding_radius: 10.0, shadow_tolerance: 0.6, flowering_age: 2.5},
+            (6.0, 5.0, 80.0, 30.0),
+        ),
+        // small, shade tolerant and quick to reproduce
+        "shrub" => (
+            GrowthControlSettingParams {max_age: 6.0, max_vigor: 2.5, min_vigor: 0.2, apical_control: 0.15, growth_rate: 0.4, tropism_time_control: 0.4, max_branch_segment_length: 0.5, branch_segment_length_scaling_coef: 1.0, tropism_angle_weight: 0.2, branching_angle: 0.8, thickening_factor: 0.03},
+            PlasticitySettingParams {seeding_frequency: 0.1, seeding_radius: 4.0, shadow_tolerance: 0.95, flowering_age: 1.5},
+            (12.0, 7.0, 100.0, 40.0),
+        ),
+        _ => return None,
+    };
+    Some(((preset.0, preset.1), preset.2))
+}
+
+/// returns every species preset
+pub fn all_species() -> Vec<SpeciesPreset> {
+    SPECIES_NAMES.iter().filter_map(|name| species(name)).collect()
+}
+
+
+/// returns the branch prototypes the species presets are made for, and their positions in (apical control, determinacy) space
+///
+/// The space goes from 0 to 1 on both axes, so the apical control of a species is used directly
+pub fn prototype_library() -> (Vec<PrototypePreset>, PrototypeConditions) {
+    // (apical control, determinacy), (mature age, layers, branching probability, divergence angle, straightness, seed)
+    let library = [
+        // low apical control: spreading, twiggy shoots when vigorous and sparse ones when not
+        ((0.2, 0.2), (0.06, 3, 0.3, 0.9, 0.6, 1)),
+        ((0.2, 0.8), (0.08, 4, 0.8, 0.9, 0.7, 2)),
+        // the middle of the space, for balanced crowns
+        ((0.5, 0.5), (0.08, 4, 0.5, 0.6, 0.8, 3)),
+        // high apical control: a straight leader with few side shoots, or whorls of wide branches when vigorous
+        ((0.8, 0.2), (0.06, 3, 0.2, 0.5, 0.95, 4)),
+        ((0.8, 0.8), (0.1, 5, 0.6, 1.2, 0.95, 5)),
+    ];
+
+    let prototypes = library.iter().map(|(_, (mature_age, layers, branching, divergence, straightness, seed))| {
+        PrototypeGenerator::new()
+            .set_mature_age(*mature_age)
+            .set_layers(*layers)
+            .set_branching_probability(*branching)
+            .set_divergence_angle(*divergence)
+            .set_straightness(*straightness)
+            .set_seed(*seed)
+            .generate_preset()
+            .expect("the library prototypes are always valid")
+    }).collect();
+    let positions = library.iter().map(|(position, _)| *position).collect();
+
+    (prototypes, (positions, 1.0, 1.0))
+}
+
+
+
+#[cfg(test)]
+mod presets_tests {
+    use super::*;
+
+    #[test]
+    fn species_by_name_test() {
+        assert_eq!(all_species().len(), SPECIES_NAMES.len());
+        assert_eq!(species("pine").unwrap().0.0.apical_control, 0.85);
+        assert!(species("palm").is_none());
+        assert!(all_species().iter().all(|species| (0.0..=1.0).contains(&species.0.1.shadow_tolerance)));
+    }
+}
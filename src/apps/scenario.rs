@@ -0,0 +1,656 @@
+//! Scenario files, which describe everything needed to set up a simulation so experiments don't need recompiling
+//!
+//! Scenarios are written in TOML, or JSON if the file has a .json extension.
+//! Every section apart from species is optional, and missing values use the app defaults.
+//! Relative paths to other files, like a heightmap or climate series, are from the directory the scenario file is in.
+//! Without any prototypes the built in prototype library is used, and a species can start from a built in preset by name
+//!
+//! ```toml
+//! [simulation]
+//! time_step = 0.5
+//! initial_plants = 25
+//! seeding = true
+//! seed = 42
+//!
+//! [terrain]
+//! type = "flat"
+//! size = 50.0
+//!
+//! [climate]
+//! seasonal_temperature = 8.0
+//! warming_rate = 0.02
+//!
+//! [[prototypes]]
+//! mature_age = 0.1
+//! apical = 10.0
+//! determinacy = 10.0
+//! node_counts = [[1], [2]]
+//! directions = [[0.0, 1.0, 0.0], [0.5, 0.8, 0.0], [-0.5, 0.8, 0.0]]
+//!
+//! [[species]]
+//! growth = { max_age = 40.0, max_vigor = 10.0, ... }
+//! plasticity = { seeding_frequency = 0.5, ... }
+//! climate = { ideal_temperature = 18.0, temperature_std_dev = 5.0, ideal_moisture = 90.0, moisture_std_dev = 15.0 }
+//!
+//! [[species]]
+//! preset = "birch"
+//! climate = { ideal_temperature = 6.0, temperature_std_dev = 5.0, ideal_moisture = 90.0, moisture_std_dev = 15.0 }
+//! ```
+use std::{fmt, fs, io, path::Path};
+use serde::{Serialize, Deserialize};
+use super::{presets, super::{
+    branches::branch_prototypes::{BranchPrototypeData, PrototypePreset, PrototypeConditions},
+    plants::plant::{GrowthControlSettingParams, PlasticitySettingParams, SpeciesPreset},
+}};
+
+
+/// An error from loading a scenario
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    /// the file could not be parsed, the message names the line and field where possible
+    Parse(String),
+    /// a value in the scenario is not allowed
+    Invalid {
+        field: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScenarioError::Io(err) => write!(f, "failed to read scenario: {}", err),
+            ScenarioError::Parse(message) => write!(f, "failed to parse scenario: {}", message),
+            ScenarioError::Invalid{field, reason} => write!(f, "invalid scenario field `{}`: {}", field, reason),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl From<io::Error> for ScenarioError {
+    fn from(err: io::Error) -> Self {
+        ScenarioError::Io(err)
+    }
+}
+
+
+/// A full description of a simulation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub simulation: SimulationSettings,
+    pub terrain: Option<TerrainSettings>,
+    pub environment: Option<EnvironmentSettings>,
+    pub light_cells: Option<LightCellSettings>,
+    pub soil: Option<SoilSettings>,
+    pub climate: Option<ClimateDriverSettings>,
+    pub sun: Option<SunSettings>,
+    pub sky_dome: Option<SkyDomeSettings>,
+    pub ground_light: Option<GroundLightSettings>,
+    #[serde(default)]
+    pub prototype_space: PrototypeSpace,
+    /// the prototype library is used if this is empty
+    #[serde(default)]
+    pub prototypes: Vec<PrototypeSettings>,
+    pub species: Vec<SpeciesSettings>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimulationSettings {
+    /// years per step
+    pub time_step: Option<f32>,
+    pub initial_plants: u32,
+    #[serde(default)]
+    pub seeding: bool,
+    /// grows roots below the terrain, which compete for soil resources
+    #[serde(default)]
+    pub roots: bool,
+    /// the side length of the soil cells roots compete in, in m
+    pub root_cell_size: Option<f32>,
+    pub seed: Option<u64>,
+    pub gravity: Option<f32>,
+    pub plant_death_rate: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TerrainSettings {
+    Flat {
+        size: f32,
+        #[serde(default)]
+        centre: [f32; 3],
+    },
+    Heightmap {
+        size: f32,
+        #[serde(default)]
+        centre: [f32; 3],
+        vertices_per_side: u32,
+        height_scale: f32,
+        heightmap: String,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvironmentSettings {
+    /// degrees celsius at y=0
+    pub temperature: f32,
+    /// the rate the temperature decreases going up
+    pub temperature_fall_off: f32,
+    /// average annual precipitation in cm
+    pub moisture: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightCellSettings {
+    pub size: f32,
+    pub check_height: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SoilSettings {
+    /// the side length of each soil cell in m
+    pub cell_size: f32,
+    /// the fraction of the missing moisture restored each year
+    pub recharge_rate: f32,
+    /// cm of moisture used each year per m^3 of plant
+    pub uptake_rate: f32,
+}
+
+/// how the climate changes over time, anything missing stays constant
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClimateDriverSettings {
+    /// degrees celsius either side of the annual mean
+    pub seasonal_temperature: f32,
+    /// the fraction of the annual precipitation either side of it
+    pub seasonal_precipitation: f32,
+    pub temperature_noise: f32,
+    /// as a fraction of the annual precipitation
+    pub precipitation_noise: f32,
+    /// degrees celsius per year
+    pub warming_rate: f32,
+    /// cm per year
+    pub precipitation_trend: f32,
+    /// a csv file of recorded weather to replay instead
+    pub series: Option<String>,
+}
+
+/// lights the plants from the path of the sun instead of from straight above
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SunSettings {
+    /// degrees, north is positive
+    pub latitude: f32,
+    /// the whole year is used without a day
+    pub day_of_year: Option<f32>,
+}
+
+/// lights the plants with diffuse light from the whole sky instead of from straight above
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyDomeSettings {
+    /// rings of directions between the horizon and straight up
+    pub rings: u32,
+    pub directions_per_ring: u32,
+}
+
+/// samples the light reaching the terrain, which seedlings need to survive
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroundLightSettings {
+    /// the side length of each ground light cell in m
+    pub cell_size: f32,
+}
+
+/// the maximum values of the apical control and determinacy that prototypes are placed between
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrototypeSpace {
+    pub max_apical: f32,
+    pub max_determinacy: f32,
+    /// how many of the nearest prototypes are blended for new branches, only ones with the same node counts as the nearest are used
+    pub blend_count: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrototypeSettings {
+    pub mature_age: f32,
+    pub node_counts: Vec<Vec<u32>>,
+    pub directions: Vec<[f32; 3]>,
+    /// where the prototype is chosen in terms of apical control
+    pub apical: f32,
+    /// where the prototype is chosen in terms of determinacy
+    pub determinacy: f32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpeciesSettings {
+    pub name: Option<String>,
+    /// the name of a built in species to start from, any of growth, plasticity and climate given replace the preset's
+    pub preset: Option<String>,
+    pub growth: Option<GrowthControlSettingParams>,
+    pub plasticity: Option<PlasticitySettingParams>,
+    pub climate: Option<ClimateSettings>,
+}
+
+/// the climate a species grows best in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClimateSettings {
+    pub ideal_temperature: f32,
+    pub temperature_std_dev: f32,
+    pub ideal_moisture: f32,
+    pub moisture_std_dev: f32,
+}
+
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        SimulationSettings {
+            time_step: None,
+            initial_plants: 1,
+            seeding: false,
+            roots: false,
+            root_cell_size: None,
+            seed: None,
+            gravity: None,
+            plant_death_rate: None,
+        }
+    }
+}
+
+impl Default for PrototypeSpace {
+    fn default() -> Self {
+        PrototypeSpace {max_apical: 20.0, max_determinacy: 20.0, blend_count: None}
+    }
+}
+
+
+impl Scenario {
+    /// loads and validates a scenario file, files ending in .json are read as JSON and anything else as TOML
+    ///
+    /// Relative paths to files in the scenario are taken from the directory the scenario is in
+    pub fn load(path: impl AsRef<Path>) -> Result<Scenario, ScenarioError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut scenario: Scenario = if path.extension().map(|extension| extension == "json").unwrap_or(false) {
+            serde_json::from_str(&text).map_err(|err| ScenarioError::Parse(err.to_string()))?
+        } else {
+            toml::from_str(&text).map_err(|err| ScenarioError::Parse(err.to_string()))?
+        };
+        if let Some(directory) = path.parent() {scenario.resolve_paths(directory);}
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// parses and validates a TOML scenario
+    pub fn from_toml_str(text: &str) -> Result<Scenario, ScenarioError> {
+        let scenario: Scenario = toml::from_str(text).map_err(|err| ScenarioError::Parse(err.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// parses and validates a JSON scenario
+    pub fn from_json_str(text: &str) -> Result<Scenario, ScenarioError> {
+        let scenario: Scenario = serde_json::from_str(text).map_err(|err| ScenarioError::Parse(err.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// joins the relative paths to files in the scenario onto the directory
+    fn resolve_paths(&mut self, directory: &Path) {
+        let resolve = |file: &mut String| {
+            if Path::new(file.as_str()).is_relative() {
+                *file = directory.join(file.as_str()).to_string_lossy().into_owned();
+            }
+        };
+        if let Some(TerrainSettings::Heightmap{heightmap, ..}) = &mut self.terrain {resolve(heightmap);}
+        if let Some(ClimateDriverSettings{series: Some(series), ..}) = &mut self.climate {resolve(series);}
+    }
+
+    /// checks every value in the scenario, returning an error naming the first bad field
+    pub fn validate(&self) -> Result<(), ScenarioError> {
+        let sim = &self.simulation;
+        if let Some(time_step) = sim.time_step {check_positive("simulation.time_step", time_step)?;}
+        if let Some(rate) = sim.plant_death_rate {check_at_least_zero("simulation.plant_death_rate", rate)?;}
+        if let Some(gravity) = sim.gravity {check_finite("simulation.gravity", gravity)?;}
+        if let Some(cell_size) = sim.root_cell_size {check_positive("simulation.root_cell_size", cell_size)?;}
+
+        if let Some(terrain) = &self.terrain {
+            match terrain {
+                TerrainSettings::Flat{size, centre} => {
+                    check_positive("terrain.size", *size)?;
+                    check_finite_vector("terrain.centre", *centre)?;
+                }
+                TerrainSettings::Heightmap{size, centre, vertices_per_side, height_scale, heightmap} => {
+                    check_positive("terrain.size", *size)?;
+                    check_finite_vector("terrain.centre", *centre)?;
+                    if *vertices_per_side < 2 {return Err(invalid("terrain.vertices_per_side", "must be at least 2"));}
+                    check_finite("terrain.height_scale", *height_scale)?;
+                    if !Path::new(heightmap).is_file() {return Err(invalid("terrain.heightmap", &format!("no file found at \"{}\"", heightmap)));}
+                }
+            }
+        }
+
+        if let Some(environment) = &self.environment {
+            check_finite("environment.temperature", environment.temperature)?;
+            check_finite("environment.temperature_fall_off", environment.temperature_fall_off)?;
+            check_at_least_zero("environment.moisture", environment.moisture)?;
+        }
+
+        if let Some(light_cells) = &self.light_cells {
+            check_positive("light_cells.size", light_cells.size)?;
+        }
+
+        if let Some(soil) = &self.soil {
+            check_positive("soil.cell_size", soil.cell_size)?;
+            check_at_least_zero("soil.recharge_rate", soil.recharge_rate)?;
+            check_at_least_zero("soil.uptake_rate", soil.uptake_rate)?;
+        }
+
+        if let Some(sun) = &self.sun {
+            check_in_range("sun.latitude", sun.latitude, -90.0, 90.0)?;
+            if let Some(day) = sun.day_of_year {check_in_range("sun.day_of_year", day, 0.0, 366.0)?;}
+        }
+
+        if let Some(sky_dome) = &self.sky_dome {
+            if self.sun.is_some() {return Err(invalid("sky_dome", "only one of sun and sky_dome can be used"));}
+            if sky_dome.rings == 0 {return Err(invalid("sky_dome.rings", "must be at least 1"));}
+            if sky_dome.directions_per_ring == 0 {return Err(invalid("sky_dome.directions_per_ring", "must be at least 1"));}
+        }
+
+        if let Some(ground_light) = &self.ground_light {
+            check_positive("ground_light.cell_size", ground_light.cell_size)?;
+        }
+
+        if let Some(climate) = &self.climate {
+            check_at_least_zero("climate.seasonal_temperature", climate.seasonal_temperature)?;
+            check_in_range("climate.seasonal_precipitation", climate.seasonal_precipitation, 0.0, 1.0)?;
+            check_at_least_zero("climate.temperature_noise", climate.temperature_noise)?;
+            check_at_least_zero("climate.precipitation_noise", climate.precipitation_noise)?;
+            check_finite("climate.warming_rate", climate.warming_rate)?;
+            check_finite("climate.precipitation_trend", climate.precipitation_trend)?;
+            if let Some(series) = &climate.series {
+                if !Path::new(series).is_file() {return Err(invalid("climate.series", &format!("no file found at \"{}\"", series)));}
+            }
+        }
+
+        check_positive("prototype_space.max_apical", self.prototype_space.max_apical)?;
+        check_positive("prototype_space.max_determinacy", self.prototype_space.max_determinacy)?;
+        if self.prototype_space.blend_count == Some(0) {return Err(invalid("prototype_space.blend_count", "must be at least 1"));}
+
+        for (i, prototype) in self.prototypes.iter().enumerate() {
+            check_positive(&format!("prototypes[{}].mature_age", i), prototype.mature_age)?;
+            check_in_range(&format!("prototypes[{}].apical", i), prototype.apical, 0.0, self.prototype_space.max_apical)?;
+            check_in_range(&format!("prototypes[{}].determinacy", i), prototype.determinacy, 0.0, self.prototype_space.max_determinacy)?;
+            BranchPrototypeData::check_shape(&prototype.node_counts, prototype.directions.len())
+                .map_err(|err| invalid(&format!("prototypes[{}].node_counts", i), &err.to_string()))?;
+            for (j, direction) in prototype.directions.iter().enumerate() {
+                let field = format!("prototypes[{}].directions[{}]", i, j);
+                check_finite_vector(&field, *direction)?;
+                if direction.iter().all(|x| *x == 0.0) {return Err(invalid(&field, "must not be zero"));}
+            }
+        }
+
+        if self.species.is_empty() {return Err(invalid("species", "at least one species is needed"));}
+        for (i, species) in self.species.iter().enumerate() {
+            let (growth, plasticity, climate) = species.resolve(i)?;
+            let field = |name: &str| format!("species[{}].growth.{}", i, name);
+            check_positive(&field("max_age"), growth.max_age)?;
+            check_positive(&field("max_vigor"), growth.max_vigor)?;
+            check_positive(&field("min_vigor"), growth.min_vigor)?;
+            if growth.min_vigor > growth.max_vigor {return Err(invalid(&field("min_vigor"), "must not be more than max_vigor"));}
+            check_in_range(&field("apical_control"), growth.apical_control, 0.0, 1.0)?;
+            check_in_range(&field("tropism_angle_weight"), growth.tropism_angle_weight, 0.0, 1.0)?;
+            check_positive(&field("growth_rate"), growth.growth_rate)?;
+            check_positive(&field("max_branch_segment_length"), growth.max_branch_segment_length)?;
+            check_positive(&field("branch_segment_length_scaling_coef"), growth.branch_segment_length_scaling_coef)?;
+            check_positive(&field("tropism_time_control"), growth.tropism_time_control)?;
+            check_positive(&field("branching_angle"), growth.branching_angle)?;
+            check_positive(&field("thickening_factor"), growth.thickening_factor)?;
+
+            let field = |name: &str| format!("species[{}].plasticity.{}", i, name);
+            check_positive(&field("seeding_frequency"), plasticity.seeding_frequency)?;
+            check_at_least_zero(&field("flowering_age"), plasticity.flowering_age)?;
+            check_positive(&field("seeding_radius"), plasticity.seeding_radius)?;
+            check_in_range(&field("shadow_tolerance"), plasticity.shadow_tolerance, 0.0, 1.0)?;
+
+            let field = |name: &str| format!("species[{}].climate.{}", i, name);
+            check_finite(&field("ideal_temperature"), climate.ideal_temperature)?;
+            check_positive(&field("temperature_std_dev"), climate.temperature_std_dev)?;
+            check_finite(&field("ideal_moisture"), climate.ideal_moisture)?;
+            check_positive(&field("moisture_std_dev"), climate.moisture_std_dev)?;
+        }
+
+        Ok(())
+    }
+
+
+    /// returns the prototypes in the form used by the app builders, which is the prototype library if the scenario has none
+    pub fn branch_presets(&self) -> (Vec<PrototypePreset>, PrototypeConditions) {
+        if self.prototypes.is_empty() {return presets::prototype_library();}
+        let prototypes = self.prototypes.iter().map(|prototype| (prototype.mature_age, prototype.node_counts.clone(), prototype.directions.clone())).collect();
+        let conditions = self.prototypes.iter().map(|prototype| (prototype.apical, prototype.determinacy)).collect();
+        (prototypes, (conditions, self.prototype_space.max_apical, self.prototype_space.max_determinacy))
+    }
+
+    /// returns the name of each species, which is the name of its preset if it isn't given one
+    pub fn species_names(&self) -> Vec<Option<String>> {
+        self.species.iter().map(|species| species.name.clone().or_else(|| species.preset.clone())).collect()
+    }
+
+    /// returns the species in the form used by the app builders
+    pub fn plant_species(&self) -> Vec<SpeciesPreset> {
+        self.species.iter().enumerate().map(|(i, species)| {
+            let (growth, plasticity, climate) = species.resolve(i).expect("the scenario has been validated");
+            ((growth, plasticity), (climate.ideal_temperature, climate.temperature_std_dev, climate.ideal_moisture, climate.moisture_std_dev))
+        }).collect()
+    }
+}
+
+
+impl SpeciesSettings {
+    /// fills in anything not given from the preset, i is the index of the species for errors
+    fn resolve(&self, i: usize) -> Result<(GrowthControlSettingParams, PlasticitySettingParams, ClimateSettings), ScenarioError> {
+        let preset = match &self.preset {
+            Some(name) => Some(presets::species(name).ok_or_else(|| invalid(
+                &format!("species[{}].preset", i),
+                &format!("unknown preset `{}`, the presets are {}", name, presets::SPECIES_NAMES.join(", ")),
+            ))?),
+            None => None,
+        };
+        let missing = |name: &str| invalid(&format!("species[{}].{}", i, name), "must be given when the species has no preset");
+
+        let growth = match (&self.growth, &preset) {
+            (Some(growth), _) => growth.clone(),
+            (None, Some(((growth, _), _))) => growth.clone(),
+            (None, None) => return Err(missing("growth")),
+        };
+        let plasticity = match (&self.plasticity, &preset) {
+            (Some(plasticity), _) => plasticity.clone(),
+            (None, Some(((_, plasticity), _))) => plasticity.clone(),
+            (None, None) => return Err(missing("plasticity")),
+        };
+        let climate = match (&self.climate, &preset) {
+            (Some(climate), _) => climate.clone(),
+            (None, Some((_, climate))) => ClimateSettings {
+                ideal_temperature: climate.0,
+                temperature_std_dev: climate.1,
+                ideal_moisture: climate.2,
+                moisture_std_dev: climate.3,
+            },
+            (None, None) => return Err(missing("climate")),
+        };
+        Ok((growth, plasticity, climate))
+    }
+}
+
+
+fn invalid(field: &str, reason: &str) -> ScenarioError {
+    ScenarioError::Invalid {field: field.to_string(), reason: reason.to_string()}
+}
+
+fn check_finite(field: &str, value: f32) -> Result<(), ScenarioError> {
+    if !value.is_finite() {return Err(invalid(field, "must be a finite number"));}
+    Ok(())
+}
+
+fn check_finite_vector(field: &str, value: [f32; 3]) -> Result<(), ScenarioError> {
+    if !value.iter().all(|x| x.is_finite()) {return Err(invalid(field, "must only contain finite numbers"));}
+    Ok(())
+}
+
+fn check_positive(field: &str, value: f32) -> Result<(), ScenarioError> {
+    check_finite(field, value)?;
+    if value <= 0.0 {return Err(invalid(field, &format!("must be greater than 0, found {}", value)));}
+    Ok(())
+}
+
+fn check_at_least_zero(field: &str, value: f32) -> Result<(), ScenarioError> {
+    check_finite(field, value)?;
+    if value < 0.0 {return Err(invalid(field, &format!("must not be negative, found {}", value)));}
+    Ok(())
+}
+
+fn check_in_range(field: &str, value: f32, min: f32, max: f32) -> Result<(), ScenarioError> {
+    check_finite(field, value)?;
+    if value < min || value > max {return Err(invalid(field, &format!("must be between {} and {}, found {}", min, max, value)));}
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod scenario_tests {
+    use super::*;
+
+    const VALID_SCENARIO: &str = r#"
+        [simulation]
+        time_step = 0.5
+        initial_plants = 5
+        seeding = true
+        seed = 7
+
+        [terrain]
+        type = "flat"
+        size = 30.0
+
+        [environment]
+        temperature = 20.0
+        temperature_fall_off = 0.1
+        moisture = 100.0
+
+        [[prototypes]]
+        mature_age = 0.1
+        apical = 10.0
+        determinacy = 10.0
+        node_counts = [[1], [2]]
+        directions = [[0.0, 1.0, 0.0], [0.5, 0.8, 0.0], [-0.5, 0.8, 0.0]]
+
+        [[species]]
+        name = "test"
+        growth = { max_age = 40.0, max_vigor = 10.0, min_vigor = 0.5, apical_control = 0.62, growth_rate = 0.19, tropism_time_control = 0.38, max_branch_segment_length = 1.0, branch_segment_length_scaling_coef = 1.0, tropism_angle_weight = 0.37, branching_angle = 0.52, thickening_factor = 0.05 }
+        plasticity = { seeding_frequency = 0.5, seeding_radius = 5.0, shadow_tolerance = 1.0, flowering_age = 15.0 }
+        climate = { ideal_temperature = 18.0, temperature_std_dev = 5.0, ideal_moisture = 90.0, moisture_std_dev = 15.0 }
+    "#;
+
+    fn invalid_field(text: &str) -> String {
+        match Scenario::from_toml_str(text) {
+            Err(ScenarioError::Invalid{field, ..}) => field,
+            other => panic!("expected an invalid field, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn valid_scenario_test() {
+        let scenario = Scenario::from_toml_str(VALID_SCENARIO).unwrap();
+        assert_eq!(scenario.simulation.seed, Some(7));
+        assert_eq!(scenario.plant_species()[0].1, (18.0, 5.0, 90.0, 15.0));
+        assert_eq!(scenario.branch_presets().1, (vec![(10.0, 10.0)], 20.0, 20.0));
+    }
+
+    #[test]
+    fn invalid_species_field_test() {
+        let text = VALID_SCENARIO.replace("max_vigor = 10.0", "max_vigor = -1.0");
+        assert_eq!(invalid_field(&text), "species[0].growth.max_vigor");
+    }
+
+    #[test]
+    fn invalid_prototype_shape_test() {
+        let text = VALID_SCENARIO.replace("node_counts = [[1], [2]]", "node_counts = [[1], [3]]");
+        assert_eq!(invalid_field(&text), "prototypes[0].node_counts");
+    }
+
+    #[test]
+    fn invalid_climate_series_test() {
+        let text = format!("{}\n[climate]\nseries = \"missing.csv\"", VALID_SCENARIO);
+        assert_eq!(invalid_field(&text), "climate.series");
+    }
+
+    #[test]
+    fn unreadable_climate_series_test() {
+        let path = std::env::temp_dir().join("unreadable_climate_series_test.csv");
+        fs::write(&path, "year,temperature,precipitation\n2000,warm,80\n").unwrap();
+        let text = format!("{}\n[climate]\nseries = {:?}", VALID_SCENARIO, path.to_str().unwrap());
+        let scenario = Scenario::from_toml_str(&text).unwrap();
+
+        match super::super::simulation_core::SimulationCore::new().with_scenario(&scenario) {
+            Err(ScenarioError::Invalid{field, ..}) => assert_eq!(field, "climate.series"),
+            Err(err) => panic!("expected an invalid climate series, got {:?}", err),
+            Ok(_) => panic!("expected an invalid climate series"),
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn example_scenario_test() {
+        let scenario = Scenario::load("assets/scenarios/example.toml").unwrap();
+        assert_eq!(scenario.species[0].name.as_deref(), Some("broadleaf"));
+    }
+
+    #[test]
+    fn relative_path_test() {
+        let directory = std::env::temp_dir().join("relative_path_test").join("scenarios");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("weather.csv"), "2000,10,100\n").unwrap();
+        fs::write(directory.join("scenario.toml"), format!("{}\n[climate]\nseries = \"weather.csv\"", VALID_SCENARIO)).unwrap();
+
+        // the series is found next to the scenario rather than in the working directory
+        let scenario = Scenario::load(directory.join("scenario.toml")).unwrap();
+        assert_eq!(scenario.climate.unwrap().series.map(|series| Path::new(&series).to_path_buf()), Some(directory.join("weather.csv")));
+        fs::remove_dir_all(directory.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn unknown_field_test() {
+        let text = VALID_SCENARIO.replace("time_step", "time_stp");
+        match Scenario::from_toml_str(&text) {
+            Err(ScenarioError::Parse(message)) => assert!(message.contains("time_stp")),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn preset_species_test() {
+        let text = "[[species]]\npreset = \"pine\"\nclimate = { ideal_temperature = 2.0, temperature_std_dev = 4.0, ideal_moisture = 70.0, moisture_std_dev = 20.0 }";
+        let scenario = Scenario::from_toml_str(text).unwrap();
+        let species = &scenario.plant_species()[0];
+        assert_eq!(species.0.0.apical_control, presets::species("pine").unwrap().0.0.apical_control);
+        assert_eq!(species.1, (2.0, 4.0, 70.0, 20.0));
+        assert_eq!(scenario.branch_presets().0.len(), presets::prototype_library().0.len());
+
+        assert_eq!(invalid_field("[[species]]\npreset = \"palm\""), "species[0].preset");
+        assert_eq!(invalid_field("[[species]]\nname = \"bare\""), "species[0].growth");
+
+        assert_eq!(Scenario::load("assets/scenarios/temperate.toml").unwrap().plant_species().len(), 4);
+    }
+}
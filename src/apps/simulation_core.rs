@@ -0,0 +1,672 @@
+//! The world and schedule setup shared by all the tree apps
+//!
+//! This can also be used to add the growth simulation to an existing bevy_ecs World and Schedule
+
+use super::{*, presets, scenario::{Scenario, ScenarioError, TerrainSettings}, export::{LightSampler, sample_light}, super::{
+    environment::{
+        terrain::*,
+        light_cells::*,
+        soil_cells::*,
+        soil_grid::*,
+        climate::*,
+        sun_path::SunPath,
+        sky_dome::SkyDome,
+        ground_light::*,
+        params::*,
+    },
+    plants::{plant_selection::*, plant_statistics::*},
+    roots::{root::*, root_development::*},
+    maths::vector_three::Vector3,
+}};
+#[cfg(feature = "vulkan_graphics")]
+use super::super::graphics::branch_mesh_gen::MeshUpdateQueue;
+use bevy_ecs::{prelude::*, system::BoxedSystem};
+
+
+/// The sets the growth systems are added to, these run in order of declaration
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    /// bounds, intersections, light, root uptake, vigor, trimming and aging
+    Growth,
+    /// new nodes, new branches, thicknesses and node positions, and root growth if roots are enabled
+    Development,
+    /// sampling the ground light if it is enabled, then plant reproduction if seeding is enabled
+    Seeding,
+    /// collecting the statistics of each species
+    Statistics,
+}
+
+
+/// The light models that can replace the light from straight above
+#[derive(Clone)]
+enum LightModelSettings {
+    SunPath(SunPath),
+    SkyDome(SkyDome),
+}
+
+
+/// the verts per side, height multiplier and path of a heightmap
+type HeightmapSettings = (u32, f32, String);
+
+/// a (temperature, precipitation) pair of climate settings
+type ClimatePair = (f32, f32);
+
+
+/// Settings for the plant simulation, used to populate a World and Schedule with everything the growth systems need
+#[derive(Default)]
+pub struct SimulationCore {
+    has_terrain: bool,
+    terrain_settings: Option<(f32, Vector3, Option<HeightmapSettings>)>, // size, centre, heightmap
+
+    gravity_strength: Option<f32>,
+    time_step: Option<f32>,
+    cell_settings: Option<(u32, f32)>,
+    plant_death_rate: Option<f32>,
+    environmental_params: Option<(f32, f32, f32)>, // temp at y=0, temp falloff, moisture
+
+    prototype_conditions: Option<PrototypeConditions>,
+    prototypes: Option<Vec<PrototypePreset>>,
+    start_plants: u32,
+    plant_species: Option<Vec<SpeciesPreset>>,
+    species_names: Option<Vec<Option<String>>>,
+    has_seeding: bool,
+    seed: Option<u64>,
+    prototype_blend_count: Option<u32>,
+    has_roots: bool,
+    root_settings: Option<(f32, f32)>, // segment length, hydrotropism
+    root_cell_size: Option<f32>,
+    soil_settings: Option<(f32, f32, f32)>, // cell size, recharge rate, uptake rate
+    climate_settings: Option<(ClimatePair, ClimatePair, ClimatePair)>, // seasons, noise, trends
+    climate_series: Option<ClimateSeries>,
+    light_model: Option<LightModelSettings>,
+    ground_light_size: Option<f32>,
+}
+
+
+impl SimulationCore {
+    /// creates a new set of simulation settings, all set to their defaults
+    ///
+    /// Please note that if no terrain is specified, plants will not be able to reproduce and initial plants will be spawned on a 50m by 50m square at y=0
+    pub fn new() -> Self {
+        SimulationCore::default()
+    }
+
+    /// uses flat terrain, overrides previous terrain settings
+    pub fn with_flat_terrain(&mut self, size: f32, centre: [f32; 3]) -> &mut SimulationCore {
+        self.has_terrain = true;
+        self.terrain_settings = Some((size, centre.into(), None));
+        self
+    }
+
+    /// uses heightmap based terrain, overrides previous terrain settings
+    pub fn with_heightmap_terrain(&mut self, size: f32, centre: [f32; 3], verts_per_side: u32, height_scale: f32, heightmap_path: &str) -> &mut SimulationCore {
+        self.has_terrain = true;
+        self.terrain_settings = Some((size, centre.into(), Some((verts_per_side, height_scale, heightmap_path.into()))));
+        self
+    }
+
+    /// sets the strength of gravity and the stength, a negative strength represents phototropism, a positive gravitropism
+    ///
+    /// the default value is 1
+    pub fn set_gravity(&mut self, strength: f32) -> &mut SimulationCore {
+        self.gravity_strength = Some(strength);
+        self
+    }
+
+    /// sets the branch presets used for the simulation, overrides the default set of branches used
+    ///
+    /// Returns an error if there are no prototypes, one of them is invalid, or there isn't a position for each of them
+    pub fn set_branch_presets(&mut self, prototypes: Vec<PrototypePreset>, conditions: PrototypeConditions) -> Result<&mut SimulationCore, PrototypeError> {
+        BranchPrototypes::validate(&prototypes)?;
+        if conditions.0.len() != prototypes.len() {
+            return Err(PrototypeError::ConditionCount {prototypes: prototypes.len(), conditions: conditions.0.len()});
+        }
+        self.prototypes = Some(prototypes);
+        self.prototype_conditions = Some(conditions);
+        Ok(self)
+    }
+
+    /// sets the plant species used for the simulation, overrides default set of plant species used
+    ///
+    /// Plant species are used for initial plant spawning and spawning of new plants without seeding
+    pub fn set_plant_species(&mut self, species: Vec<SpeciesPreset>) -> &mut SimulationCore {
+        self.plant_species = Some(species);
+        self.species_names = None;
+        self
+    }
+
+    /// names the plant species in the order they were set, the names are written in the statistics
+    ///
+    /// Setting the plant species again removes the names, species presets are named after their preset
+    pub fn set_species_names(&mut self, names: Vec<String>) -> &mut SimulationCore {
+        self.species_names = Some(names.into_iter().map(Some).collect());
+        self
+    }
+
+    /// sets the plant species used for the simulation to the named species presets, see the presets module for the names
+    ///
+    /// Panics if a name isn't one of the presets
+    pub fn set_preset_species(&mut self, names: &[&str]) -> &mut SimulationCore {
+        let species = names.iter().map(|name| presets::species(name)
+            .unwrap_or_else(|| panic!("there is no species preset named \"{}\", the presets are {:?}", name, presets::SPECIES_NAMES))
+        ).collect();
+        self.set_plant_species(species);
+        self.set_species_names(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    /// set how many plants are spawned at the start of the simulation
+    pub fn set_initial_plant_num(&mut self, num: u32) -> &mut SimulationCore {
+        self.start_plants = num;
+        self
+    }
+
+    /// sets the size of the cell grid used to shadow distribution, the default is 1m^3. And the number of cells above or below that will be checked for shadow distribution purposes
+    pub fn set_shadow_cell_data(&mut self, size: f32, check_height: u32) -> &mut SimulationCore {
+        self.cell_settings = Some((check_height, size.abs()));
+        self
+    }
+
+    /// sets the physical time step used for plant aging in years per schedule update, defaults to 1.0
+    pub fn set_time_step(&mut self, step: f32) -> &mut SimulationCore {
+        self.time_step = Some(step.abs());
+        self
+    }
+
+    /// sets the rate at which plants die, defaults to 1.0
+    pub fn set_plant_death_rate(&mut self, rate: f32) -> &mut SimulationCore {
+        self.plant_death_rate = Some(rate.abs());
+        self
+    }
+
+    /// sets the environmental parameters used by the simulation
+    ///
+    /// - Temperature: (Degrees Celsius at a height of y=0, rate of temperature decrease away from y=0)
+    /// - Moisture: Average annual precipitation, cm
+    pub fn set_environmental_parameters(&mut self, temperature: (f32, f32), moisture: f32) -> &mut SimulationCore {
+        self.environmental_params = Some((temperature.0, temperature.1, moisture));
+        self
+    }
+
+    /// - Enables plant seeding, meaning that plants will reproduce
+    /// - This is disabled by default
+    /// - Has no effect without initial plants
+    pub fn enable_seeding(&mut self) -> &mut SimulationCore {
+        self.has_seeding = true;
+        self
+    }
+
+    /// - Enables root growth below the terrain, the roots compete for soil resources which limits the vigor of their plants
+    /// - This is disabled by default
+    /// - The roots compete in soil cells 0.5m across, which can be changed with set_root_cell_size
+    pub fn enable_roots(&mut self) -> &mut SimulationCore {
+        self.has_roots = true;
+        self
+    }
+
+    /// sets the distance between root nodes in m, defaults to 0.2, and how strongly roots turn towards soil resources, defaults to 1
+    pub fn set_root_growth(&mut self, segment_length: f32, hydrotropism: f32) -> &mut SimulationCore {
+        self.root_settings = Some((segment_length.abs(), hydrotropism));
+        self
+    }
+
+    /// sets the side length in m of the soil cells the roots compete for resources in, defaults to 0.5 and can't be less than 0.01
+    pub fn set_root_cell_size(&mut self, cell_size: f32) -> &mut SimulationCore {
+        self.root_cell_size = Some(cell_size.abs().max(MIN_CELL_SIZE));
+        self
+    }
+
+    /// sets the soil grid, which holds the moisture and nutrients under the plants
+    ///
+    /// - Cell size: the side length of each cell in m, defaults to 2 and can't be less than 0.01
+    /// - Recharge rate: the fraction of the missing moisture restored by precipitation each year, defaults to 0.5
+    /// - Uptake rate: the cm of moisture used each year for every m^3 of plant, defaults to 0.2
+    pub fn set_soil_grid(&mut self, cell_size: f32, recharge_rate: f32, uptake_rate: f32) -> &mut SimulationCore {
+        self.soil_settings = Some((cell_size.abs().max(MIN_CELL_SIZE), recharge_rate, uptake_rate));
+        self
+    }
+
+    /// makes the temperature and precipitation change over time, starting from the environmental parameters
+    ///
+    /// Each setting is a pair of (temperature, precipitation):
+    /// - Seasons: how far the temperature moves either side of the annual mean over the year, and the fraction of the precipitation that does
+    /// - Noise: the standard deviation of each year's difference from the mean, for precipitation as a fraction of the mean
+    /// - Trends: the change in the mean each year, in degrees celsius and cm
+    ///
+    /// Plants adapt to the annual climate as it changes and barely grow in cold seasons, seasons are only seen with steps shorter than a year
+    pub fn set_climate(&mut self, seasons: (f32, f32), noise: (f32, f32), trends: (f32, f32)) -> &mut SimulationCore {
+        self.climate_settings = Some((seasons, noise, trends));
+        self
+    }
+
+    /// replays recorded weather from a csv file instead of using a procedural climate, the simulation starts at the beginning of the first year in the file
+    ///
+    /// The rows are all (year, temperature, precipitation) or all (year, month, temperature, precipitation),
+    /// yearly rows give the annual precipitation and monthly rows the precipitation of the month, in cm.
+    /// The temperature and precipitation are interpolated between rows, and the temperature fall off still comes from the environmental parameters
+    ///
+    /// The file is read straight away, returning an error if it can't be read or has no samples
+    pub fn set_climate_series(&mut self, path: &str) -> Result<&mut SimulationCore, ClimateSeriesError> {
+        self.climate_series = Some(ClimateSeries::load(path)?);
+        Ok(self)
+    }
+
+    /// lights the plants from the path of the sun at a latitude in degrees instead of from straight above, north is positive
+    ///
+    /// The sun is sampled every two hours on the given day of the year, or on the equinoxes and solstices without one
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sun_path(&mut self, latitude: f32, day_of_year: Option<f32>) -> &mut SimulationCore {
+        self.light_model = Some(LightModelSettings::SunPath(SunPath::from_location(latitude, day_of_year)));
+        self
+    }
+
+    /// lights the plants from a list of directions towards the light and their weights instead of from straight above
+    ///
+    /// +x is east, +y is up and +z is south, directions that don't point up are left out
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_light_directions(&mut self, directions: Vec<([f32; 3], f32)>) -> &mut SimulationCore {
+        let directions = directions.into_iter().map(|(direction, weight)| (direction.into(), weight)).collect();
+        self.light_model = Some(LightModelSettings::SunPath(SunPath::from_directions(directions)));
+        self
+    }
+
+    /// lights the plants with diffuse light from the whole sky instead of from straight above,
+    /// using rings of directions between the horizon and straight up with the given number of directions in each
+    ///
+    /// More directions are smoother but slower, 3 rings of 8 is a good start
+    ///
+    /// Only one light model is used, so the last one set replaces any set before it
+    pub fn set_sky_dome(&mut self, rings: u32, directions_per_ring: u32) -> &mut SimulationCore {
+        self.light_model = Some(LightModelSettings::SkyDome(SkyDome::new(rings, directions_per_ring)));
+        self
+    }
+
+    /// samples the light reaching the terrain each step in a grid of square cells with the given side length in m, at least 0.01, using the light model of the plants
+    ///
+    /// When seeding, the ground light is the chance of a seedling surviving, which shade tolerant species are less affected by
+    pub fn set_ground_light(&mut self, cell_size: f32) -> &mut SimulationCore {
+        self.ground_light_size = Some(cell_size.abs().max(MIN_CELL_SIZE));
+        self
+    }
+
+    /// sets the seed used for all the random parts of the simulation,
+    /// the same seed and settings will always give the same plants
+    ///
+    /// Without a seed, every simulation will be different
+    pub fn set_seed(&mut self, seed: u64) -> &mut SimulationCore {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// applies every setting in the scenario apart from the time step, which depends on the app
+    ///
+    /// Returns an error if a file the scenario names can't be read
+    pub fn with_scenario(&mut self, scenario: &Scenario) -> Result<&mut SimulationCore, ScenarioError> {
+        let sim = &scenario.simulation;
+        self.set_initial_plant_num(sim.initial_plants);
+        if sim.seeding {self.enable_seeding();}
+        if sim.roots {self.enable_roots();}
+        if let Some(cell_size) = sim.root_cell_size {self.set_root_cell_size(cell_size);}
+        if let Some(seed) = sim.seed {self.set_seed(seed);}
+        if let Some(gravity) = sim.gravity {self.set_gravity(gravity);}
+        if let Some(rate) = sim.plant_death_rate {self.set_plant_death_rate(rate);}
+
+        match &scenario.terrain {
+            Some(TerrainSettings::Flat{size, centre}) => {self.with_flat_terrain(*size, *centre);}
+            Some(TerrainSettings::Heightmap{size, centre, vertices_per_side, height_scale, heightmap}) => {
+                self.with_heightmap_terrain(*size, *centre, *vertices_per_side, *height_scale, heightmap);
+            }
+            None => {}
+        }
+
+        if let Some(environment) = &scenario.environment {
+            self.set_environmental_parameters((environment.temperature, environment.temperature_fall_off), environment.moisture);
+        }
+        if let Some(light_cells) = &scenario.light_cells {
+            self.set_shadow_cell_data(light_cells.size, light_cells.check_height);
+        }
+        if let Some(soil) = &scenario.soil {
+            self.set_soil_grid(soil.cell_size, soil.recharge_rate, soil.uptake_rate);
+        }
+        if let Some(sun) = &scenario.sun {
+            self.set_sun_path(sun.latitude, sun.day_of_year);
+        }
+        if let Some(sky_dome) = &scenario.sky_dome {
+            self.set_sky_dome(sky_dome.rings, sky_dome.directions_per_ring);
+        }
+        if let Some(ground_light) = &scenario.ground_light {
+            self.set_ground_light(ground_light.cell_size);
+        }
+        if let Some(climate) = &scenario.climate {
+            self.set_climate(
+                (climate.seasonal_temperature, climate.seasonal_precipitation),
+                (climate.temperature_noise, climate.precipitation_noise),
+                (climate.warming_rate, climate.precipitation_trend),
+            );
+            if let Some(series) = &climate.series {
+                self.set_climate_series(series).map_err(|err| ScenarioError::Invalid{field: "climate.series".into(), reason: err.to_string()})?;
+            }
+        }
+
+        let (prototypes, conditions) = scenario.branch_presets();
+        self.set_branch_presets(prototypes, conditions).map_err(|err| ScenarioError::Invalid{field: "prototypes".into(), reason: err.to_string()})?;
+        if let Some(count) = scenario.prototype_space.blend_count {self.set_prototype_blending(count);}
+        self.set_plant_species(scenario.plant_species());
+        self.species_names = Some(scenario.species_names());
+        Ok(self)
+    }
+
+    /// sets how many of the nearest branch prototypes are blended together to make new branches, defaults to 1 which means no blending
+    ///
+    /// Only prototypes with the same node counts as the nearest one are blended, differently shaped ones are left out rather than resampled.
+    /// Blending has no effect where the nearest prototype has a shape that none of the others share
+    pub fn set_prototype_blending(&mut self, count: u32) -> &mut SimulationCore {
+        self.prototype_blend_count = Some(count.max(1));
+        self
+    }
+
+    /// returns the physical time step in years per schedule update
+    pub fn time_step(&self) -> f32 {
+        self.time_step.unwrap_or(DEFAULT_TIMESTEP)
+    }
+
+    /// returns the light exposure system for the chosen light model
+    fn light_exposure_system(&self) -> BoxedSystem {
+        match self.light_model {
+            Some(LightModelSettings::SunPath(_)) => Box::new(IntoSystem::into_system(calculate_branch_light_exposure::<SunPath>)),
+            Some(LightModelSettings::SkyDome(_)) => Box::new(IntoSystem::into_system(calculate_branch_light_exposure::<SkyDome>)),
+            None => Box::new(IntoSystem::into_system(calculate_branch_light_exposure::<ColumnLight>)),
+        }
+    }
+
+    /// returns the function that samples the chosen light model, for exporting the light
+    pub(super) fn light_sampler(&self) -> LightSampler {
+        match self.light_model {
+            Some(LightModelSettings::SunPath(_)) => sample_light::<SunPath>,
+            Some(LightModelSettings::SkyDome(_)) => sample_light::<SkyDome>,
+            None => sample_light::<ColumnLight>,
+        }
+    }
+
+    /// returns the ground light system for the chosen light model
+    fn ground_light_system(&self) -> BoxedSystem {
+        match self.light_model {
+            Some(LightModelSettings::SunPath(_)) => Box::new(IntoSystem::into_system(update_ground_light::<SunPath>)),
+            Some(LightModelSettings::SkyDome(_)) => Box::new(IntoSystem::into_system(update_ground_light::<SkyDome>)),
+            None => Box::new(IntoSystem::into_system(update_ground_light::<ColumnLight>)),
+        }
+    }
+
+    #[cfg(feature = "vulkan_graphics")]
+    pub(super) fn terrain_type(&self) -> TerrainType {
+        match &self.terrain_settings {
+            Some((_, _, None)) if self.has_terrain => TerrainType::Flat,
+            Some((_, _, Some(_))) if self.has_terrain => TerrainType::Bumpy,
+            _ => TerrainType::Absent,
+        }
+    }
+
+
+
+    /// adds all the resources, terrain and initial plants needed by the simulation to the world
+    ///
+    /// Returns the ids of the root branches of the initial plants
+    pub fn populate_world(&self, world: &mut World) -> Vec<Entity> {
+
+        // transform data
+        let gravity_strength = self.gravity_strength.unwrap_or(DEFAULT_GRAVITY_STRENGTH);
+        let time_step = self.time_step();
+        let (branch_types, branch_conditions) = match (self.prototypes.clone(), self.prototype_conditions.clone()) {
+            (Some(prototypes), Some(conditions)) => (prototypes, conditions),
+            _ => presets::prototype_library(),
+        };
+        let cell_settings = self.cell_settings.unwrap_or(DEFAULT_CELL_SETTINGS);
+        let plant_death_rate = self.plant_death_rate.unwrap_or(DEFAULT_PLANT_DEATH_RATE);
+        let has_plants = self.start_plants > 0;
+        let plant_species = self.plant_species.clone().unwrap_or_else(presets::all_species);
+        let mut environmental_params = self.environmental_params.unwrap_or(DEFAULT_ENVIRONMENTAL_PARAMS);
+        let climate = match (&self.climate_series, self.climate_settings) {
+            (Some(series), _) => {
+                let climate = ClimateDriver::from_series(series.clone());
+                // the initial plants and soil use the climate of the first year
+                (environmental_params.0, environmental_params.2) = (climate.mean_temperature, climate.mean_precipitation);
+                Some(climate)
+            }
+            (None, Some((seasons, noise, trends))) => {
+                let mut climate = ClimateDriver::new(environmental_params.0, environmental_params.2);
+                (climate.seasonal_temperature, climate.seasonal_precipitation) = seasons;
+                (climate.temperature_noise, climate.precipitation_noise) = noise;
+                (climate.warming_rate, climate.precipitation_trend) = trends;
+                Some(climate)
+            }
+            (None, None) => None,
+        };
+        let soil_settings = self.soil_settings.unwrap_or(DEFAULT_SOIL_SETTINGS);
+
+        let mut branch_sampler = BranchPrototypesSampler::create(branch_conditions.0, branch_conditions.1, branch_conditions.2);
+        branch_sampler.blend_count = self.prototype_blend_count.unwrap_or(1).max(1) as usize;
+        let mut plant_species_sampler = PlantSpeciesSampler::new(plant_species, time_step);
+        match (&self.plant_species, &self.species_names) {
+            (None, _) => plant_species_sampler.set_names(presets::SPECIES_NAMES.iter().map(|name| Some(name.to_string())).collect()),
+            (Some(_), Some(names)) => plant_species_sampler.set_names(names.clone()),
+            (Some(_), None) => {}
+        }
+
+        ///////////////// resources
+        create_gravity_resource(world, [0, -1, 0], gravity_strength);
+        create_physical_age_time_step(world, time_step);
+        create_simulation_rng(world, self.seed);
+
+        world.insert_resource(BranchPrototypes::new(branch_types).expect("the prototypes are validated when they are set"));
+        match self.light_model.clone() {
+            Some(LightModelSettings::SunPath(sun_path)) => world.insert_resource(sun_path),
+            Some(LightModelSettings::SkyDome(sky_dome)) => world.insert_resource(sky_dome),
+            None => world.insert_resource(ColumnLight),
+        }
+        world.insert_resource(PlantDeathRate::new(plant_death_rate));
+        if self.has_roots {
+            let root_settings = self.root_settings.unwrap_or(DEFAULT_ROOT_SETTINGS);
+            world.insert_resource(SoilCells::new(self.root_cell_size.unwrap_or(DEFAULT_ROOT_CELL_SIZE)));
+            world.insert_resource(RootGrowthSettings::new(root_settings.0, root_settings.1));
+        }
+
+
+        let (plant_spawning_bounds, terrain_collider_ref) = {
+            if self.has_terrain {
+                let settings = self.terrain_settings.clone().unwrap();
+                if settings.2.is_none() {
+                    spawn_flat_terrain(settings.0, settings.1, world)
+                }
+                else {
+                    let subsettings = settings.2.clone().unwrap();
+                    spawn_heightmap_terrain(settings.0, subsettings.0, subsettings.1, settings.1, subsettings.2, world)
+                }
+            }
+            else {
+                spawn_flat_terrain(DEFAULT_TERRAIN.0, DEFAULT_TERRAIN.1, world)
+            }
+        };
+
+        // the light cells cover the terrain, with a margin for branches that grow past its edge
+        let mut light_cells = LightCells::new(cell_settings.0 as i32, cell_settings.1);
+        light_cells.set_horizontal_bounds(
+            (plant_spawning_bounds.1.start() - LIGHT_CELL_MARGIN, plant_spawning_bounds.1.end() + LIGHT_CELL_MARGIN),
+            (plant_spawning_bounds.2.start() - LIGHT_CELL_MARGIN, plant_spawning_bounds.2.end() + LIGHT_CELL_MARGIN),
+        );
+        world.insert_resource(light_cells);
+        if let Some(cell_size) = self.ground_light_size {
+            world.insert_resource(GroundLight::new(
+                (*plant_spawning_bounds.1.start(), *plant_spawning_bounds.1.end()),
+                (*plant_spawning_bounds.2.start(), *plant_spawning_bounds.2.end()),
+                cell_size,
+            ));
+        }
+
+        // the soil covers the terrain, starting at the precipitation level
+        let soil_grid = SoilGrid::new(
+            (*plant_spawning_bounds.1.start(), *plant_spawning_bounds.1.end()),
+            (*plant_spawning_bounds.2.start(), *plant_spawning_bounds.2.end()),
+            soil_settings.0,
+            environmental_params.2,
+            soil_settings.1,
+            soil_settings.2,
+        );
+
+
+        // spawn initial plant(s)
+
+        let mut initial_plant_data = Vec::new();
+        let rng = &mut world.resource_mut::<SimulationRng>().rng;
+
+        for _ in 0..self.start_plants {
+
+            let (x, z) = (rng.gen_range(plant_spawning_bounds.1.clone()), rng.gen_range(plant_spawning_bounds.2.clone()));
+
+            let hit = terrain_collider_ref.check_ray([x, plant_spawning_bounds.0 + 5.0, z], [0, -1, 0], None).unwrap();
+
+            initial_plant_data.push((plant_species_sampler.get_plant(environmental_params.0 + hit.hit_position.y * environmental_params.1, soil_grid.get_moisture(hit.hit_position), rng), hit))
+        }
+
+        let mut root_ids = Vec::new();
+        for data in initial_plant_data {
+
+            if let (Some((species, spawn_data, climate_adapt)), hit) = data {
+
+                let root_node_id = world.spawn(BranchNodeBundle{
+                    data: BranchNodeData{
+                        thickening_factor: spawn_data.0.thickening_factor,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }).id();
+
+                let root_branch_id = world.spawn(BranchBundle{
+                    data: BranchData {
+                        root_node: Some(root_node_id),
+                        root_position: hit.hit_position,
+                        normal: hit.hit_normal,
+                        ..Default::default()
+                    },
+                    prototype: BranchPrototypeRef(branch_sampler.get_prototype_index(spawn_data.0.apical_control, branch_conditions.2)),
+                    ..Default::default()
+                }).id();
+
+                world.spawn(PlantBundle{
+                    growth_factors: spawn_data.0,
+                    data: PlantData {
+                        root_node: Some(root_branch_id),
+                        position: hit.hit_position,
+                        climate_adaption: climate_adapt,
+                        ..Default::default()
+                    },
+                    plasticity_params: spawn_data.1,
+                    ..Default::default()
+                }).insert(species);
+
+                root_ids.push(root_branch_id);
+            }
+        }
+        world.insert_resource(branch_sampler);
+
+        if has_plants && root_ids.is_empty() {panic!("No intial plants generated")}
+
+        // the growth systems push updated branches to a mesh queue when graphics are compiled in
+        #[cfg(feature = "vulkan_graphics")]
+        world.spawn(MeshUpdateQueue::new_from_many(root_ids.clone(), 5));
+
+        world.insert_resource(plant_species_sampler);
+        world.insert_resource(soil_grid);
+        if let Some(climate) = climate {
+            world.insert_resource(climate);
+        }
+        world.insert_resource(MoistureAndTemp {
+            moisture: environmental_params.2,
+            temp_at_zero: environmental_params.0,
+            temp_fall_off: environmental_params.1,
+        });
+        create_forest_statistics(world, None, 0.0);
+
+        root_ids
+    }
+
+
+    /// adds the growth systems to the schedule, in the sets described by SimulationSet
+    ///
+    /// Does nothing if there are no initial plants
+    pub fn add_systems(&self, schedule: &mut Schedule) {
+        if self.start_plants == 0 {return;}
+
+        schedule.configure_sets((
+            SimulationSet::Growth,
+            SimulationSet::Development,
+            SimulationSet::Seeding,
+            SimulationSet::Statistics,
+        ).chain());
+
+        schedule.add_systems((
+            update_branch_bounds,
+            update_plant_bounds,
+            update_soil_grid,
+            update_plant_intersections,
+            update_branch_intersections,
+            calculate_branch_intersection_volumes,
+            // debug_log_branches,
+            // debug_log_cells,
+            step_plant_age,
+            self.light_exposure_system(),
+            calculate_growth_vigor,
+            trim_branches,
+            apply_system_buffers, // this makes sure nodes and branches have been removed
+            remove_dead_connections,
+            assign_growth_rates,
+            step_physiological_age,
+        ).chain().in_set(SimulationSet::Growth));
+
+        schedule.add_systems((
+            update_branch_nodes,
+            apply_system_buffers, // this makes sure new nodes are spawned
+            determine_create_new_branches,
+            apply_system_buffers, // this makes sure new branches are spawned
+            assign_thicknesses,
+            calculate_segment_lengths_and_tropism,
+        ).chain().in_set(SimulationSet::Development));
+
+        if self.has_roots {
+            schedule.add_systems((
+                spawn_plant_roots,
+                apply_system_buffers, // this makes sure new plants have roots
+                calculate_root_uptake,
+            ).chain().in_set(SimulationSet::Growth).before(calculate_growth_vigor));
+            // plants that died while growing are gone by now, so their roots are removed in the same step
+            schedule.add_systems((
+                remove_dead_roots,
+                grow_roots,
+            ).in_set(SimulationSet::Development));
+        }
+
+        // the soil moisture changes every step, so plants adapt to it with or without a changing climate
+        schedule.add_system(update_climate_adaption.after(update_soil_grid).before(calculate_growth_vigor).in_set(SimulationSet::Growth));
+
+        if self.climate_settings.is_some() || self.climate_series.is_some() {
+            schedule.add_systems((
+                update_climate.before(update_soil_grid),
+                apply_temperature_to_growth_rates.after(assign_growth_rates).before(step_physiological_age),
+            ).in_set(SimulationSet::Growth));
+        }
+
+        // the light cells were filled while growing, so the ground light is ready before seeding
+        if self.ground_light_size.is_some() {
+            schedule.add_system(self.ground_light_system().in_set(SimulationSet::Seeding).before(seed_plants));
+        }
+
+        if self.has_seeding {
+            schedule.add_system(seed_plants.in_set(SimulationSet::Seeding));
+        }
+
+        schedule.add_systems((
+            apply_system_buffers, // this makes sure seeded plants are spawned
+            collect_forest_statistics,
+        ).chain().in_set(SimulationSet::Statistics));
+    }
+}
@@ -0,0 +1,265 @@
+//! Runs a scenario without any graphics and writes the results to an output directory
+//!
+//! Every output interval, and after the last step, a folder named after the step is made containing
+//! the plant skeletons, the meshes in the chosen format and a checkpoint of the simulation.
+//! stats.csv has a row of statistics for every species after every step
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use synthetic_silviculture::apps::{
+    looped_app::*,
+    scenario::Scenario,
+};
+
+
+const USAGE: &str = "\
+usage: silviculture <scenario> [options]
+
+runs a scenario file (toml, or json with a .json extension) headless
+
+options:
+    --years <years>       how many years to run for to the nearest step, defaults to 50
+    --steps <steps>       how many steps to run for, instead of a number of years
+    --seed <seed>         the random seed, overrides the seed in the scenario
+    --interval <steps>    write outputs every this many steps, defaults to only the last step
+    --format <format>     the mesh format: obj, gltf, both or none, defaults to gltf
+    --out <dir>           the output directory, defaults to ./output
+    -h, --help            prints this message";
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum MeshFormat {
+    Obj,
+    Gltf,
+    Both,
+    Absent,
+}
+
+/// how long the simulation runs for
+enum Duration {
+    Years(f32),
+    Steps(u32),
+}
+
+struct Args {
+    scenario: PathBuf,
+    duration: Duration,
+    seed: Option<u64>,
+    interval: Option<u32>,
+    format: MeshFormat,
+    out: PathBuf,
+}
+
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+
+/// parses the command line arguments, returns none if help was asked for
+fn parse_args(args: Vec<String>) -> Result<Option<Args>, String> {
+    let mut scenario = None;
+    let mut years = None;
+    let mut steps = None;
+    let mut seed = None;
+    let mut interval = None;
+    let mut format = MeshFormat::Gltf;
+    let mut out = PathBuf::from("output");
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--years" => years = Some(parse_number::<f32>("--years", value("--years")?)?),
+            "--steps" => steps = Some(parse_number::<u32>("--steps", value("--steps")?)?),
+            "--seed" => seed = Some(parse_number::<u64>("--seed", value("--seed")?)?),
+            "--interval" => interval = Some(parse_number::<u32>("--interval", value("--interval")?)?),
+            "--out" => out = PathBuf::from(value("--out")?),
+            "--format" => format = match value("--format")?.as_str() {
+                "obj" => MeshFormat::Obj,
+                "gltf" | "glb" => MeshFormat::Gltf,
+                "both" => MeshFormat::Both,
+                "none" => MeshFormat::Absent,
+                other => return Err(format!("unknown format \"{}\", expected obj, gltf, both or none", other)),
+            },
+            other if other.starts_with('-') => return Err(format!("unknown option \"{}\"", other)),
+            other => {
+                if scenario.is_some() {return Err(format!("unexpected argument \"{}\"", other));}
+                scenario = Some(PathBuf::from(other));
+            }
+        }
+    }
+
+    let duration = match (years, steps) {
+        (Some(_), Some(_)) => return Err("only one of --years and --steps can be used".to_string()),
+        (Some(years), None) if years.is_finite() && years >= 0.0 => Duration::Years(years),
+        (Some(years), None) => return Err(format!("--years must be a positive number, found {}", years)),
+        (None, Some(steps)) => Duration::Steps(steps),
+        (None, None) => Duration::Years(50.0),
+    };
+    if interval == Some(0) {return Err("--interval must be at least 1".to_string());}
+
+    Ok(Some(Args {
+        scenario: scenario.ok_or("no scenario file given".to_string())?,
+        duration,
+        seed,
+        interval,
+        format,
+        out,
+    }))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} expects a number, found \"{}\"", name, value))
+}
+
+
+/// builds the app from the scenario and runs it, writing the outputs
+fn run(args: &Args) -> Result<(), String> {
+    let scenario = Scenario::load(&args.scenario).map_err(|err| format!("{}: {}", args.scenario.display(), err))?;
+
+    let mut builder = LoopedTreeApp::new();
+    builder.with_scenario(&scenario).map_err(|err| format!("{}: {}", args.scenario.display(), err))?;
+    if let Some(seed) = args.seed {builder.set_seed(seed);}
+    let mut app = builder.build();
+
+    fs::create_dir_all(&args.out).map_err(|err| format!("failed to create {}: {}", args.out.display(), err))?;
+
+    // the number of steps is worked out once, so that rounding errors in the elapsed years can't add or drop a step
+    let steps = match args.duration {
+        Duration::Years(years) if app.time_step() > 0.0 => (years / app.time_step()).round() as u32,
+        Duration::Years(_) => 0,
+        Duration::Steps(steps) => steps,
+    };
+
+    // outputs are written every interval and after the last step
+    let interval = args.interval.unwrap_or(steps).max(1);
+    let mut step: u32 = 0;
+    while step < steps {
+        let chunk = interval.min(steps - step);
+        app.run_steps(chunk);
+        step += chunk;
+        write_outputs(&mut app, &args.out, step, args.format)?;
+    }
+    if steps == 0 {
+        write_outputs(&mut app, &args.out, step, args.format)?;
+    }
+
+    println!("ran {} steps ({} years), outputs written to {}", step, app.elapsed_years(), args.out.display());
+    Ok(())
+}
+
+/// writes the skeletons, meshes and a checkpoint to a folder for the step
+fn write_outputs(app: &mut LoopedTreeApp, out: &Path, step: u32, format: MeshFormat) -> Result<(), String> {
+    let dir = out.join(format!("step_{:06}", step));
+    let failed = |path: PathBuf, err: &dyn std::fmt::Display| format!("failed to write {}: {}", path.display(), err);
+    fs::create_dir_all(&dir).map_err(|err| failed(dir.clone(), &err))?;
+
+    // the statistics file has every step so far, so it is rewritten each time
+    app.export_statistics(out.join("stats.csv")).map_err(|err| failed(out.join("stats.csv"), &err))?;
+
+    app.export_skeletons(dir.join("skeletons.json")).map_err(|err| failed(dir.join("skeletons.json"), &err))?;
+    if format == MeshFormat::Obj || format == MeshFormat::Both {
+        app.export_obj(dir.join("forest.obj")).map_err(|err| failed(dir.join("forest.obj"), &err))?;
+    }
+    if format == MeshFormat::Gltf || format == MeshFormat::Both {
+        app.export_glb(dir.join("forest.glb")).map_err(|err| failed(dir.join("forest.glb"), &err))?;
+    }
+    app.save_checkpoint(dir.join("checkpoint.sscp")).map_err(|err| failed(dir.join("checkpoint.sscp"), &err))?;
+
+    Ok(())
+}
+
+
+
+#[cfg(test)]
+mod silviculture_tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let args = parse(&["forest.toml", "--steps", "20", "--seed", "7", "--interval", "5", "--format", "both", "--out", "results"]).unwrap().unwrap();
+        assert_eq!(args.scenario, PathBuf::from("forest.toml"));
+        assert!(matches!(args.duration, Duration::Steps(20)));
+        assert_eq!((args.seed, args.interval, args.out), (Some(7), Some(5), PathBuf::from("results")));
+        assert!(args.format == MeshFormat::Both);
+
+        let args = parse(&["forest.toml"]).unwrap().unwrap();
+        assert!(matches!(args.duration, Duration::Years(years) if years == 50.0));
+        assert!(args.format == MeshFormat::Gltf && args.out == Path::new("output"));
+
+        assert!(parse(&["forest.toml", "--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn bad_args_test() {
+        let error = |args: &[&str]| parse(args).err().expect("the arguments should be rejected");
+
+        assert_eq!(error(&["forest.toml", "--frobnicate"]), "unknown option \"--frobnicate\"");
+        assert_eq!(error(&["forest.toml", "--steps"]), "--steps needs a value");
+        assert_eq!(error(&["forest.toml", "--out"]), "--out needs a value");
+        assert_eq!(error(&["forest.toml", "--steps", "ten"]), "--steps expects a number, found \"ten\"");
+        assert_eq!(error(&["forest.toml", "--format", "fbx"]), "unknown format \"fbx\", expected obj, gltf, both or none");
+        assert_eq!(error(&["forest.toml", "--years", "5", "--steps", "5"]), "only one of --years and --steps can be used");
+        assert_eq!(error(&["forest.toml", "--interval", "0"]), "--interval must be at least 1");
+        assert_eq!(error(&["forest.toml", "other.toml"]), "unexpected argument \"other.toml\"");
+        assert_eq!(error(&["--steps", "5"]), "no scenario file given");
+    }
+
+    #[test]
+    fn run_test() {
+        let out = std::env::temp_dir().join("silviculture_run_test");
+        let _ = fs::remove_dir_all(&out);
+        fs::create_dir_all(&out).unwrap();
+        let scenario = out.join("scenario.toml");
+        fs::write(&scenario, "\
+            [simulation]\ntime_step = 0.5\ninitial_plants = 2\nseed = 3\n\
+            [terrain]\ntype = \"flat\"\nsize = 10.0\n\
+            [[species]]\npreset = \"birch\"\n\
+            climate = { ideal_temperature = 10.0, temperature_std_dev = 5.0, ideal_moisture = 100.0, moisture_std_dev = 30.0 }\n",
+        ).unwrap();
+
+        let args = Args {scenario, duration: Duration::Steps(2), seed: None, interval: Some(1), format: MeshFormat::Both, out: out.clone()};
+        run(&args).unwrap();
+
+        assert!(out.join("stats.csv").is_file());
+        for step in ["step_000001", "step_000002"] {
+            for file in ["skeletons.json", "forest.obj", "forest.glb", "checkpoint.sscp"] {
+                assert!(out.join(step).join(file).is_file(), "{}/{} was not written", step, file);
+            }
+        }
+
+        // 1.6 years of 0.5 year steps rounds to 3 steps, with only the last one written
+        let years_out = out.join("years");
+        let args = Args {duration: Duration::Years(1.6), interval: None, out: years_out.clone(), ..args};
+        run(&args).unwrap();
+        let steps: Vec<String> = fs::read_dir(&years_out).unwrap().map(|entry| entry.unwrap().file_name().into_string().unwrap()).filter(|name| name.starts_with("step_")).collect();
+        assert_eq!(steps, vec!["step_000003"]);
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
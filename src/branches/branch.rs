@@ -1,5 +1,7 @@
 #![allow(dead_code, unused_variables, unused_imports)]
 use bevy_ecs::prelude::*;
+use bevy_ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
+use serde::{Serialize, Deserialize};
 use itertools::Itertools;
 use super::{
     super::maths::{vector_three::Vector3, bounding_sphere::BoundingSphere, matrix_three::Matrix3},
@@ -20,7 +22,7 @@ use super::super::graphics::mesh::Mesh;
 pub struct BranchTag;
 
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize)]
 pub struct BranchData {
     pub normal: Vector3,
     pub root_node: Option<Entity>,
@@ -29,7 +31,7 @@ pub struct BranchData {
     pub full_grown: bool,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize)]
 pub struct BranchGrowthData {
     pub intersection_list: Vec<Entity>,
     pub intersection_volume: f32,
@@ -40,7 +42,7 @@ pub struct BranchGrowthData {
     pub layers: u32,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize)]
 pub struct BranchBounds  {
     pub bounds: BoundingSphere
 }
@@ -68,7 +70,7 @@ pub struct BranchBundle {
     pub prototype: BranchPrototypeRef,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Default, Component, Serialize, Deserialize)]
 pub struct BranchConnectionData {
     pub parent: Option<Entity>,
     pub children: (Option<Entity>, Option<Entity>),
@@ -129,6 +131,42 @@ impl From<BoundingSphere> for BranchBounds {
 }
 
 
+impl MapEntities for BranchData {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(root_node) = self.root_node {
+            self.root_node = Some(entity_map.get(root_node)?);
+        }
+        if let Some(parent_node) = self.parent_node {
+            self.parent_node = Some(entity_map.get(parent_node)?);
+        }
+        Ok(())
+    }
+}
+
+impl MapEntities for BranchGrowthData {
+    /// the intersection list is rebuilt every update, so any branches that no longer exist are dropped rather than failing
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.intersection_list = self.intersection_list.iter().filter_map(|id| entity_map.get(*id).ok()).collect();
+        Ok(())
+    }
+}
+
+impl MapEntities for BranchConnectionData {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(parent) = self.parent {
+            self.parent = Some(entity_map.get(parent)?);
+        }
+        if let Some(child) = self.children.0 {
+            self.children.0 = Some(entity_map.get(child)?);
+        }
+        if let Some(child) = self.children.1 {
+            self.children.1 = Some(entity_map.get(child)?);
+        }
+        Ok(())
+    }
+}
+
+
 impl Default for BranchData {
     fn default() -> Self {
         BranchData {
@@ -155,15 +193,6 @@ impl Default for BranchGrowthData {
     }
 }
 
-impl Default for BranchConnectionData {
-    fn default() -> Self {
-        BranchConnectionData {
-            parent: None,
-            children: (None, None),
-            updates_to_more_children: 0,
-        }
-    }
-}
 
 
 ///////////////////////////////////////////////////////////////////////////////////////
@@ -176,12 +205,13 @@ pub fn get_branch_parent_id(
     connections_query: &Query<&BranchConnectionData, With<BranchTag>>
 ) -> Option<Entity> {
     if let Ok(child_data) = connections_query.get(child_id) {
-        return child_data.parent;
+        child_data.parent
     }
     else {panic!("Failed to get parent branch: child id was {:?}", child_id)}
 }
 
 
+#[allow(clippy::unnecessary_unwrap)]
 pub fn get_branches_tip_to_base(
     connections_query: &Query<&BranchConnectionData, With<BranchTag>>,
     root_branch: Entity,
@@ -204,6 +234,7 @@ pub fn get_branches_tip_to_base(
 }
 
 /// returns all branches from a root that can still have children
+#[allow(clippy::unnecessary_unwrap)]
 pub fn get_terminal_branches(
     connections_query: &Query<&mut BranchConnectionData, With<BranchTag>>,
     root_branch: Entity,
@@ -232,6 +263,7 @@ pub fn get_terminal_branches(
 }
 
 /// returns all non-terminal branches from a tree
+#[allow(clippy::unnecessary_unwrap)]
 pub fn get_non_terminal_branches(
     connections_query: &Query<&BranchConnectionData, With<BranchTag>>,
     root_branch: Entity
@@ -253,6 +285,7 @@ pub fn get_non_terminal_branches(
     list
 }
 
+#[allow(clippy::unnecessary_unwrap)]
 pub fn get_branches_base_to_tip(
     connections_query: &Query<&BranchConnectionData, With<BranchTag>>,
     root_branch: Entity,
@@ -273,6 +306,7 @@ pub fn get_branches_base_to_tip(
 }
 
 
+#[allow(clippy::unnecessary_unwrap)]
 pub fn get_branch_bounds_base_to_tip(
     bounds_query: &Query<&BranchBounds, With<BranchTag>>,
     connections_query: &Query<&mut BranchConnectionData, With<BranchTag>>,
@@ -294,7 +328,7 @@ pub fn get_branch_bounds_base_to_tip(
 
     for id in ids {
         if let Ok(bounds) = bounds_query.get(id) {
-            out.push(bounds.bounds.clone());
+            out.push(bounds.bounds);
         }
     }
 
@@ -327,15 +361,15 @@ pub fn get_children_vigor(
     // if they are the same then just split the vigor evenly
     
     if light_exp_one == light_exp_two {
-        return (parent_vigor / 2.0, parent_vigor / 2.0);
+        (parent_vigor / 2.0, parent_vigor / 2.0)
     }
     else if light_exp_one > light_exp_two {
         let child_one_vigor = parent_vigor * ((apical * light_exp_one) / (apical * light_exp_one + (1.0-apical) * light_exp_two));
-        return (child_one_vigor, parent_vigor-child_one_vigor);
+        (child_one_vigor, parent_vigor-child_one_vigor)
     }
     else {
         let child_two_vigor = parent_vigor * ((apical * light_exp_two) / (apical * light_exp_two + (1.0-apical) * light_exp_one));
-        return (parent_vigor - child_two_vigor, child_two_vigor);
+        (parent_vigor - child_two_vigor, child_two_vigor)
     }
     
 }
@@ -3,6 +3,7 @@ use bevy_ecs::prelude::*;
 use super::{
     super::{
         plants::plant::*,
+        roots::root::PlantRoots,
         environment::{params::*, light_cells::*},
         maths::{vector_three::Vector3, matrix_three::Matrix3, lerp, bounding_sphere::BoundingSphere},
     },
@@ -16,12 +17,14 @@ use itertools::Itertools;
 use super::super::graphics::branch_mesh_gen::MeshUpdateQueue;
 
 
-pub fn calculate_branch_light_exposure(
+/// fills the light cells with the branch volumes, then finds the light exposure of every terminal branch using the light model L
+pub fn calculate_branch_light_exposure<L: LightModel>(
     mut branches_query: Query<(&mut BranchGrowthData, &BranchBounds), With<BranchTag>>,
     branch_connection_query: Query<&mut BranchConnectionData, With<BranchTag>>,
     plant_query: Query<(&PlantPlasticityParameters, &PlantData), With<PlantTag>>,
 
     mut light_cells: ResMut<LightCells>,
+    light_model: Res<L>,
 ) {
     // update the light cells
     light_cells.set_all_zero();
@@ -29,6 +32,9 @@ pub fn calculate_branch_light_exposure(
     for (_growth_data, bounds) in branches_query.iter() {
         light_cells.add_volume_to_cell(bounds.bounds.centre / cell_size, bounds.bounds.get_volume());
     }
+    light_cells.propagate_shadows();
+
+    let cell_light = |position: Vector3| light_model.get_cell_light(&light_cells, position / cell_size);
 
     // update light exposure
     for (plasticity_params, plant_data) in plant_query.iter() {
@@ -37,9 +43,9 @@ pub fn calculate_branch_light_exposure(
 
         for id in get_terminal_branches(&branch_connection_query, plant_data.root_node.unwrap()) {
             if let Ok((mut growth_data, bounds)) = branches_query.get_mut(id) {
-                growth_data.light_exposure = lerp(tolerance, 1.0, light_cells.get_cell_light(bounds.bounds.centre / cell_size) * (-growth_data.intersection_volume).exp());
+                growth_data.light_exposure = lerp(tolerance, 1.0, cell_light(bounds.bounds.centre) * (-growth_data.intersection_volume).exp());
                 if growth_data.light_exposure.is_nan() {
-                    panic!("Light exposure is not a number: \n- Cell light: {}, \n- Intersection Volume: {} \n- shadow_tolerance: {}", light_cells.get_cell_light(bounds.bounds.centre / light_cells.size()), growth_data.intersection_volume, tolerance);
+                    panic!("Light exposure is not a number: \n- Cell light: {}, \n- Intersection Volume: {} \n- shadow_tolerance: {}", cell_light(bounds.bounds.centre), growth_data.intersection_volume, tolerance);
                 }
             }
         }
@@ -50,8 +56,10 @@ pub fn calculate_branch_light_exposure(
 /// takes data from the branches and distributes it, we do a tip to base pass and sum light exposure at branching points
 /// after this we use a helper function to distribute growth vigor up the plant
 /// this means that branches closer to the root have a higher growth vigor than those further away
+///
+/// The max vigor of plants with roots is scaled by their root vigor
 pub fn calculate_growth_vigor(
-    plant_query: Query<(&PlantData, &PlantGrowthControlFactors), With<PlantTag>>,
+    plant_query: Query<(&PlantData, &PlantGrowthControlFactors, Option<&PlantRoots>), With<PlantTag>>,
     mut branch_query: Query<&mut BranchGrowthData, With<BranchTag>>,
     branch_connections_query: Query<&BranchConnectionData, With<BranchTag>>
 ) {
@@ -83,7 +91,8 @@ pub fn calculate_growth_vigor(
         }
 
         if let Ok(mut root_data) = branch_query.get_mut(plant_data.0.root_node.unwrap()) {
-            root_data.growth_vigor = root_data.light_exposure.min(plant_data.1.max_vigor * plant_data.0.climate_adaption);
+            let root_vigor = plant_data.2.map(|roots| roots.root_vigor).unwrap_or(1.0);
+            root_data.growth_vigor = root_data.light_exposure.min(plant_data.1.max_vigor * plant_data.0.climate_adaption * root_vigor);
         }
         // distribute vigor to branches
         for id in get_branches_base_to_tip(&branch_connections_query, plant_data.0.root_node.unwrap()) {
@@ -237,6 +246,34 @@ pub fn assign_growth_rates(
     }
 }
 
+/// the temperature below which plants stop growing
+const MIN_GROWTH_TEMPERATURE: f32 = 0.0;
+/// how far above the minimum the temperature has to be for full growth
+const GROWTH_TEMPERATURE_RANGE: f32 = 5.0;
+
+/// slows the growth of plants where the temperature of the current step is too cold, so that plants barely grow in winter
+///
+/// Must be called after assigning growth rates, and is only needed when the climate changes over time
+pub fn apply_temperature_to_growth_rates(
+    plant_query: Query<&PlantData, With<PlantTag>>,
+    mut branch_data_query: Query<&mut BranchGrowthData, With<BranchTag>>,
+    branch_connections_query: Query<&BranchConnectionData, With<BranchTag>>,
+    environment: Res<MoistureAndTemp>,
+) {
+    for plant_data in plant_query.iter() {
+        if plant_data.root_node.is_none() {continue;}
+        let temp = environment.temp_at_zero + plant_data.position.y * environment.temp_fall_off;
+        let growth_factor = ((temp - MIN_GROWTH_TEMPERATURE) / GROWTH_TEMPERATURE_RANGE).clamp(0.0, 1.0);
+        if growth_factor >= 1.0 {continue;}
+
+        for id in get_branches_base_to_tip(&branch_connections_query, plant_data.root_node.unwrap()) {
+            if let Ok(mut branch_data) = branch_data_query.get_mut(id) {
+                branch_data.growth_rate *= growth_factor;
+            }
+        }
+    }
+}
+
 fn sigmoid(x: f32) -> f32 {
     3.0 * x.powi(2) - 2.0 * x.powi(3)
 }
@@ -287,6 +324,7 @@ pub fn step_physiological_age(
 /// 
 /// The number of layers on the tree that stores the nodes will be lineraly interpolated between 1 and max 
 /// using the age of the branch and the mature age of its prototype reference
+#[allow(clippy::needless_range_loop)]
 pub fn update_branch_nodes(
     mut branch_data_query: Query<(&BranchData, &mut BranchGrowthData, &BranchPrototypeRef), With<BranchTag>>,
 
@@ -307,7 +345,7 @@ pub fn update_branch_nodes(
         if branch_growth_data.layers < target_layers {
             // println!("check");
             // get a list of nodes on the current layer and loop through them
-            let current_layer = get_nodes_on_layer(&mut node_connections_query, branch_data.root_node.unwrap(), branch_growth_data.layers);
+            let current_layer = get_nodes_on_layer(&node_connections_query, branch_data.root_node.unwrap(), branch_growth_data.layers);
             // println!("{}", current_layer.len());
             
             for i in 0..current_layer.len() {
@@ -348,6 +386,7 @@ pub fn update_branch_nodes(
 
 
 /// decides if and where to create new branches on a plant
+#[allow(clippy::too_many_arguments)]
 pub fn determine_create_new_branches(
     plant_query: Query<(&PlantData, &PlantGrowthControlFactors), With<PlantTag>>,
 
@@ -356,7 +395,7 @@ pub fn determine_create_new_branches(
     mut branch_connections_query: Query<&mut BranchConnectionData, With<BranchTag>>,
 
     branch_prototypes_sampler: Res<BranchPrototypesSampler>,
-    branch_prototypes: Res<BranchPrototypes>,
+    mut branch_prototypes: ResMut<BranchPrototypes>,
 
     mut node_data_query: Query<(&mut BranchNodeGrowthData, &BranchNodeData), With<BranchNodeTag>>,
     node_connections_query: Query<&BranchNodeConnectionData, With<BranchNodeTag>>,
@@ -366,7 +405,6 @@ pub fn determine_create_new_branches(
     mut commands: Commands
 ) {
 
-    let prototypes = &branch_prototypes.prototypes;
     let tropism_dir = gravity_res.gravity_dir * (gravity_res.tropism_strength / gravity_res.tropism_strength.abs());
 
     for (plant_data, plant_growth_factors) in plant_query.iter() {
@@ -410,11 +448,11 @@ pub fn determine_create_new_branches(
                     }
                 }
 
-                let prototype_index = branch_prototypes_sampler.get_prototype_index(apical, branch_growth_data.growth_vigor * branch_prototypes_sampler.max_determinancy / v_max);
-                if branch_bounds.len() == 0 {branch_bounds = get_branch_bounds_base_to_tip(&branch_bounds_query, &branch_connections_query, branch_data.root_node.unwrap())}
+                let prototype_index = branch_prototypes_sampler.sample(&mut branch_prototypes, apical, branch_growth_data.growth_vigor * branch_prototypes_sampler.max_determinancy / v_max);
+                if branch_bounds.is_empty() {branch_bounds = get_branch_bounds_base_to_tip(&branch_bounds_query, &branch_connections_query, branch_data.root_node.unwrap())}
 
                 // get the nodes on the branch that could generate new branches
-                let mut possible_terminal_nodes = get_possible_new_branch_nodes(&mut node_data_query, &node_connections_query, branch_data.root_node.unwrap(), v_min, v_max, &prototypes[prototype_index], plant_angle, plant_dist_control, branch_data.normal, tropism_dir, max_branch_length, &branch_bounds);
+                let mut possible_terminal_nodes = get_possible_new_branch_nodes(&mut node_data_query, &node_connections_query, branch_data.root_node.unwrap(), v_min, v_max, &branch_prototypes.prototypes[prototype_index], plant_angle, plant_dist_control, branch_data.normal, tropism_dir, max_branch_length, &branch_bounds);
                 if possible_terminal_nodes.0.is_none() {continue;}
 
 
@@ -473,6 +511,7 @@ pub fn determine_create_new_branches(
 /// 
 /// returns the two nodes on the branch that are the options for branch attachments
 /// the data returned for each is an id, and the node's position and thickening factor
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn get_possible_new_branch_nodes(
     node_data_query: &mut Query<(&mut BranchNodeGrowthData, &BranchNodeData), With<BranchNodeTag>>,
     node_connections_query: &Query<&BranchNodeConnectionData, With<BranchNodeTag>>,
@@ -493,7 +532,7 @@ fn get_possible_new_branch_nodes(
     
 
     // sum up light exposure at branching points
-    for id in get_nodes_tip_to_base(&node_connections_query, root_node) {
+    for id in get_nodes_tip_to_base(node_connections_query, root_node) {
         #[allow(unused_assignments)]
         let mut light_exposure = 0.0;
         if let Ok(node_data) = node_data_query.get(id) {
@@ -512,7 +551,7 @@ fn get_possible_new_branch_nodes(
     }
 
     // distribute vigor up the branch, there is no apical control so it's slightly easier
-    for id in get_nodes_base_to_tip(&node_connections_query, root_node) {
+    for id in get_nodes_base_to_tip(node_connections_query, root_node) {
         #[allow(unused_assignments)]
         let mut parent_vigor = 0.0;        
 
@@ -522,7 +561,7 @@ fn get_possible_new_branch_nodes(
 
         if let Ok(parent_connections) = node_connections_query.get(id) {
 
-            if parent_connections.children.len() == 0 {continue;}
+            if parent_connections.children.is_empty() {continue;}
             if parent_connections.children.len() == 1 {
                 if let Ok(mut only_child) = node_data_query.get_mut(parent_connections.children[0]) {
                     only_child.0.growth_vigor = parent_vigor;
@@ -555,7 +594,7 @@ fn get_possible_new_branch_nodes(
     // get the possible nodes
     let mut possible_nodes = Vec::new();
 
-    for id in get_terminal_nodes(&node_connections_query, root_node) {
+    for id in get_terminal_nodes(node_connections_query, root_node) {
         if let Ok(node_data) = node_data_query.get(id) {
             if node_data.0.growth_vigor <= branch_min_vigor {continue;}
 
@@ -566,7 +605,7 @@ fn get_possible_new_branch_nodes(
     }
 
     // check cases for 0 or 1 node
-    if possible_nodes.len() == 0 {return (None, None);}
+    if possible_nodes.is_empty() {return (None, None);}
     if possible_nodes.len() == 1 {return (Some(possible_nodes[0].0), None);}
 
     // create the inital best nodes
@@ -605,6 +644,7 @@ fn get_possible_new_branch_nodes(
 /// helper function to calculate the normal of a new branch module
 /// 
 /// The normal is based on 1 of 4 options, whichever has the smallest value from the distribution fn
+#[allow(clippy::too_many_arguments)]
 fn get_new_normal(
     plant_angle: f32,
     plant_dist_control: f32,
@@ -650,6 +690,7 @@ fn tropism(
 
 
 /// only checks for collisions with branches inside of the plant
+#[allow(clippy::ptr_arg)]
 fn possible_collisions_volume(
     bounds: BoundingSphere,
     other_bounds: &Vec<BoundingSphere>,
@@ -846,6 +887,7 @@ pub fn calculate_segment_lengths_and_tropism(
 #[cfg(not(feature = "vulkan_graphics"))]
 /// calculates the positions of all the branch nodes using:
 /// segment_length = min(max_segment_length, segment_length_scale * parent_node_physiological_age)
+#[allow(clippy::unnecessary_unwrap)]
 pub fn calculate_segment_lengths_and_tropism(
     plant_query: Query<(&PlantData, &PlantGrowthControlFactors), With<PlantTag>>,
 
@@ -922,7 +964,7 @@ pub fn calculate_segment_lengths_and_tropism(
                 // update all node positions
                 let node_pairs = get_nodes_and_connections_base_to_tip(&branch_node_connections_query, branch_data.root_node.unwrap());
 
-                if node_pairs.len() == 0 {continue;}
+                if node_pairs.is_empty() {continue;}
 
                 for i in 0..node_pairs.len() {
                     if let Ok(mut node_pair) = branch_node_query.get_many_mut(node_pairs[i]) {
@@ -997,7 +1039,7 @@ pub fn calculate_branch_intersection_volumes(
         for id_other in data.intersection_list.iter() {
             intersections.push(*id_other);
         }
-        intersection_lists.push((id, bounds.bounds.clone(), intersections));
+        intersection_lists.push((id, bounds.bounds, intersections));
     }
 
     for branch_one in intersection_lists {
@@ -1039,7 +1081,7 @@ pub fn update_branch_intersections(
                 if other_plant.root_node.is_none() {continue;}
                 for id in get_branches_base_to_tip(&branch_connections_query, other_plant.root_node.unwrap()) {
                     if let Ok(branch) = &branch_query.get(id) {
-                        other_plant_branch_bounds.push((branch.0.bounds.clone(), id));
+                        other_plant_branch_bounds.push((branch.0.bounds, id));
                     }
                 }
             }
@@ -1065,7 +1107,7 @@ pub fn update_branch_intersections(
                 if combination[0] == combination[1] {continue;}
                 let other_data: BoundingSphere;
                 if let Ok(branch_two) = branch_query.get(*combination[1]){
-                    other_data = branch_two.0.bounds.clone();
+                    other_data = branch_two.0.bounds;
                 } else {panic!("failed to get branch pair")};
                 if let Ok(mut branch_one) = branch_query.get_mut(*combination[0]){
                     if branch_one.0.bounds.is_intersecting_sphere(other_data) {
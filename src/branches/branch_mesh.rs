@@ -0,0 +1,140 @@
+//! Branch mesh generation that does not depend on the graphics feature
+//!
+//! The graphics use this to build their branch meshes, and the apps use it to output plant meshes
+use std::f32::consts::PI;
+use itertools::Itertools;
+use super::super::maths::{vector_three::Vector3, matrix_three::Matrix3};
+
+
+/// Creates a polygon of vectors,
+/// each of the vectors is a direction
+/// so can be used to generate a polygon from a central point.
+/// By default, the first direction will go along the x axis.
+/// A rotation will rotate by that many radians anticlockwise about the y axis
+pub fn create_vector_polygon(sides: u32, rotation: Option<f32>) -> Vec<Vector3> {
+
+    let inner_rotation_matrix = Matrix3::from_angle_y(2.0 * PI / sides as f32);
+
+    let initial_direction = match rotation {
+        Some(rotation) => Vector3::X().transform(Matrix3::from_angle_y(-rotation)),
+        None => Vector3::X(),
+    };
+
+    let mut vectors = vec![initial_direction];
+    for i in 0..(sides-1) as usize {
+        vectors.push(vectors[i].clone().transform(inner_rotation_matrix));
+    }
+
+    vectors
+}
+
+
+/// generates the vertices and indices of a branch mesh from node pairs and polygon directions
+///
+/// The vertices are moved to the branch root position, so are in world space
+pub fn create_branch_mesh_components(
+    branch_normal: Vector3,
+    root_pos: Vector3,
+    mut node_pos: Vec<Vector3>,
+    node_thicknesses: Vec<f32>,
+    node_pairs:  Vec<(usize, usize)>,
+    polygon_directions: &[Vector3],
+) -> (Vec<Vector3>, Vec<u32>) {
+
+    let branch_rotation_matrix = {
+        let rotation_axis = branch_normal.cross(Vector3::Y());
+        let rotation_angle = branch_normal.angle_to(Vector3::Y());
+        Matrix3::from_angle_and_axis(-rotation_angle, rotation_axis)
+    };
+
+    for node in node_pos.iter_mut() {
+        node.mut_transform(branch_rotation_matrix);
+    }
+
+    let mut vertices: Vec<Vector3> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    let num_indices = polygon_directions.len() as u32 * 2;
+
+    for pair in node_pairs.iter() {
+        let (node_1, node_2) = (node_pos[pair.0], node_pos[pair.1]);
+        let (thick_1, thick_2) = (node_thicknesses[pair.0], node_thicknesses[pair.1]);
+
+        let mut branch_line = node_2 - node_1;
+        branch_line.normalise();
+
+        let allignment_mat = {
+            let mut rotation_axis = branch_line.cross(Vector3::Y());
+            rotation_axis.normalise();
+            let rotation_angle = Vector3::Y().angle_to(branch_line);
+            Matrix3::from_angle_and_axis(-rotation_angle, rotation_axis)
+        };
+
+        // add vertices and indices
+        let vert_index = vertices.len() as u32;
+        let mut incr: u32 = 0;
+        for direction in polygon_directions.iter() {
+            let new_dir = direction.transform(allignment_mat);
+            vertices.push(node_1 + (new_dir * thick_1));
+            vertices.push(node_2 + (new_dir * thick_2));
+
+            // magic index stuff, this is just how it works, idk how else to explain it
+            // it needed to loop round so that's where the mod comes in
+            indices.push(vert_index + incr);
+            indices.push(vert_index + 1 + (incr + 2) % num_indices);
+            indices.push(vert_index + (incr + 2) % num_indices);
+            indices.push(vert_index + 1 + (incr + 2) % num_indices);
+            indices.push(vert_index + incr);
+            indices.push(vert_index + incr + 1);
+            incr += 2;
+        }
+
+
+    }
+
+    // move mesh to root
+    for vertex in vertices.iter_mut() {
+        *vertex += root_pos;
+    }
+
+    (vertices, indices)
+}
+
+
+/// calculates smooth shaded normals for the given mesh components
+pub fn calculate_smooth_normals(vertices: &[Vector3], indices: &[u32]) -> Vec<Vector3> {
+    let mut normals: Vec<Vector3> = vec![Vector3::ZERO(); vertices.len()];
+
+    for i in (0..indices.len()).step_by(3) {
+        let dir_one = vertices[indices[i] as usize] - vertices[indices[i + 2] as usize];
+        let dir_two = vertices[indices[i + 1] as usize] - vertices[indices[i + 2] as usize];
+        let normal = dir_one.cross(dir_two);
+
+        normals[indices[i] as usize] += normal;
+        normals[indices[i + 1] as usize] += normal;
+        normals[indices[i + 2] as usize] += normal;
+    }
+
+    normals.iter().map(|normal| normal.normalised()).collect()
+}
+
+
+/// flat shades the given mesh components, returning new vertices, normals and indices
+pub fn flat_shade_mesh_components(vertices: &[Vector3], indices: &[u32]) -> (Vec<Vector3>, Vec<Vector3>, Vec<u32>) {
+    let mut new_verts: Vec<Vector3> = Vec::new();
+    let mut new_normals: Vec<Vector3> = Vec::new();
+
+    for i in (0..indices.len()).step_by(3) {
+        let v_one = vertices[indices[i] as usize];
+        let v_two = vertices[indices[i + 1] as usize];
+        let v_thr = vertices[indices[i + 2] as usize];
+
+        let normal = (v_one - v_thr).cross(v_two - v_thr).normalised();
+
+        new_verts.append(&mut vec![v_one, v_two, v_thr]);
+        new_normals.append(&mut vec![normal, normal, normal]);
+    }
+
+    let indices = (0..(new_verts.len()) as u32).collect_vec();
+    (new_verts, new_normals, indices)
+}
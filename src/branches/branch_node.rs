@@ -1,12 +1,14 @@
 #![allow(dead_code, unused_variables, unused_imports)]
 use bevy_ecs::prelude::*;
+use bevy_ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
+use serde::{Serialize, Deserialize};
 use super::super::maths::vector_three::Vector3;
 
 
 #[derive(Component)]
 pub struct BranchNodeTag;
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize)]
 pub struct BranchNodeData {
     pub position: Vector3,
     pub tropism_offset: Vector3,
@@ -16,13 +18,13 @@ pub struct BranchNodeData {
     pub thickening_factor: f32,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize)]
 pub struct BranchNodeGrowthData {
     pub light_exposure: f32,
     pub growth_vigor: f32,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Default, Serialize, Deserialize)]
 pub struct BranchNodeConnectionData {
     pub parent: Option<Entity>,
     pub children: Vec<Entity>,
@@ -62,12 +64,15 @@ impl Default for BranchNodeData {
 }
 
 
-impl Default for BranchNodeConnectionData {
-    fn default() -> Self {
-        BranchNodeConnectionData {
-            parent: None,
-            children: Vec::new(),
+impl MapEntities for BranchNodeConnectionData {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(parent) = self.parent {
+            self.parent = Some(entity_map.get(parent)?);
+        }
+        for child in self.children.iter_mut() {
+            *child = entity_map.get(*child)?;
         }
+        Ok(())
     }
 }
 
@@ -146,7 +151,7 @@ pub fn get_terminal_nodes(
     loop {
         if i >= list.len() {break;}
         if let Ok(node_connections) = connections_query.get(list[i]) {
-            if node_connections.children.len() == 0 {
+            if node_connections.children.is_empty() {
                 i += 1;
                 continue;
             }
@@ -1,15 +1,8 @@
 #![allow(dead_code, unused_variables, unused_imports)]
-use std::{collections::HashMap};
-
+use std::{collections::BTreeMap, fmt};
 use bevy_ecs::prelude::*;
-use plotters::{prelude::*, palette::white_point::C};
-use voronator::{
-    delaunator::Point,
-    CentroidDiagram,
-    VoronoiDiagram,
-};
-use image::{GenericImageView, DynamicImage, ImageBuffer};
 use rand::Rng;
+use serde::{Serialize, Deserialize};
 
 use super::{
     branch::BranchBundle,
@@ -24,21 +17,38 @@ use super::{
 ////////////////////////////////// Branch Prototypes //////////////////////////////////
 ///////////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Resource)]
+/// Chooses branch prototypes by finding the nearest one to a point in apical control and determinacy space
+///
+/// Both axes are normalised by their max values, so they have the same weight when measuring distances
+#[derive(Resource, Serialize, Deserialize)]
 pub struct BranchPrototypesSampler {
-    pub prototypes: HashMap<image::Rgba<u8>, usize>,
-    pub voronoi: DynamicImage,
-    pub max_apical: f32, 
+    /// the (apical control, determinacy) position of each prototype
+    pub prototypes: Vec<(f32, f32)>,
+    pub max_apical: f32,
     pub max_determinancy: f32,
+    /// how many of the nearest prototypes are blended together for new branches, 1 turns blending off
+    pub blend_count: usize,
 }
 
+/// how finely blend weights are split, weights are rounded so that similar blends share a prototype
+const BLEND_RESOLUTION: f32 = 16.0;
+
 
 
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 pub struct BranchPrototypeRef (pub usize);
 
 
+/// a branch prototype as it is given to the builders: (mature age, node counts, directions)
+pub type PrototypePreset = (f32, Vec<Vec<u32>>, Vec<[f32; 3]>);
+
+/// the (apical control, determinacy) position of each prototype, then the max apical control and max determinacy
+pub type PrototypeConditions = (Vec<(f32, f32)>, f32, f32);
+
+/// the node counts and directions of a prototype, without its mature age
+pub(crate) type PrototypeShape = (Vec<Vec<u32>>, Vec<[f32; 3]>);
+
 /// Struct for storing data about a branch prototype
 /// 
 /// #### Parameters:
@@ -46,6 +56,7 @@ pub struct BranchPrototypeRef (pub usize);
 /// - layers: the number of layers in the tree the branch will have when fully grown
 /// - node_counts: how many children each node on a layer of the tree has
 /// - directions: a list of normalised directions that correspond to the node pairs generated by the get_nodes_and_connections_base_to_tip fn in branch_nodes
+#[derive(Serialize, Deserialize)]
 pub struct BranchPrototypeData {
     pub mature_age: f32, // the mature age of the branch, used to interpolate growth
     pub layers: u32, // the number of layers in the tree diagram, used for interpolating growth
@@ -55,8 +66,110 @@ pub struct BranchPrototypeData {
 }
 
 
+/// The ways a branch prototype can be inconsistent
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrototypeError {
+    /// a layer of node counts doesn't have an entry for each node on the layer before it
+    LayerSize {
+        layer: usize,
+        entries: usize,
+        nodes: u32,
+    },
+    /// a layer of node counts has no children at all
+    EmptyLayer(usize),
+    /// the number of directions doesn't match the number of node pairs
+    DirectionCount {
+        pairs: u32,
+        directions: usize,
+    },
+    /// the direction at the given index has no length
+    ZeroDirection(usize),
+    /// the direction at the given index has a value that is infinite or NaN
+    NonFiniteDirection(usize),
+    /// the mature age, which must be finite and greater than 0
+    MatureAge(f32),
+    /// there must be at least one prototype to choose from
+    NoPrototypes,
+    /// a prototype in a list is invalid
+    InList {
+        index: usize,
+        error: Box<PrototypeError>,
+    },
+    /// the number of prototype positions doesn't match the number of prototypes
+    ConditionCount {
+        prototypes: usize,
+        conditions: usize,
+    },
+}
+
+impl fmt::Display for PrototypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrototypeError::LayerSize{layer, entries, nodes} => write!(f, "node_counts[{}] has {} entries, but there are {} nodes on layer {}", layer, entries, nodes, layer + 1),
+            PrototypeError::EmptyLayer(layer) => write!(f, "node_counts[{}] adds no nodes", layer),
+            PrototypeError::DirectionCount{pairs, directions} => write!(f, "the node counts describe {} node pairs, but there are {} directions", pairs, directions),
+            PrototypeError::ZeroDirection(i) => write!(f, "directions[{}] has no length", i),
+            PrototypeError::NonFiniteDirection(i) => write!(f, "directions[{}] is not finite", i),
+            PrototypeError::MatureAge(age) => write!(f, "the mature age must be greater than 0, found {}", age),
+            PrototypeError::NoPrototypes => write!(f, "there are no prototypes"),
+            PrototypeError::InList{index, error} => write!(f, "prototypes[{}]: {}", index, error),
+            PrototypeError::ConditionCount{prototypes, conditions} => write!(f, "there are {} prototypes, but {} prototype positions", prototypes, conditions),
+        }
+    }
+}
+
+impl std::error::Error for PrototypeError {}
+
+
 impl BranchPrototypeData {
-    pub fn new(mature_age: f32, node_counts: Vec<Vec<u32>>, directions: Vec<[f32; 3]>) -> Self {
+    /// checks that the node counts describe a valid tree, and that there is a direction for every node pair
+    ///
+    /// The first layer always has a single node, and each layer of node counts needs an entry for every node on the layer above it
+    pub fn check_shape(node_counts: &[Vec<u32>], direction_count: usize) -> Result<(), PrototypeError> {
+        let mut layer_nodes: u32 = 1;
+        let mut total_pairs: u32 = 0;
+        for (i, layer_counts) in node_counts.iter().enumerate() {
+            if layer_counts.len() != layer_nodes as usize {
+                return Err(PrototypeError::LayerSize {layer: i, entries: layer_counts.len(), nodes: layer_nodes});
+            }
+            layer_nodes = layer_counts.iter().sum();
+            if layer_nodes == 0 {
+                return Err(PrototypeError::EmptyLayer(i));
+            }
+            total_pairs += layer_nodes;
+        }
+
+        if total_pairs as usize != direction_count {
+            return Err(PrototypeError::DirectionCount {pairs: total_pairs, directions: direction_count});
+        }
+        Ok(())
+    }
+
+    /// checks everything needed to make a prototype that can be grown: the shape, the directions and the mature age
+    pub fn validate(mature_age: f32, node_counts: &[Vec<u32>], directions: &[[f32; 3]]) -> Result<(), PrototypeError> {
+        if !mature_age.is_finite() || mature_age <= 0.0 {return Err(PrototypeError::MatureAge(mature_age));}
+        Self::check_shape(node_counts, directions.len())?;
+        for (i, direction) in directions.iter().enumerate() {
+            if !direction.iter().all(|x| x.is_finite()) {return Err(PrototypeError::NonFiniteDirection(i));}
+            if Vector3::from(*direction).magnitude() <= f32::EPSILON {return Err(PrototypeError::ZeroDirection(i));}
+        }
+        Ok(())
+    }
+
+    /// validates the prototype before creating it, the directions are normalised
+    pub fn new(mature_age: f32, node_counts: Vec<Vec<u32>>, directions: Vec<[f32; 3]>) -> Result<Self, PrototypeError> {
+        Self::validate(mature_age, &node_counts, &directions)?;
+        let directions: Vec<[f32; 3]> = directions.into_iter().map(|direction| Vector3::from(direction).normalised().into()).collect();
+        Ok(Self::from_valid(mature_age, node_counts, directions))
+    }
+
+    /// returns the prototype in the form used by the app builders' set_branch_presets
+    pub fn to_preset(&self) -> PrototypePreset {
+        (self.mature_age, self.node_counts.clone(), self.directions.iter().map(|direction| (*direction).into()).collect())
+    }
+
+    /// creates the prototype without checking it, the node counts and directions must already be valid
+    fn from_valid(mature_age: f32, node_counts: Vec<Vec<u32>>, directions: Vec<[f32; 3]>) -> Self {
         let (layers, cuml_nodes) = {
             let mut layer_count = 1;
             let mut nodes = vec![1];
@@ -116,12 +229,10 @@ impl BranchPrototypeData {
 
         let node_positions = {
             let mut nodes: Vec<Vector3> = vec![Vector3::ZERO()];
-            let mut i = 0;
-            for dir_set in dirs {
+            for (i, dir_set) in dirs.into_iter().enumerate() {
                 for dir in dir_set {
                     nodes.push(nodes[i] + dir * max_length);
                 }
-                i += 1;
             }
             nodes
         };
@@ -168,20 +279,77 @@ impl BranchPrototypeData {
 }
 
 
-#[derive(Resource)]
+#[derive(Resource, Serialize, Deserialize)]
 pub struct BranchPrototypes {
-    pub prototypes: Vec<BranchPrototypeData>
+    pub prototypes: Vec<BranchPrototypeData>,
+    /// blended prototypes are added after the original ones, this maps the rounded blend weights to their index
+    blended: BTreeMap<Vec<(usize, u32)>, usize>,
 }
 
 impl BranchPrototypes {
-    pub fn new(data: Vec<(f32, Vec<Vec<u32>>, Vec<[f32; 3]>)> ) -> Self{
+    /// validates every prototype before creating them, there must be at least one
+    pub fn new(data: Vec<PrototypePreset> ) -> Result<Self, PrototypeError> {
+        Self::validate(&data)?;
         let mut prototypes = Vec::new();
         for (mature_age, node_counts, directions) in data {
-            prototypes.push(BranchPrototypeData::new(mature_age, node_counts, directions));
+            prototypes.push(BranchPrototypeData::new(mature_age, node_counts, directions)?);
+        }
+        Ok(BranchPrototypes {
+            prototypes,
+            blended: BTreeMap::new(),
+        })
+    }
+
+    /// checks that there is at least one prototype and that all of them can be grown
+    pub fn validate(data: &[PrototypePreset]) -> Result<(), PrototypeError> {
+        if data.is_empty() {return Err(PrototypeError::NoPrototypes);}
+        for (index, (mature_age, node_counts, directions)) in data.iter().enumerate() {
+            BranchPrototypeData::validate(*mature_age, node_counts, directions)
+                .map_err(|error| PrototypeError::InList {index, error: Box::new(error)})?;
         }
-        BranchPrototypes {
-            prototypes
+        Ok(())
+    }
+
+    /// returns the index of a prototype blended from the weighted prototypes, creating it if it doesn't exist yet
+    ///
+    /// The weights should be ordered nearest first, as from BranchPrototypesSampler::get_nearest_prototypes.
+    /// Only prototypes with the same node counts as the first can be blended, as the directions are matched up by position.
+    /// Any others are left out and the remaining weights rescaled, they aren't resampled onto a common layout
+    pub fn get_blended_index(&mut self, weights: &[(usize, f32)]) -> usize {
+        let first = match weights.first() {
+            Some(first) => first.0,
+            None => return 0,
+        };
+
+        // round the weights so there is a limited number of blends
+        let key: Vec<(usize, u32)> = weights.iter()
+            .filter(|(index, _)| self.prototypes[*index].node_counts == self.prototypes[first].node_counts)
+            .map(|(index, weight)| (*index, (weight * BLEND_RESOLUTION).round() as u32))
+            .filter(|(_, weight)| *weight > 0)
+            .collect();
+        if key.len() <= 1 {return key.first().map(|(index, _)| *index).unwrap_or(first);}
+        if let Some(index) = self.blended.get(&key) {return *index;}
+
+        let total: u32 = key.iter().map(|(_, weight)| weight).sum();
+        let mut mature_age = 0.0;
+        let mut directions = vec![Vector3::ZERO(); self.prototypes[first].directions.len()];
+        for (index, weight) in key.iter() {
+            let (prototype, weight) = (&self.prototypes[*index], *weight as f32 / total as f32);
+            mature_age += prototype.mature_age * weight;
+            for (direction, prototype_direction) in directions.iter_mut().zip(prototype.directions.iter()) {
+                *direction += *prototype_direction * weight;
+            }
         }
+
+        // opposite directions can cancel out, so fall back to the nearest prototype's direction
+        let directions: Vec<[f32; 3]> = directions.into_iter().zip(self.prototypes[first].directions.iter())
+            .map(|(direction, fallback)| if direction.magnitude() > 1e-4 {direction.normalised().into()} else {(*fallback).into()})
+            .collect();
+
+        let node_counts = self.prototypes[first].node_counts.clone();
+        self.prototypes.push(BranchPrototypeData::from_valid(mature_age, node_counts, directions));
+        self.blended.insert(key, self.prototypes.len() - 1);
+        self.prototypes.len() - 1
     }
 
     /// returns a set of directions
@@ -215,105 +383,168 @@ impl BranchPrototypes {
 
 
 ///////////////////////////////////////////////////////////////////////////////////////
-////////////////////////////////// Voronoi Diagram ////////////////////////////////////
+////////////////////////////////// Prototype Sampler //////////////////////////////////
 ///////////////////////////////////////////////////////////////////////////////////////
 
 
 impl BranchPrototypesSampler {
 
+    /// returns the index of the prototype nearest to the given apical control and determinacy
+    ///
+    /// Values outside of 0 to the max are clamped, so any value will give a prototype
     pub fn get_prototype_index(&self, apical: f32, determinancy: f32) -> usize {
+        let point = self.normalise(apical, determinancy);
 
-        let x: u32 = (apical * (self.voronoi.height() as f32 / self.max_apical)).round() as u32 - 1;
-        let y: u32 = (determinancy * (self.voronoi.width() as f32 / self.max_determinancy)).round() as u32 - 1;
-        
-        self.prototypes.get(&self.voronoi.get_pixel(x, y)).unwrap().clone()
+        let mut nearest = (0, f32::MAX);
+        for (i, prototype) in self.prototypes.iter().enumerate() {
+            let prototype = self.normalise(prototype.0, prototype.1);
+            let sqr_distance = (point.0 - prototype.0).powi(2) + (point.1 - prototype.1).powi(2);
+            if sqr_distance < nearest.1 {nearest = (i, sqr_distance);}
+        }
+        nearest.0
+    }
+
+    /// returns up to count of the nearest prototypes, nearest first, with weights that add up to 1
+    ///
+    /// Weights are the inverse of the distance to each prototype, so a point on a prototype only uses that prototype
+    pub fn get_nearest_prototypes(&self, apical: f32, determinancy: f32, count: usize) -> Vec<(usize, f32)> {
+        let point = self.normalise(apical, determinancy);
+
+        let mut distances: Vec<(usize, f32)> = self.prototypes.iter().enumerate().map(|(i, prototype)| {
+            let prototype = self.normalise(prototype.0, prototype.1);
+            (i, ((point.0 - prototype.0).powi(2) + (point.1 - prototype.1).powi(2)).sqrt())
+        }).collect();
+        distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+        distances.truncate(count.max(1));
+
+        if distances.first().map(|nearest| nearest.1 <= f32::EPSILON).unwrap_or(false) {
+            return vec![(distances[0].0, 1.0)];
+        }
+        let total: f32 = distances.iter().map(|(_, distance)| 1.0 / distance).sum();
+        distances.into_iter().map(|(i, distance)| (i, (1.0 / distance) / total)).collect()
+    }
+
+    /// returns the index of the prototype for a new branch, blending the nearest prototypes if blending is on
+    pub fn sample(&self, prototypes: &mut BranchPrototypes, apical: f32, determinancy: f32) -> usize {
+        if self.blend_count <= 1 {return self.get_prototype_index(apical, determinancy);}
+        prototypes.get_blended_index(&self.get_nearest_prototypes(apical, determinancy, self.blend_count))
     }
 
-    #[allow(unused_must_use)]
     pub fn create(
         prototype_positions: Vec<(f32, f32)>,
-        size: (u32, u32), 
-        max_apical: f32, 
+        max_apical: f32,
         max_determinancy: f32
     ) -> BranchPrototypesSampler {
-
-        let mut sampler = BranchPrototypesSampler {
-            prototypes: HashMap::new(),
-            voronoi: DynamicImage::new_rgba8(size.0, size.1),
+        BranchPrototypesSampler {
+            prototypes: prototype_positions,
             max_apical,
             max_determinancy,
-        };
-
-        let mut points: Vec<Point> = vec![];
-        let mut colors: Vec<[u8; 3]> = vec![];
-        let mut current_colour: [u8; 3] = [0, 0, 0];
-        for (apical, determinancy) in prototype_positions {
-            points.push(Point{x: apical as f64, y: determinancy as f64});
-
-            // generate and use a colour
-            let colour = {
-                let mut b = current_colour[2].overflowing_add(1);
-                let mut g = if b.1 {b.0 = 0; current_colour[1].overflowing_add(1)} else {(current_colour[1], false)};
-                let r = if g.1 {g.0 = 0; current_colour[0].overflowing_add(1)} else {(current_colour[0], false)};
-                if r.1 {panic!("Too many branch prototypes in use - Maximum of 16777216")}
-                [r.0, g.0, b.0]
-            };
-            current_colour = colour;
-            sampler.prototypes.insert(image::Rgba([colour[0], colour[1], colour[2], 255]), sampler.prototypes.len());
-            colors.push(colour);
-        }
-
-
-        let diagram = VoronoiDiagram::new(
-            &Point {x: 0.0, y: 0.0},
-            &Point {x: sampler.max_apical as f64, y: sampler.max_determinancy as f64},
-            &points,
-        )
-        .unwrap();
-
-        let mut image_buf: Vec<u8> = Vec::new();
-        for _i in 0..(size.0 * size.1) * 3 {
-            image_buf.push(0);
+            blend_count: 1,
         }
+    }
 
-        
-        {
-            let root = BitMapBackend::with_buffer(&mut image_buf, sampler.voronoi.dimensions()).into_drawing_area();
-            root.fill(&WHITE);
+    /// clamps the point to the space and scales it to 0..1 on both axes
+    fn normalise(&self, apical: f32, determinancy: f32) -> (f32, f32) {
+        let apical = if self.max_apical > 0.0 {apical.clamp(0.0, self.max_apical) / self.max_apical} else {0.0};
+        let determinancy = if self.max_determinancy > 0.0 {determinancy.clamp(0.0, self.max_determinancy) / self.max_determinancy} else {0.0};
+        (apical, determinancy)
+    }
 
+}
 
-            let root = root.apply_coord_spec(RangedCoord::<RangedCoordf32, RangedCoordf32>::new(
-                0f32..sampler.max_apical as f32,
-                0f32..sampler.max_determinancy as f32,
-                (0..sampler.voronoi.dimensions().0 as i32, 0..sampler.voronoi.dimensions().1 as i32),
-            ));
 
 
-            for i in 0..points.len() {
-                let p: Vec<(f32, f32)> = diagram.cells()[i].points().into_iter().map(|x| (x.x as f32, x.y as f32)).collect();
-                let color = RGBColor{
-                    0: colors[i][0],
-                    1: colors[i][1],
-                    2: colors[i][2],
-                };
+#[cfg(test)]
+mod branch_prototypes_tests {
+    use super::*;
 
-                let poly = Polygon::new(p.clone(), ShapeStyle{color: color.to_rgba(), filled: true, stroke_width: 2});
-                root.draw(&poly);
-            }
+    #[test]
+    fn nearest_prototype_test() {
+        let sampler = BranchPrototypesSampler::create(vec![(5.0, 5.0), (15.0, 15.0), (5.0, 15.0)], 20.0, 20.0);
 
-            root.present();
-        }
+        assert_eq!(sampler.get_prototype_index(4.0, 6.0), 0);
+        assert_eq!(sampler.get_prototype_index(16.0, 12.0), 1);
+        assert_eq!(sampler.get_prototype_index(6.0, 13.0), 2);
+    }
 
-        // sampler.voronoi = image::open(TEMP_IMAGE_PATH).unwrap();
-        sampler.voronoi = DynamicImage::ImageRgb8(ImageBuffer::from_raw(size.0, size.1, image_buf).unwrap());
+    #[test]
+    fn edge_of_space_test() {
+        let sampler = BranchPrototypesSampler::create(vec![(5.0, 5.0), (15.0, 15.0)], 20.0, 10.0);
 
-        sampler
+        // these used to underflow or go past the edge of the image
+        assert_eq!(sampler.get_prototype_index(0.0, 0.0), 0);
+        assert_eq!(sampler.get_prototype_index(20.0, 10.0), 1);
+        assert_eq!(sampler.get_prototype_index(-3.0, 50.0), 0);
+        assert_eq!(sampler.get_prototype_index(f32::MAX, f32::MAX), 1);
     }
 
-}
+    #[test]
+    fn normalised_distance_test() {
+        // determinacy has a much smaller range, so small changes in it matter more
+        let sampler = BranchPrototypesSampler::create(vec![(0.0, 0.0), (10.0, 1.0)], 100.0, 1.0);
 
+        assert_eq!(sampler.get_prototype_index(2.0, 0.8), 1);
+    }
 
+    #[test]
+    fn invalid_prototype_test() {
+        let error = BranchPrototypeData::new(0.1, vec![vec![1], vec![2]], vec![[0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]).err();
+        assert_eq!(error, Some(PrototypeError::DirectionCount {pairs: 3, directions: 2}));
 
+        let error = BranchPrototypeData::new(0.1, vec![vec![1]], vec![[0.0, 0.0, 0.0]]).err();
+        assert_eq!(error.map(|error| error.to_string()), Some("directions[0] has no length".to_string()));
 
+        assert_eq!(BranchPrototypes::new(Vec::new()).err(), Some(PrototypeError::NoPrototypes));
+        let error = BranchPrototypes::new(vec![(0.1, vec![vec![1]], vec![[0.0, 1.0, 0.0]]), (0.0, vec![vec![1]], vec![[0.0, 1.0, 0.0]])]).err();
+        assert_eq!(error, Some(PrototypeError::InList {index: 1, error: Box::new(PrototypeError::MatureAge(0.0))}));
+    }
 
+    #[test]
+    fn blended_prototype_test() {
+        let mut prototypes = BranchPrototypes::new(vec![
+            (1.0, vec![vec![1]], vec![[1.0, 0.0, 0.0]]),
+            (3.0, vec![vec![1]], vec![[0.0, 1.0, 0.0]]),
+            (5.0, vec![vec![2]], vec![[0.0, 0.0, 1.0], [0.0, 1.0, 0.0]]),
+        ]).unwrap();
+        let mut sampler = BranchPrototypesSampler::create(vec![(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)], 10.0, 10.0);
+        sampler.blend_count = 3;
+
+        // halfway between the first two, the third has different node counts so isn't used
+        let index = sampler.sample(&mut prototypes, 5.0, 0.0);
+        assert_eq!(index, 3);
+        assert_eq!(prototypes.prototypes[index].mature_age, 2.0);
+        let direction = prototypes.prototypes[index].directions[0];
+        assert!((direction - Vector3::new(0.5_f32.sqrt(), 0.5_f32.sqrt(), 0.0)).magnitude() < 1e-5);
+
+        // the same blend reuses the prototype, and being on a prototype doesn't blend
+        assert_eq!(sampler.sample(&mut prototypes, 5.0, 0.0), 3);
+        assert_eq!(sampler.sample(&mut prototypes, 10.0, 0.0), 1);
+        assert_eq!(prototypes.prototypes.len(), 4);
+    }
 
+    #[test]
+    fn mixed_shape_blend_test() {
+        let mut prototypes = BranchPrototypes::new(vec![
+            (1.0, vec![vec![1]], vec![[1.0, 0.0, 0.0]]),
+            (3.0, vec![vec![1]], vec![[0.0, 1.0, 0.0]]),
+            (5.0, vec![vec![2]], vec![[0.0, 0.0, 1.0], [0.0, 1.0, 0.0]]),
+        ]).unwrap();
+        let mut sampler = BranchPrototypesSampler::create(vec![(0.0, 0.0), (10.0, 0.0), (5.0, 10.0)], 10.0, 10.0);
+        sampler.blend_count = 3;
+
+        // the nearest has a shape none of the others share, so it is used alone
+        assert_eq!(sampler.get_nearest_prototypes(5.0, 9.0, 3)[0].0, 2);
+        assert_eq!(sampler.sample(&mut prototypes, 5.0, 9.0), 2);
+        assert_eq!(prototypes.prototypes.len(), 3);
+
+        // the second nearest has a different shape, so only the first and third are blended
+        let nearest: Vec<usize> = sampler.get_nearest_prototypes(2.0, 4.0, 3).iter().map(|(index, _)| *index).collect();
+        assert_eq!(nearest, vec![0, 2, 1]);
+        let index = sampler.sample(&mut prototypes, 2.0, 4.0);
+        assert_eq!(index, 3);
+        assert_eq!(prototypes.prototypes[index].node_counts, vec![vec![1]]);
+        assert_eq!(prototypes.prototypes[index].directions.len(), 1);
+        let mature_age = prototypes.prototypes[index].mature_age;
+        assert!(mature_age > 1.0 && mature_age < 2.0, "the blend should lean towards the nearest prototype, got {}", mature_age);
+    }
+}
@@ -0,0 +1,230 @@
+use std::{collections::HashMap, fmt};
+use super::{
+    branch_prototypes::{BranchPrototypeData, PrototypeError, PrototypePreset, PrototypeShape},
+    super::maths::{vector_three::Vector3, matrix_three::Matrix3},
+};
+
+
+/// the longest string the rules are allowed to grow, to stop runaway rules using all the memory
+const MAX_LSYSTEM_LENGTH: usize = 1_000_000;
+
+
+/// A bracketed L-system that can be turned into a branch prototype
+///
+/// The turtle starts at the root node of the branch facing up the branch, +y. The symbols it understands are:
+/// - F: grow a new node forwards from the current one
+/// - + and -: turn left and right
+/// - & and ^: pitch down and up
+/// - \ and /: roll left and right
+/// - |: turn around
+/// - [ and ]: start and end a side branch
+///
+/// Any other symbol is only used by the rules
+pub struct LSystem {
+    axiom: String,
+    rules: HashMap<char, String>,
+    iterations: u32,
+    angles: (f32, f32, f32),
+    mature_age: f32,
+}
+
+/// An error from turning an L-system into a branch prototype
+#[derive(Debug, Clone, PartialEq)]
+pub enum LSystemError {
+    /// a ] with no [ before it, at the given position in the expanded string
+    UnmatchedClose(usize),
+    /// there are [ without a matching ]
+    UnclosedBranches(usize),
+    /// the string has no F so there are no nodes
+    NoSegments,
+    /// the string became longer than the limit while applying the rules
+    TooLong(usize),
+    /// the prototype made from the string is invalid, with the reason it was rejected
+    Prototype(PrototypeError),
+}
+
+impl fmt::Display for LSystemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LSystemError::UnmatchedClose(position) => write!(f, "the ] at position {} has no matching [", position),
+            LSystemError::UnclosedBranches(count) => write!(f, "{} [ are never closed", count),
+            LSystemError::NoSegments => write!(f, "the l-system has no F symbols, so there are no nodes"),
+            LSystemError::TooLong(length) => write!(f, "the l-system grew to over {} symbols", length),
+            LSystemError::Prototype(err) => write!(f, "the l-system makes an invalid prototype: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LSystemError {}
+
+impl From<PrototypeError> for LSystemError {
+    fn from(err: PrototypeError) -> Self {
+        LSystemError::Prototype(err)
+    }
+}
+
+
+impl LSystem {
+    /// creates an l-system with no rules, turtle angles of 25 degrees and a mature age of 0.1
+    pub fn new(axiom: &str) -> LSystem {
+        LSystem {
+            axiom: axiom.to_string(),
+            rules: HashMap::new(),
+            iterations: 1,
+            angles: (25.0, 25.0, 25.0),
+            mature_age: 0.1,
+        }
+    }
+
+    /// adds a rule that replaces the symbol with the replacement each iteration, overrides any previous rule for the symbol
+    pub fn add_rule(&mut self, symbol: char, replacement: &str) -> &mut LSystem {
+        self.rules.insert(symbol, replacement.to_string());
+        self
+    }
+
+    /// sets how many times the rules are applied to the axiom, defaults to 1
+    pub fn set_iterations(&mut self, iterations: u32) -> &mut LSystem {
+        self.iterations = iterations;
+        self
+    }
+
+    /// sets the turtle's turn (+ -), pitch (& ^) and roll (\ /) angles in degrees, as is usual for l-systems
+    pub fn set_angles(&mut self, turn: f32, pitch: f32, roll: f32) -> &mut LSystem {
+        self.angles = (turn, pitch, roll);
+        self
+    }
+
+    /// sets the age the branch is fully grown at, defaults to 0.1
+    pub fn set_mature_age(&mut self, age: f32) -> &mut LSystem {
+        self.mature_age = age;
+        self
+    }
+
+
+    /// applies the rules to the axiom for every iteration and returns the resulting string
+    pub fn expand(&self) -> Result<String, LSystemError> {
+        let mut current = self.axiom.clone();
+        for _ in 0..self.iterations {
+            let mut next = String::with_capacity(current.len());
+            for symbol in current.chars() {
+                match self.rules.get(&symbol) {
+                    Some(replacement) => next.push_str(replacement),
+                    None => next.push(symbol),
+                }
+                if next.len() > MAX_LSYSTEM_LENGTH {return Err(LSystemError::TooLong(MAX_LSYSTEM_LENGTH));}
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+
+    /// expands the l-system and draws it with the turtle, returning the prototype
+    pub fn to_prototype(&self) -> Result<BranchPrototypeData, LSystemError> {
+        let (node_counts, directions) = self.interpret(&self.expand()?)?;
+        Ok(BranchPrototypeData::new(self.mature_age, node_counts, directions)?)
+    }
+
+    /// turns the l-system into the form used by the app builders' set_branch_presets
+    pub fn to_preset(&self) -> Result<PrototypePreset, LSystemError> {
+        Ok(self.to_prototype()?.to_preset())
+    }
+
+
+    /// draws the string with the turtle, then orders the nodes into layers by their depth from the root
+    fn interpret(&self, symbols: &str) -> Result<PrototypeShape, LSystemError> {
+        let (turn, pitch, roll) = (self.angles.0.to_radians(), self.angles.1.to_radians(), self.angles.2.to_radians());
+
+        // the children of each node and the direction each node grew in, node 0 is the root
+        let mut children: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut directions: Vec<Vector3> = vec![Vector3::Y()];
+
+        // the turtle is its current node, heading, left and up
+        let mut turtle = (0, Vector3::Y(), Vector3::new(-1.0, 0.0, 0.0), Vector3::Z());
+        let mut stack = Vec::new();
+
+        for (position, symbol) in symbols.chars().enumerate() {
+            let (_, heading, left, up) = turtle;
+            match symbol {
+                'F' => {
+                    children.push(Vec::new());
+                    directions.push(heading.normalised());
+                    let node = children.len() - 1;
+                    children[turtle.0].push(node);
+                    turtle.0 = node;
+                }
+                '+' => turtle = rotate_turtle(turtle, turn, up),
+                '-' => turtle = rotate_turtle(turtle, -turn, up),
+                '&' => turtle = rotate_turtle(turtle, pitch, left),
+                '^' => turtle = rotate_turtle(turtle, -pitch, left),
+                '\\' => turtle = rotate_turtle(turtle, roll, heading),
+                '/' => turtle = rotate_turtle(turtle, -roll, heading),
+                '|' => turtle = rotate_turtle(turtle, std::f32::consts::PI, up),
+                '[' => stack.push(turtle),
+                ']' => turtle = stack.pop().ok_or(LSystemError::UnmatchedClose(position))?,
+                _ => {}
+            }
+        }
+        if !stack.is_empty() {return Err(LSystemError::UnclosedBranches(stack.len()));}
+        if children.len() == 1 {return Err(LSystemError::NoSegments);}
+
+        // walk the tree a layer at a time, which is the order the node pairs are used in
+        let mut node_counts = Vec::new();
+        let mut pair_directions = Vec::new();
+        let mut layer = vec![0];
+        while layer.iter().any(|node| !children[*node].is_empty()) {
+            node_counts.push(layer.iter().map(|node| children[*node].len() as u32).collect());
+            layer = layer.iter().flat_map(|node| children[*node].iter().copied()).collect();
+            pair_directions.extend(layer.iter().map(|node| -> [f32; 3] {directions[*node].into()}));
+        }
+
+        Ok((node_counts, pair_directions))
+    }
+}
+
+
+/// rotates the turtle's heading, left and up around the axis
+fn rotate_turtle(turtle: (usize, Vector3, Vector3, Vector3), angle: f32, axis: Vector3) -> (usize, Vector3, Vector3, Vector3) {
+    let rotation = Matrix3::from_angle_and_axis(angle, axis);
+    (turtle.0, turtle.1.transform(rotation), turtle.2.transform(rotation), turtle.3.transform(rotation))
+}
+
+
+
+#[cfg(test)]
+mod lsystem_tests {
+    use super::*;
+
+    #[test]
+    fn expand_test() {
+        let mut lsystem = LSystem::new("X");
+        lsystem.add_rule('X', "F[+X]X").set_iterations(2);
+
+        assert_eq!(lsystem.expand().unwrap(), "F[+F[+X]X]F[+X]X");
+    }
+
+    #[test]
+    fn prototype_layers_test() {
+        let mut lsystem = LSystem::new("F[+F]F");
+        lsystem.set_iterations(0).set_angles(90.0, 0.0, 0.0);
+        let (mature_age, node_counts, directions) = lsystem.to_preset().unwrap();
+
+        assert_eq!(mature_age, 0.1);
+        assert_eq!(node_counts, vec![vec![1], vec![2]]);
+        assert_eq!(directions.len(), 3);
+        assert!((Vector3::from(directions[0]) - Vector3::Y()).magnitude() < 1e-5);
+        // the side branch is turned 90 degrees, and the main branch carries on up
+        assert!(Vector3::from(directions[1]).dot(Vector3::Y()).abs() < 1e-5);
+        assert!((Vector3::from(directions[2]) - Vector3::Y()).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn bracket_error_test() {
+        let mut lsystem = LSystem::new("F]F");
+        lsystem.set_iterations(0);
+        assert_eq!(lsystem.to_prototype().err(), Some(LSystemError::UnmatchedClose(1)));
+
+        let mut lsystem = LSystem::new("F[F");
+        lsystem.set_iterations(0);
+        assert_eq!(lsystem.to_prototype().err(), Some(LSystemError::UnclosedBranches(1)));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod branch;
 pub mod branch_prototypes;
 pub mod branch_node;
-pub mod branch_development;
\ No newline at end of file
+pub mod branch_development;
+pub mod branch_mesh;
+pub mod prototype_generator;
+pub mod lsystem;
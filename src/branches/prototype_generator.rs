@@ -0,0 +1,166 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use super::{
+    branch_prototypes::{BranchPrototypeData, PrototypeError, PrototypePreset, PrototypeShape},
+    super::maths::{vector_three::Vector3, rotate_away},
+};
+
+
+/// the most a continuing node can bend away from its parent when the straightness is 0
+const MAX_BEND_ANGLE: f32 = std::f32::consts::FRAC_PI_4;
+
+
+/// Builds branch prototypes from a few high level parameters instead of writing out the node counts and directions
+///
+/// Every node has a child that continues the branch, and may have a lateral child that grows off to the side.
+/// Lateral children are spread around the branch by the phyllotaxis angle, like leaves around a stem
+pub struct PrototypeGenerator {
+    mature_age: f32,
+    layers: u32,
+    branching_probability: f32,
+    divergence_angle: f32,
+    phyllotaxis_angle: f32,
+    straightness: f32,
+    seed: u64,
+}
+
+
+impl PrototypeGenerator {
+    /// creates a generator with the default parameters, which make a 4 layer prototype
+    pub fn new() -> PrototypeGenerator {
+        PrototypeGenerator {
+            mature_age: 0.1,
+            layers: 4,
+            branching_probability: 0.5,
+            divergence_angle: 0.6,
+            phyllotaxis_angle: 2.4, // close to the golden angle
+            straightness: 0.8,
+            seed: 0,
+        }
+    }
+
+    /// sets the age the branch is fully grown at, defaults to 0.1
+    pub fn set_mature_age(&mut self, age: f32) -> &mut PrototypeGenerator {
+        self.mature_age = age;
+        self
+    }
+
+    /// sets the number of layers of nodes including the root node, at least 2, defaults to 4
+    pub fn set_layers(&mut self, layers: u32) -> &mut PrototypeGenerator {
+        self.layers = layers.max(2);
+        self
+    }
+
+    /// sets the chance of each node having a lateral child, from 0 to 1, defaults to 0.5
+    pub fn set_branching_probability(&mut self, probability: f32) -> &mut PrototypeGenerator {
+        self.branching_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// sets the angle in radians between a lateral child and its parent's direction, defaults to 0.6
+    pub fn set_divergence_angle(&mut self, angle: f32) -> &mut PrototypeGenerator {
+        self.divergence_angle = angle;
+        self
+    }
+
+    /// sets the angle in radians around the branch between one lateral child and the next, defaults to 2.4
+    pub fn set_phyllotaxis_angle(&mut self, angle: f32) -> &mut PrototypeGenerator {
+        self.phyllotaxis_angle = angle;
+        self
+    }
+
+    /// sets how closely continuing children follow their parent's direction, from 0 to 1, defaults to 0.8
+    pub fn set_straightness(&mut self, straightness: f32) -> &mut PrototypeGenerator {
+        self.straightness = straightness.clamp(0.0, 1.0);
+        self
+    }
+
+    /// sets the seed for the random parts of the prototype, the same parameters and seed always give the same prototype
+    pub fn set_seed(&mut self, seed: u64) -> &mut PrototypeGenerator {
+        self.seed = seed;
+        self
+    }
+
+
+    /// generates the node counts and directions and returns the validated prototype
+    pub fn generate(&self) -> Result<BranchPrototypeData, PrototypeError> {
+        let (node_counts, directions) = self.generate_shape();
+        BranchPrototypeData::new(self.mature_age, node_counts, directions)
+    }
+
+    /// generates the prototype in the form used by the app builders' set_branch_presets
+    pub fn generate_preset(&self) -> Result<PrototypePreset, PrototypeError> {
+        Ok(self.generate()?.to_preset())
+    }
+
+    /// creates the node counts and directions, layer by layer from the root node
+    fn generate_shape(&self) -> PrototypeShape {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut node_counts: Vec<Vec<u32>> = Vec::new();
+        let mut directions: Vec<[f32; 3]> = Vec::new();
+        let mut lateral_count = 0;
+
+        // the direction each node on the layer grew in, the root grows straight up
+        let mut layer = vec![Vector3::Y()];
+        for _ in 1..self.layers {
+            let mut layer_counts = Vec::new();
+            let mut next_layer = Vec::new();
+
+            for parent in layer.iter() {
+                let first_child = next_layer.len();
+                let bend = (1.0 - self.straightness) * MAX_BEND_ANGLE * rng.gen::<f32>();
+                next_layer.push(rotate_away(*parent, bend, rng.gen_range(0.0..std::f32::consts::TAU)));
+
+                if rng.gen::<f32>() < self.branching_probability {
+                    next_layer.push(rotate_away(*parent, self.divergence_angle, self.phyllotaxis_angle * lateral_count as f32));
+                    lateral_count += 1;
+                }
+                layer_counts.push((next_layer.len() - first_child) as u32);
+            }
+
+            node_counts.push(layer_counts);
+            directions.extend(next_layer.iter().map(|direction| -> [f32; 3] {(*direction).into()}));
+            layer = next_layer;
+        }
+
+        (node_counts, directions)
+    }
+}
+
+impl Default for PrototypeGenerator {
+    fn default() -> Self {
+        PrototypeGenerator::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod prototype_generator_tests {
+    use super::*;
+
+    #[test]
+    fn generated_prototype_test() {
+        let mut generator = PrototypeGenerator::new();
+        generator.set_layers(6).set_branching_probability(0.7).set_seed(3);
+        let prototype = generator.generate().unwrap();
+
+        assert_eq!(prototype.node_counts.len(), 5);
+        assert!(BranchPrototypeData::check_shape(&prototype.node_counts, prototype.directions.len()).is_ok());
+        assert!(prototype.directions.iter().all(|direction| (direction.magnitude() - 1.0).abs() < 1e-5));
+
+        // the same seed gives the same prototype
+        assert_eq!(generator.generate_preset().unwrap(), prototype.to_preset());
+    }
+
+    #[test]
+    fn divergence_angle_test() {
+        let mut generator = PrototypeGenerator::new();
+        generator.set_layers(2).set_branching_probability(1.0).set_straightness(1.0).set_divergence_angle(0.5);
+        let (node_counts, directions) = generator.generate_shape();
+
+        assert_eq!(node_counts, vec![vec![2]]);
+        assert!((Vector3::from(directions[0]).angle_to(Vector3::Y())).abs() < 1e-4);
+        assert!((Vector3::from(directions[1]).angle_to(Vector3::Y()) - 0.5).abs() < 1e-4);
+    }
+}
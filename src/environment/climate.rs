@@ -0,0 +1,343 @@
+use std::{f32::consts::PI, fmt, fs, io, path::Path};
+use bevy_ecs::prelude::*;
+use rand_distr::{Normal, Distribution};
+use serde::{Serialize, Deserialize};
+use super::params::*;
+
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+/// ClimateDriver changes the temperature and precipitation over time, and updates MoistureAndTemp every step
+///
+/// The climate has a seasonal cycle over each year, noise that changes from one year to the next, and long term trends.
+/// The seasonal cycle is averaged over each step, so steps of a whole year only see the annual climate.
+/// The year is coldest and wettest at its start
+///
+/// Components:
+///     - mean_temperature: the annual mean temperature at y=0 at the start of the simulation, degrees celsius
+///     - mean_precipitation: the annual precipitation at the start of the simulation, cm
+///     - seasonal_temperature: how far the temperature moves above and below the annual mean over the year
+///     - seasonal_precipitation: the fraction of the annual precipitation that moves above and below it over the year, from 0 to 1
+///     - temperature_noise: the standard deviation of the difference between each year's temperature and the mean
+///     - precipitation_noise: the standard deviation of the same difference for precipitation, as a fraction of the mean
+///     - warming_rate: the change in the mean temperature each year
+///     - precipitation_trend: the change in the mean precipitation each year, cm
+///     - series: recorded weather that replaces the means, seasons, noise and trends when there is one
+pub struct ClimateDriver {
+    pub mean_temperature: f32,
+    pub mean_precipitation: f32,
+    pub seasonal_temperature: f32,
+    pub seasonal_precipitation: f32,
+    pub temperature_noise: f32,
+    pub precipitation_noise: f32,
+    pub warming_rate: f32,
+    pub precipitation_trend: f32,
+    pub series: Option<ClimateSeries>,
+    elapsed_years: f32,
+    anomaly_year: Option<i64>,
+    anomaly: (f32, f32),
+    annual: (f32, f32),
+}
+
+
+impl ClimateDriver {
+
+    /// creates a climate that stays at the given annual means, without seasons, noise or trends
+    pub fn new(mean_temperature: f32, mean_precipitation: f32) -> Self {
+        ClimateDriver {
+            mean_temperature,
+            mean_precipitation,
+            seasonal_temperature: 0.0,
+            seasonal_precipitation: 0.0,
+            temperature_noise: 0.0,
+            precipitation_noise: 0.0,
+            warming_rate: 0.0,
+            precipitation_trend: 0.0,
+            series: None,
+            elapsed_years: 0.0,
+            anomaly_year: None,
+            anomaly: (0.0, 0.0),
+            annual: (mean_temperature, mean_precipitation),
+        }
+    }
+
+    /// creates a climate that replays the recorded weather, starting from the start of its first year
+    pub fn from_series(series: ClimateSeries) -> Self {
+        let (temperature, precipitation) = series.mean_between(0.0, 1.0);
+        ClimateDriver {
+            series: Some(series),
+            ..ClimateDriver::new(temperature, precipitation)
+        }
+    }
+
+    /// returns the temperature at y=0 and the precipitation of the current year, without the seasonal cycle
+    pub fn annual_climate(&self) -> (f32, f32) {
+        self.annual
+    }
+
+    /// moves the climate forward by the given years, and returns the mean temperature at y=0 and precipitation over them
+    ///
+    /// The yearly noise is sampled once for each year, at the end of the step
+    pub fn step(&mut self, years: f32, rng: &mut impl rand::Rng) -> (f32, f32) {
+        let start = self.elapsed_years;
+        self.elapsed_years += years;
+
+        if let Some(series) = &self.series {
+            self.annual = series.mean_between(self.elapsed_years - 1.0, self.elapsed_years);
+            return series.mean_between(start, self.elapsed_years);
+        }
+
+        let year = self.elapsed_years.floor() as i64;
+        if self.anomaly_year != Some(year) {
+            self.anomaly_year = Some(year);
+            self.anomaly = (
+                sample_noise(self.temperature_noise, rng),
+                sample_noise(self.precipitation_noise, rng),
+            );
+        }
+
+        let temperature = self.mean_temperature + self.warming_rate * self.elapsed_years + self.anomaly.0;
+        let precipitation = ((self.mean_precipitation + self.precipitation_trend * self.elapsed_years) * (1.0 + self.anomaly.1)).max(0.0);
+        self.annual = (temperature, precipitation);
+
+        // the mean of cos(2 pi t) over the step, which is 0 over whole years
+        let season = if years > 0.0 {
+            ((2.0 * PI * self.elapsed_years).sin() - (2.0 * PI * start).sin()) / (2.0 * PI * years)
+        } else {
+            (2.0 * PI * start).cos()
+        };
+
+        (
+            temperature - self.seasonal_temperature * season,
+            (precipitation * (1.0 + self.seasonal_precipitation.clamp(0.0, 1.0) * season)).max(0.0),
+        )
+    }
+}
+
+/// An error from loading a climate series
+#[derive(Debug)]
+pub enum ClimateSeriesError {
+    Io(io::Error),
+    /// a line of the file could not be read, lines are counted from 1
+    Parse {
+        line: usize,
+        reason: String,
+    },
+    /// the file has no samples
+    Empty,
+}
+
+impl fmt::Display for ClimateSeriesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClimateSeriesError::Io(err) => write!(f, "failed to read climate series: {}", err),
+            ClimateSeriesError::Parse{line, reason} => write!(f, "failed to parse climate series at line {}: {}", line, reason),
+            ClimateSeriesError::Empty => write!(f, "climate series has no samples"),
+        }
+    }
+}
+
+impl std::error::Error for ClimateSeriesError {}
+
+impl From<io::Error> for ClimateSeriesError {
+    fn from(err: io::Error) -> Self {
+        ClimateSeriesError::Io(err)
+    }
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// ClimateSeries is recorded weather, the temperature and precipitation are interpolated linearly between samples
+/// and held at the first and last samples outside them
+///
+/// The csv has rows of either (year, temperature, precipitation) or (year, month, temperature, precipitation), but not a mix of both,
+/// with an optional header and lines starting with # ignored.
+/// Yearly rows give the annual precipitation, and monthly rows the precipitation of the month, both in cm.
+/// Temperatures are in degrees celsius at y=0
+///
+/// Components:
+///     - start_year: the year the simulation starts at the beginning of
+///     - samples: (years since the start, temperature, annual rate of precipitation), yearly samples are in the middle of their year and monthly ones in the middle of their month
+pub struct ClimateSeries {
+    start_year: i32,
+    samples: Vec<(f32, f32, f32)>,
+}
+
+impl ClimateSeries {
+
+    /// loads a climate series from a csv file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClimateSeriesError> {
+        Self::from_csv(&fs::read_to_string(path)?)
+    }
+
+    /// reads a climate series from the text of a csv file, the rows must be in time order
+    pub fn from_csv(text: &str) -> Result<Self, ClimateSeriesError> {
+        let mut rows: Vec<(i32, Option<u32>, f32, f32)> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {continue;}
+            let parse_error = |reason: String| ClimateSeriesError::Parse{line: i + 1, reason};
+
+            let values: Vec<&str> = line.split(',').map(|value| value.trim()).collect();
+            // the header is the first row with a year that isn't a number
+            if rows.is_empty() && values[0].parse::<f32>().is_err() {continue;}
+
+            let number = |value: &str| value.parse::<f32>().ok().filter(|x| x.is_finite()).ok_or_else(|| parse_error(format!("`{}` is not a number", value)));
+            let year = values[0].parse::<i32>().map_err(|_| parse_error(format!("`{}` is not a year", values[0])))?;
+            let row = match values.len() {
+                3 => (year, None, number(values[1])?, number(values[2])?),
+                4 => {
+                    let month = values[1].parse::<u32>().ok().filter(|month| (1..=12).contains(month))
+                        .ok_or_else(|| parse_error(format!("`{}` is not a month from 1 to 12", values[1])))?;
+                    (year, Some(month), number(values[2])?, number(values[3])?)
+                }
+                count => return Err(parse_error(format!("expected 3 or 4 values but found {}", count))),
+            };
+            if row.3 < 0.0 {return Err(parse_error("precipitation must not be negative".into()));}
+            if let Some(last) = rows.last() {
+                if row.1.is_some() != last.1.is_some() {return Err(parse_error("rows must be all yearly or all monthly".into()));}
+                if (row.0, row.1.unwrap_or(0)) <= (last.0, last.1.unwrap_or(0)) {return Err(parse_error("rows must be in time order".into()));}
+            }
+            rows.push(row);
+        }
+
+        let start_year = rows.first().ok_or(ClimateSeriesError::Empty)?.0;
+        let samples = rows.into_iter().map(|(year, month, temperature, precipitation)| match month {
+            Some(month) => ((year - start_year) as f32 + (month as f32 - 0.5) / 12.0, temperature, precipitation * 12.0),
+            None => ((year - start_year) as f32 + 0.5, temperature, precipitation),
+        }).collect();
+
+        Ok(ClimateSeries{start_year, samples})
+    }
+
+    /// returns the temperature and annual rate of precipitation at a time in years since the start
+    pub fn value_at(&self, time: f32) -> (f32, f32) {
+        let next = self.samples.partition_point(|sample| sample.0 <= time);
+        if next == 0 {
+            let first = self.samples[0];
+            return (first.1, first.2);
+        }
+        if next == self.samples.len() {
+            let last = self.samples[next - 1];
+            return (last.1, last.2);
+        }
+
+        let (before, after) = (self.samples[next - 1], self.samples[next]);
+        let t = (time - before.0) / (after.0 - before.0);
+        (before.1 + (after.1 - before.1) * t, before.2 + (after.2 - before.2) * t)
+    }
+
+    /// returns the mean temperature and annual rate of precipitation between two times, in years since the start
+    pub fn mean_between(&self, start: f32, end: f32) -> (f32, f32) {
+        if end <= start {return self.value_at(start);}
+
+        // the values are linear between the samples, so the trapezium rule between them is exact
+        let times: Vec<f32> = std::iter::once(start)
+            .chain(self.samples.iter().map(|sample| sample.0).filter(|time| *time > start && *time < end))
+            .chain(std::iter::once(end))
+            .collect();
+        let mut total = (0.0, 0.0);
+        for pair in times.windows(2) {
+            let (a, b) = (self.value_at(pair[0]), self.value_at(pair[1]));
+            let width = pair[1] - pair[0];
+            total.0 += (a.0 + b.0) / 2.0 * width;
+            total.1 += (a.1 + b.1) / 2.0 * width;
+        }
+        (total.0 / (end - start), total.1 / (end - start))
+    }
+}
+
+
+fn sample_noise(std_dev: f32, rng: &mut impl rand::Rng) -> f32 {
+    if std_dev <= 0.0 {return 0.0;}
+    Normal::new(0.0, std_dev).unwrap().sample(rng)
+}
+
+
+/// steps the climate driver and sets the temperature and moisture used by the rest of the simulation
+pub fn update_climate(
+    mut climate: ResMut<ClimateDriver>,
+    mut environment: ResMut<MoistureAndTemp>,
+    timestep: Res<PhysicalAgeStep>,
+    mut rng: ResMut<SimulationRng>,
+) {
+    let (temperature, precipitation) = climate.step(timestep.step, &mut rng.rng);
+    environment.temp_at_zero = temperature;
+    environment.moisture = precipitation;
+}
+
+
+
+#[cfg(test)]
+mod climate_tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn seasons_and_trend_test() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut climate = ClimateDriver::new(10.0, 100.0);
+        climate.seasonal_temperature = 8.0;
+        climate.seasonal_precipitation = 0.5;
+        climate.warming_rate = 0.1;
+
+        // whole year steps only see the annual climate
+        let (temperature, precipitation) = climate.step(1.0, &mut rng);
+        assert!((temperature - 10.1).abs() < 0.0001);
+        assert!((precipitation - 100.0).abs() < 0.001);
+
+        // winter is colder and wetter than summer
+        let winter = climate.step(0.1, &mut rng);
+        climate.step(0.35, &mut rng);
+        let summer = climate.step(0.1, &mut rng);
+        assert!(winter.0 < summer.0 - 10.0);
+        assert!(winter.1 > summer.1);
+
+        for _ in 0..10 {climate.step(0.5, &mut rng);}
+        assert!((climate.annual_climate().0 - (10.0 + 0.1 * 6.55)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn noise_test() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut climate = ClimateDriver::new(10.0, 100.0);
+        climate.temperature_noise = 1.0;
+        climate.precipitation_noise = 0.1;
+
+        // the noise stays the same within a year
+        let first = climate.step(0.25, &mut rng);
+        let second = climate.step(0.25, &mut rng);
+        assert_eq!(first, second);
+
+        let years: Vec<(f32, f32)> = (0..20).map(|_| climate.step(1.0, &mut rng)).collect();
+        assert!(years.iter().any(|year| year.0 != years[0].0));
+        assert!(years.iter().all(|year| year.1 >= 0.0));
+    }
+
+    #[test]
+    fn series_test() {
+        let yearly = ClimateSeries::from_csv("year,temperature,precipitation\n# a comment\n2000, 10, 100\n2002, 14, 60\n").unwrap();
+        assert_eq!(yearly.start_year, 2000);
+        assert_eq!(yearly.value_at(0.0), (10.0, 100.0));
+        assert_eq!(yearly.value_at(1.5), (12.0, 80.0));
+        assert_eq!(yearly.value_at(10.0), (14.0, 60.0));
+        assert_eq!(yearly.mean_between(1.0, 2.0), (12.0, 80.0));
+
+        // monthly precipitation is turned into an annual rate
+        let monthly = ClimateSeries::from_csv("1990,1,0,10\n1990,2,4,5\n").unwrap();
+        assert_eq!(monthly.value_at(1.0 / 24.0), (0.0, 120.0));
+        let (temperature, precipitation) = monthly.value_at(1.0 / 12.0);
+        assert!((temperature - 2.0).abs() < 0.0001 && (precipitation - 90.0).abs() < 0.001);
+
+        let mut climate = ClimateDriver::from_series(yearly);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        climate.step(1.0, &mut rng);
+        assert_eq!(climate.step(1.0, &mut rng), (12.0, 80.0));
+        assert_eq!(climate.annual_climate(), (12.0, 80.0));
+
+        assert!(matches!(ClimateSeries::from_csv("2000,10,100\n1999,10,100"), Err(ClimateSeriesError::Parse{line: 2, ..})));
+        assert!(matches!(ClimateSeries::from_csv("2000,10,100\n2000,6,10,10"), Err(ClimateSeriesError::Parse{line: 2, ..})));
+        assert!(matches!(ClimateSeries::from_csv("2000,10"), Err(ClimateSeriesError::Parse{line: 1, ..})));
+        assert!(matches!(ClimateSeries::from_csv("year,temperature,precipitation"), Err(ClimateSeriesError::Empty)));
+    }
+}
@@ -0,0 +1,157 @@
+use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+use super::{
+    super::maths::{vector_three::Vector3, colliders::Collider},
+    light_cells::{LightCells, LightModel},
+    terrain::{TerrainCollider, TerrainTag},
+};
+
+
+/// the height above the ground the light is sampled at in m, about the height of a seedling
+const SAMPLE_HEIGHT: f32 = 0.1;
+
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+/// GroundLight stores the light reaching the forest floor in a grid of square cells over the terrain
+///
+/// The light is sampled just above the terrain under the centre of each cell, from 0 to 1.
+/// Positions outside the grid use the nearest cell on its edge, and cells with no terrain under them are fully lit
+///
+/// Components:
+///     - origin: the x and z of the corner of the grid with the lowest coordinates
+///     - cell_size: the side length of each cell in m
+///     - width and depth: the number of cells along x and z
+///     - surface: the point on the terrain under the centre of each cell, empty until the terrain has been found
+pub struct GroundLight {
+    origin: (f32, f32),
+    cell_size: f32,
+    width: usize,
+    depth: usize,
+    surface: Vec<Option<Vector3>>,
+    light: Vec<f32>,
+}
+
+
+impl GroundLight {
+
+    /// creates a grid covering the given x and z ranges, with every cell fully lit
+    ///
+    /// The cell size must be positive, the app builders make sure of this
+    pub fn new(x_range: (f32, f32), z_range: (f32, f32), cell_size: f32) -> Self {
+        let width = (((x_range.1 - x_range.0) / cell_size).ceil() as usize).max(1);
+        let depth = (((z_range.1 - z_range.0) / cell_size).ceil() as usize).max(1);
+
+        GroundLight {
+            origin: (x_range.0, z_range.0),
+            cell_size,
+            width,
+            depth,
+            surface: Vec::new(),
+            light: vec![1.0; width * depth],
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// returns the number of cells along x and z
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.depth)
+    }
+
+    /// returns the x and z of the corner of the grid with the lowest coordinates
+    pub fn origin(&self) -> (f32, f32) {
+        self.origin
+    }
+
+    /// returns whether the terrain under the cells has been found
+    pub fn has_surface(&self) -> bool {
+        self.surface.len() == self.light.len()
+    }
+
+    /// finds the point on the terrain under the centre of each cell, or none if there isn't one
+    pub fn find_surface(&mut self, find_point: impl Fn(f32, f32) -> Option<Vector3>) {
+        self.surface = (0..self.width * self.depth).map(|index| {
+            let (x, z) = (index % self.width, index / self.width);
+            find_point(self.origin.0 + (x as f32 + 0.5) * self.cell_size, self.origin.1 + (z as f32 + 0.5) * self.cell_size)
+        }).collect();
+    }
+
+    /// samples the light just above the terrain in every cell, does nothing until the surface has been found
+    pub fn update(&mut self, light_at: impl Fn(Vector3) -> f32) {
+        for (light, point) in self.light.iter_mut().zip(self.surface.iter()) {
+            *light = match point {
+                Some(point) => light_at(*point + Vector3::Y() * SAMPLE_HEIGHT),
+                None => 1.0,
+            };
+        }
+    }
+
+    /// returns the coordinates of the cell the position is over, clamped to the grid
+    fn cell_coords(&self, position: Vector3) -> (usize, usize) {
+        let x = ((position.x - self.origin.0) / self.cell_size).floor().clamp(0.0, (self.width - 1) as f32);
+        let z = ((position.z - self.origin.1) / self.cell_size).floor().clamp(0.0, (self.depth - 1) as f32);
+        (x as usize, z as usize)
+    }
+
+    /// returns the light reaching the ground in the cell the position is over, from 0 to 1
+    pub fn get_light(&self, position: impl Into<Vector3>) -> f32 {
+        let (x, z) = self.cell_coords(position.into());
+        self.light[z * self.width + x]
+    }
+
+    /// returns the light reaching the ground in every cell, with x changing fastest then z
+    pub fn light(&self) -> &[f32] {
+        &self.light
+    }
+}
+
+
+/// samples the light reaching the terrain with the light model L, finding the terrain under each cell the first time it runs
+///
+/// Must be called after the light cells are filled
+pub fn update_ground_light<L: LightModel>(
+    terrain_query: Query<&TerrainCollider, With<TerrainTag>>,
+    light_cells: Res<LightCells>,
+    light_model: Res<L>,
+    mut ground_light: ResMut<GroundLight>,
+) {
+    if !ground_light.has_surface() {
+        let terrain = terrain_query.single();
+        ground_light.find_surface(|x, z| {
+            terrain.collider.check_ray([x, terrain.max_height, z], [0, -1, 0], None).map(|hit| hit.hit_position)
+        });
+    }
+    ground_light.update(|position| light_model.get_light_at(&light_cells, position));
+}
+
+
+
+#[cfg(test)]
+mod ground_light_tests {
+    use super::*;
+    use super::super::light_cells::ColumnLight;
+
+    #[test]
+    fn ground_light_test() {
+        let mut ground_light = GroundLight::new((-2.0, 2.0), (0.0, 2.0), 1.0);
+        assert_eq!(ground_light.dimensions(), (4, 2));
+        assert_eq!(ground_light.get_light([0.5, 0.0, 0.5]), 1.0);
+
+        // the ground is at y = 1 apart from a hole along the far x edge, with shade close enough above it to reach the ground over one cell and the hole
+        ground_light.find_surface(|x, z| if x > 1.0 {None} else {Some(Vector3::new(x, 1.0, z))});
+        assert!(ground_light.has_surface());
+        let mut cells = LightCells::new(2, 1.0);
+        cells.add_volume_to_cell([0, 3, 0], 2.0);
+        cells.add_volume_to_cell([1, 3, 1], 2.0);
+        cells.propagate_shadows();
+        ground_light.update(|position| ColumnLight.get_light_at(&cells, position));
+
+        assert_eq!(ground_light.get_light([0.5, 0.0, 0.5]), (-2.0_f32).exp());
+        assert_eq!(ground_light.light()[2], (-2.0_f32).exp());
+        assert_eq!(ground_light.get_light([-0.5, 0.0, 0.5]), 1.0);
+        assert_eq!(ground_light.get_light([1.5, 0.0, 1.5]), 1.0);
+        assert_eq!(ground_light.get_light([0.5, 0.0, -10.0]), (-2.0_f32).exp());
+    }
+}
@@ -1,21 +1,91 @@
 #![allow(dead_code, unused_variables, unused_imports)]
 use std::{collections::HashMap, fmt::Debug};
-use super::super::maths::vector_three_int::Vector3Int;
+use super::super::maths::{vector_three::Vector3, vector_three_int::Vector3Int};
 use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
 
 
-#[derive(Resource)]
+/// the side length of each chunk of cells
+const CHUNK_SIZE: i32 = 16;
+/// the number of cells in each column of a chunk
+const CHUNK_COLUMN: usize = CHUNK_SIZE as usize;
+/// the number of cells in each chunk
+const CHUNK_VOLUME: usize = CHUNK_COLUMN * CHUNK_COLUMN * CHUNK_COLUMN;
+
+
+/// A way of finding the light reaching a cell from the shadow volumes in the light cells
+///
+/// The model used by the light exposure system is chosen by which one is added as a resource
+pub trait LightModel: Resource {
+    /// returns the light reaching a cell, from 0 to 1
+    fn get_cell_light(&self, cells: &LightCells, id: impl Into<Vector3Int>) -> f32;
+
+    /// returns the light reaching a position in m, from 0 to 1
+    fn get_light_at(&self, cells: &LightCells, position: impl Into<Vector3>) -> f32 {
+        self.get_cell_light(cells, position.into() / cells.size())
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+/// ColumnLight is the light from straight above, with shadow cast down the columns of cells
+pub struct ColumnLight;
+
+impl LightModel for ColumnLight {
+    fn get_cell_light(&self, cells: &LightCells, id: impl Into<Vector3Int>) -> f32 {
+        cells.get_cell_light(id)
+    }
+}
+
+
+#[derive(Clone, Serialize, Deserialize)]
+/// a dense cube of cells, stored column by column so that walking down a column is walking through memory
+struct LightChunk {
+    volumes: Vec<f32>,
+    above: Vec<f32>,
+    exists: Vec<bool>,
+    cell_count: u32,
+}
+
+impl LightChunk {
+    fn new() -> Self {
+        LightChunk {
+            volumes: vec![0.0; CHUNK_VOLUME],
+            above: vec![0.0; CHUNK_VOLUME],
+            exists: vec![false; CHUNK_VOLUME],
+            cell_count: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.volumes.fill(0.0);
+        self.above.fill(0.0);
+        self.exists.fill(false);
+        self.cell_count = 0;
+    }
+}
+
+
+#[derive(Resource, Serialize, Deserialize)]
 /// LightCells stores shadow data in a grid of cube units for growth vigor calulcations
-/// 
+///
+/// The cells are stored in dense chunks, a chunk is added when a volume is first added inside it
+/// and removed when it has stayed empty from one clear to the next.
+/// The shadow from above is found for every cell at once by propagate_shadows, until then it is found by walking up each column
+///
 /// Components:
-///     - cells: a hash map using cell coordinates as a key and storing the cells own shadow volume and the shadow volume gained from above
+///     - chunks: a hash map using chunk coordinates as a key and storing each cell's own shadow volume and the shadow volume gained from above
 ///     - check_height: The maximum value up or down it will check for a cell existing without finding one before stopping, a value less than 5 should be fine
 ///     - cell_size: The side length of each cell in m
+///     - horizontal_bounds: the lowest and highest x and z coordinates of cells that are stored, volume added outside them is ignored
+///     - occupied: the lowest and highest coordinates of any cell, used to stop rays once they leave the cells
+///     - propagated: whether the shadow from above is up to date for every cell
 pub struct LightCells {
-    // cells hash map, set out as Hashmap<id, (own_shadow_volume, upwards_shadow_volume)>
-    cells: HashMap<Vector3Int, (f32, f32)>,
+    chunks: HashMap<Vector3Int, LightChunk>,
     check_height: i32,
     cell_size: f32,
+    horizontal_bounds: Option<((i32, i32), (i32, i32))>,
+    occupied: Option<(Vector3Int, Vector3Int)>,
+    propagated: bool,
 }
 
 
@@ -24,43 +94,133 @@ impl LightCells {
     /// creates a new light_cells_resource with a given check height
     pub fn new(check_height: i32, cell_size: f32) -> Self{
         LightCells {
-            cells: HashMap::new(),
+            chunks: HashMap::new(),
             check_height,
             cell_size: cell_size.abs(),
+            horizontal_bounds: None,
+            occupied: None,
+            propagated: true,
         }
     }
 
+    /// only stores cells inside the given x and z ranges in m, which bounds the memory used, any volume outside them casts no shadow
+    pub fn set_horizontal_bounds(&mut self, x_range: (f32, f32), z_range: (f32, f32)) {
+        let cell = |x: f32| (x / self.cell_size).floor() as i32;
+        self.horizontal_bounds = Some(((cell(x_range.0), cell(x_range.1)), (cell(z_range.0), cell(z_range.1))));
+    }
+
     pub fn size(&self) -> f32 {
         self.cell_size
     }
 
-    /// sets all the cell shadow values to 0
+    /// returns the lowest and highest x and z coordinates of cells that can be stored, if the cells are bounded
+    pub fn horizontal_bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        self.horizontal_bounds
+    }
+
+    /// returns the lowest and highest coordinates of any cell, or none if there are no cells
+    pub fn occupied_bounds(&self) -> Option<(Vector3Int, Vector3Int)> {
+        self.occupied
+    }
+
+    /// returns the coordinates and own shadow volume of every cell, in no particular order
+    pub fn cells(&self) -> impl Iterator<Item = (Vector3Int, f32)> + '_ {
+        self.chunks.iter().flat_map(|(chunk_id, chunk)| {
+            let corner = *chunk_id * CHUNK_SIZE;
+            chunk.exists.iter().enumerate()
+                .filter(|(_, exists)| **exists)
+                .map(move |(index, _)| {
+                    let (x, z, y) = (index / (CHUNK_COLUMN * CHUNK_COLUMN), (index / CHUNK_COLUMN) % CHUNK_COLUMN, index % CHUNK_COLUMN);
+                    (corner + Vector3Int::new(x as i32, y as i32, z as i32), chunk.volumes[index])
+                })
+        })
+    }
+
+    /// returns the own shadow volume of a cell, or none if it does not exist
+    pub fn get_cell_volume(&self, id: impl Into<Vector3Int>) -> Option<f32> {
+        self.get_cell(id.into()).map(|shade_data| shade_data.0)
+    }
+
+    /// returns the number of chunks of cells being stored
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// sets all the cell shadow values to 0, and removes every cell
+    ///
+    /// Chunks that had no cells since the last clear are removed, the rest are kept to be filled again
     pub fn set_all_zero(&mut self) {
-        for cell in self.cells.iter_mut() {
-            cell.1.0 = 0.0;
-            cell.1.1 = 0.0;
+        self.chunks.retain(|_, chunk| chunk.cell_count > 0);
+        for chunk in self.chunks.values_mut() {
+            chunk.clear();
         }
+        self.occupied = None;
+        self.propagated = true;
     }
 
-    /// adds a new cell to the grid, updating it's parent shadow and child shadow
-    fn add_cell(&mut self, id: Vector3Int, shadow_volume: f32) {
-        let parent_volume = match self.cells.get(&(id + Vector3Int::Y())) {
-            Some(shadow_data) => shadow_data.0 + shadow_data.1,
-            None => 0.0
-        };
-        self.cells.insert(id, (shadow_volume, parent_volume));
-        self.propogate_down(id);
+    /// returns the coordinates of the chunk a cell is in, and the index of the cell in the chunk
+    fn locate(id: Vector3Int) -> (Vector3Int, usize) {
+        let chunk = Vector3Int::new(id.x.div_euclid(CHUNK_SIZE), id.y.div_euclid(CHUNK_SIZE), id.z.div_euclid(CHUNK_SIZE));
+        let (x, y, z) = (id.x.rem_euclid(CHUNK_SIZE) as usize, id.y.rem_euclid(CHUNK_SIZE) as usize, id.z.rem_euclid(CHUNK_SIZE) as usize);
+        (chunk, (x * CHUNK_COLUMN + z) * CHUNK_COLUMN + y)
     }
 
+    /// returns the own shadow volume and the shadow volume from above of a cell, if it exists
+    fn get_cell(&self, id: Vector3Int) -> Option<(f32, f32)> {
+        let (chunk, index) = Self::locate(id);
+        let chunk = self.chunks.get(&chunk)?;
+        if chunk.exists[index] {Some((chunk.volumes[index], chunk.above[index]))} else {None}
+    }
 
     /// increases the shadow volume of a given cell, or creates one if it does not exist
     pub fn add_volume_to_cell(&mut self, id: impl Into<Vector3Int>, additional_volume: f32) {
         let id: Vector3Int = id.into();
+        if let Some(((min_x, max_x), (min_z, max_z))) = self.horizontal_bounds {
+            if id.x < min_x || id.x > max_x || id.z < min_z || id.z > max_z {return;}
+        }
 
-        match self.cells.get_mut(&id) {
-            Some(shade_data) => {shade_data.0 += additional_volume; self.propogate_down(id);},
-            None => {self.add_cell(id, additional_volume);}
+        let (chunk, index) = Self::locate(id);
+        let chunk = self.chunks.entry(chunk).or_insert_with(LightChunk::new);
+        if !chunk.exists[index] {
+            chunk.exists[index] = true;
+            chunk.cell_count += 1;
+
+            self.occupied = Some(match self.occupied {
+                Some((min, max)) => (
+                    Vector3Int::new(min.x.min(id.x), min.y.min(id.y), min.z.min(id.z)),
+                    Vector3Int::new(max.x.max(id.x), max.y.max(id.y), max.z.max(id.z)),
+                ),
+                None => (id, id),
+            });
         }
+        chunk.volumes[index] += additional_volume;
+        self.propagated = false;
+    }
+
+    /// returns the shadow volume reaching a cell that exists from the cells above it
+    fn get_volume_above(&self, id: Vector3Int) -> f32 {
+        if self.propagated {
+            return self.get_cell(id).map(|shade_data| shade_data.1).unwrap_or(0.0);
+        }
+
+        // walk up the column, a gap of check height missing cells stops the shadow
+        let max_y = self.occupied.map(|(_, max)| max.y).unwrap_or(id.y);
+        let mut volume_above = 0.0;
+        let mut gap = 0;
+        for y in (id.y + 1)..=max_y {
+            match self.get_cell(Vector3Int::new(id.x, y, id.z)) {
+                Some(shade_data) => {
+                    if gap >= self.check_height {break;}
+                    volume_above += shade_data.0;
+                    gap = 0;
+                }
+                None => {
+                    gap += 1;
+                    if gap >= self.check_height {break;}
+                }
+            }
+        }
+        volume_above
     }
 
     /// returns the shadow value of a given cell, if it does not exist but a cell above within the check range exists, it's shadow value is taken
@@ -69,57 +229,125 @@ impl LightCells {
     pub fn get_cell_light(&self, id: impl Into<Vector3Int>) -> f32 {
         let id: Vector3Int = id.into();
 
-        match self.cells.get(&id) {
-            Some(shade_data) => return (-shade_data.0 - shade_data.1).exp(),
-            None => ()
+        for count in 0..=self.check_height.max(0) {
+            let next_id = id + Vector3Int::Y() * count;
+            if let Some(shade_data) = self.get_cell(next_id) {
+                return (-shade_data.0 - self.get_volume_above(next_id)).exp();
+            }
         }
+        1.0
+    }
 
-        let mut count = 1;
-            
-        loop {
-            if count > self.check_height {break 1.0;}
+    /// finds the shadow volume from above of every cell, going down each column of chunks from the top
+    ///
+    /// A cell gets the shadow of the cells above it as long as there is no gap of check height missing cells between them
+    pub fn propagate_shadows(&mut self) {
+        if self.propagated {return;}
 
-            let next_id = id + Vector3Int::Y() * count;
+        let mut columns: HashMap<(i32, i32), Vec<i32>> = HashMap::new();
+        for chunk in self.chunks.keys() {
+            columns.entry((chunk.x, chunk.z)).or_default().push(chunk.y);
+        }
 
-            match self.cells.get(&next_id) {
-                Some(shade_data) => {break (-shade_data.0 - shade_data.1).exp();},
-                None => ()
+        let check_height = self.check_height.max(0) as u32;
+        for ((x, z), mut heights) in columns {
+            heights.sort_unstable_by(|a, b| b.cmp(a));
+
+            // the shadow volume so far and the gap since the last cell, for each column of cells
+            let mut running = vec![(0.0_f32, u32::MAX); CHUNK_COLUMN * CHUNK_COLUMN];
+            let mut last_height: Option<i32> = None;
+            for y in heights {
+                if let Some(last_height) = last_height {
+                    let skipped = ((last_height - y - 1) * CHUNK_SIZE) as u32;
+                    for column in running.iter_mut() {column.1 = column.1.saturating_add(skipped);}
+                }
+                last_height = Some(y);
+
+                let chunk = self.chunks.get_mut(&Vector3Int::new(x, y, z)).unwrap();
+                for (i, column) in running.iter_mut().enumerate() {
+                    for index in (i * CHUNK_COLUMN..(i + 1) * CHUNK_COLUMN).rev() {
+                        if !chunk.exists[index] {
+                            column.1 = column.1.saturating_add(1);
+                            continue;
+                        }
+                        chunk.above[index] = if column.1 < check_height {column.0} else {0.0};
+                        *column = (chunk.above[index] + chunk.volumes[index], 0);
+                    }
+                }
             }
-
-            count += 1;
         }
-    }
 
-    /// propogates shadow down the cells from a parent, does nothing if the start id does not exist
-    fn propogate_down(&mut self, start_id: Vector3Int) {
-        
+        self.propagated = true;
+    }
 
-        let mut current_shadow_volume = match self.cells.get(&start_id) {
-            Some(shade_data) => shade_data.0 + shade_data.1,
-            None => return,
+    /// returns the total shadow volume of the cells a ray passes through, going from the middle of a cell towards the light in the given direction
+    ///
+    /// The cell's own volume is included, and the ray stops when it leaves the cells that exist or passes through a gap of check height missing cells,
+    /// the same as the shadow down a column. A direction that doesn't point up only gets the cell's own volume
+    pub fn get_shadow_volume_towards(&self, id: impl Into<Vector3Int>, direction: impl Into<Vector3>) -> f32 {
+        let mut id: Vector3Int = id.into();
+        let direction: Vector3 = direction.into();
+        let own_volume = |id: Vector3Int| self.get_cell(id).map(|shade_data| shade_data.0).unwrap_or(0.0);
+
+        let mut total_volume = own_volume(id);
+        let (min, max) = match self.occupied {
+            Some(bounds) if direction.y > 0.0 => bounds,
+            _ => return total_volume,
         };
+        // walk through every cell the ray touches, t is the distance along the ray in cells
+        let step = |x: f32| if x > 0.0 {1} else if x < 0.0 {-1} else {0};
+        let (step_x, step_z) = (step(direction.x), step(direction.z));
+        let t_delta = Vector3::new(1.0 / direction.x.abs(), 1.0 / direction.y, 1.0 / direction.z.abs());
+        let mut t_max = t_delta * 0.5;
+        let mut gap = 0;
 
-        let mut count = 1;
-        let mut dist = 1;
         loop {
-            if count > self.check_height {break;}
-
-            let next_id = start_id - Vector3Int::Y() * dist;
-
-            match self.cells.get_mut(&next_id) {
-                Some(shade_data) => {shade_data.1 = current_shadow_volume; current_shadow_volume += shade_data.0; count = 0;},
-                None => count += 1
+            if t_max.x < t_max.y && t_max.x < t_max.z {
+                id.x += step_x;
+                t_max.x += t_delta.x;
+            } else if t_max.z < t_max.y {
+                id.z += step_z;
+                t_max.z += t_delta.z;
+            } else {
+                id.y += 1;
+                t_max.y += t_delta.y;
             }
 
-            dist += 1;
+            if id.y > max.y {break;}
+            if (id.x > max.x && step_x >= 0) || (id.x < min.x && step_x <= 0) {break;}
+            if (id.z > max.z && step_z >= 0) || (id.z < min.z && step_z <= 0) {break;}
+            match self.get_cell(id) {
+                Some(shade_data) => {
+                    if gap >= self.check_height {break;}
+                    total_volume += shade_data.0;
+                    gap = 0;
+                }
+                None => {
+                    gap += 1;
+                    if gap >= self.check_height {break;}
+                }
+            }
         }
+
+        total_volume
+    }
+
+    /// returns the weighted mean of the light reaching a cell from each direction, the weights should add up to 1
+    ///
+    /// light_value = sum(weight * exp(-shadow_volume_towards_direction))
+    pub fn get_directional_light(&self, id: impl Into<Vector3Int>, directions: &[(Vector3, f32)]) -> f32 {
+        let id: Vector3Int = id.into();
+        directions.iter()
+            .map(|(direction, weight)| weight * (-self.get_shadow_volume_towards(id, *direction)).exp())
+            .sum()
     }
 }
 
 impl Debug for LightCells {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Light Cells")
-            .field("Cells", &self.cells)
+            .field("Chunks", &self.chunks.len())
+            .field("Occupied", &self.occupied)
             .finish()
     }
 }
@@ -207,6 +435,32 @@ mod light_cells_tests{
         assert_eq!(cells.get_cell_light(id), (-6.0_f32).exp());
     }
 
+    #[test]
+    fn shadow_towards_test() {
+        let build = |check_height: i32| {
+            let mut cells = LightCells::new(check_height, 1.0);
+            cells.add_volume_to_cell([0, 0, 0], 1.0);
+            cells.add_volume_to_cell([0, 3, 0], 2.0);
+            cells.add_volume_to_cell([3, 3, 0], 4.0);
+            cells
+        };
+
+        // the diagonal ray passes through 5 missing cells before it reaches [3, 3, 0]
+        let cells = build(6);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [0.0, 1.0, 0.0]), 3.0);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [1.0, 1.0, 0.0]), 5.0);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [-1.0, 1.0, 0.0]), 1.0);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [1.0, -1.0, 0.0]), 1.0);
+
+        // gaps of check height missing cells stop the shadow, the same as down a column
+        let cells = build(3);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [0.0, 1.0, 0.0]), 3.0);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [1.0, 1.0, 0.0]), 1.0);
+        let cells = build(2);
+        assert_eq!(cells.get_shadow_volume_towards([0, 0, 0], [0.0, 1.0, 0.0]), 1.0);
+        assert_eq!(cells.get_cell_light([0, 0, 0]), (-1.0_f32).exp());
+    }
+
     #[test]
     fn size_tests() {
         let mut cells = LightCells::new(0, 0.5);
@@ -222,6 +476,34 @@ mod light_cells_tests{
         assert_eq!(cells.get_cell_light(Vector3::ZERO() / cells.size()), (-1.0_f32).exp());
         assert_eq!(cells.get_cell_light(Vector3::Y() * 2.0 / cells.size()), 1.0);
     }
+
+    #[test]
+    fn chunk_test() {
+        // cells spread over several chunks, with a gap too big to cast shadow across
+        let mut cells = LightCells::new(3, 1.0);
+        for (y, volume) in [(40, 1.0), (30, 2.0), (17, 0.5), (15, 0.25), (-2, 4.0), (-5, 1.0)] {
+            cells.add_volume_to_cell([1, y, -1], volume);
+        }
+        let ids = [[1, 40, -1], [1, 28, -1], [1, 15, -1], [1, 13, -1], [1, -5, -1]];
+        let walked: Vec<f32> = ids.iter().map(|id| cells.get_cell_light(*id)).collect();
+        cells.propagate_shadows();
+        let propagated: Vec<f32> = ids.iter().map(|id| cells.get_cell_light(*id)).collect();
+        assert_eq!(walked, propagated);
+        assert_eq!(propagated[2], (-0.75_f32).exp());
+        assert_eq!(propagated[4], (-5.0_f32).exp());
+        assert_eq!(cells.cells().count(), 6);
+        assert!(cells.cells().any(|(id, volume)| id == [1, -5, -1].into() && volume == 1.0));
+
+        // cells outside the bounds are left out, and chunks that stay empty are removed
+        cells.set_horizontal_bounds((-8.0, 8.0), (-8.0, 8.0));
+        cells.add_volume_to_cell([20, 0, 0], 1.0);
+        assert_eq!(cells.get_cell_light([20, 0, 0]), 1.0);
+        let chunks = cells.chunk_count();
+        cells.set_all_zero();
+        assert_eq!(cells.chunk_count(), chunks);
+        cells.set_all_zero();
+        assert_eq!(cells.chunk_count(), 0);
+    }
 }
 
 // this is just here while there is a vscode bug that doesn't save terminal history
@@ -1,3 +1,8 @@
 pub mod params;
 pub mod terrain;
-pub mod light_cells;
\ No newline at end of file
+pub mod light_cells;
+pub mod soil_cells;pub mod soil_grid;
+pub mod climate;
+pub mod sun_path;
+pub mod sky_dome;
+pub mod ground_light;
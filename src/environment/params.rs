@@ -1,13 +1,16 @@
 //! this file is mainly to manage data about the entire environment, like gravity, temperature, and precipitation levels
 #![allow(dead_code, unused_variables, unused_imports)]
 use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use super::super::{
     maths::vector_three::Vector3
 };
 
 
 // moisture and temperature
-#[derive(Resource)]
+#[derive(Resource, Serialize, Deserialize)]
 pub struct MoistureAndTemp {
     pub temp_at_zero: f32,
     pub temp_fall_off: f32,
@@ -15,7 +18,7 @@ pub struct MoistureAndTemp {
 }
 
 // gravity
-#[derive(Resource)]
+#[derive(Resource, Serialize, Deserialize)]
 pub struct GravityResources{
     pub gravity_dir: Vector3,
     pub tropism_strength: f32, // positive for gravitropism, negative for phototropism
@@ -37,7 +40,7 @@ pub fn create_gravity_resource(
 
 
 // PhysicalAgeStep
-#[derive(Resource)]
+#[derive(Resource, Serialize, Deserialize)]
 pub struct PhysicalAgeStep{
     pub step: f32,
 }
@@ -47,4 +50,25 @@ pub fn create_physical_age_time_step(
     step: f32
 ) {
     world.insert_resource(PhysicalAgeStep{step});
-}
\ No newline at end of file
+}
+
+
+// random number generation
+/// The random number generator used by every stochastic part of the simulation,
+/// so that a simulation with the same seed and settings will always grow the same plants
+#[derive(Resource, Serialize, Deserialize)]
+pub struct SimulationRng {
+    pub rng: ChaCha8Rng,
+}
+
+/// adds the random number generator into the world, if there is no seed it is seeded from the system's entropy
+pub fn create_simulation_rng(
+    world: &mut World,
+    seed: Option<u64>,
+) {
+    let rng = match seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+    world.insert_resource(SimulationRng{rng});
+}
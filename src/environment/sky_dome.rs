@@ -0,0 +1,80 @@
+use std::f32::consts::PI;
+use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+use super::{
+    super::maths::{vector_three::Vector3, vector_three_int::Vector3Int},
+    light_cells::{LightCells, LightModel},
+};
+
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+/// SkyDome lights the light cells with diffuse light from the whole sky, like an overcast day
+///
+/// The transmittance exp(-shadow_volume) is integrated over a hemisphere of directions through the cells.
+/// The directions are in rings around the vertical, and each is weighted by the cosine of its angle from vertical and the part of the sky it covers
+///
+/// Components:
+///     - directions: the normalised directions towards the sky and their weights, which add up to 1
+pub struct SkyDome {
+    directions: Vec<(Vector3, f32)>,
+}
+
+
+impl SkyDome {
+
+    /// creates a sky dome with the given number of rings of directions between the horizon and straight up, and directions in each ring
+    ///
+    /// Every other ring is turned by half the angle between its directions, so the rings don't line up
+    pub fn new(rings: u32, directions_per_ring: u32) -> Self {
+        let (rings, directions_per_ring) = (rings.max(1), directions_per_ring.max(1));
+
+        let mut directions = Vec::new();
+        for ring in 0..rings {
+            let zenith = (ring as f32 + 0.5) / rings as f32 * PI / 2.0;
+            let weight = zenith.cos() * zenith.sin();
+            for i in 0..directions_per_ring {
+                let azimuth = (i as f32 + 0.5 * (ring % 2) as f32) / directions_per_ring as f32 * 2.0 * PI;
+                let direction = Vector3::new(zenith.sin() * azimuth.cos(), zenith.cos(), zenith.sin() * azimuth.sin());
+                directions.push((direction, weight));
+            }
+        }
+
+        let total_weight: f32 = directions.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in directions.iter_mut() {
+            *weight /= total_weight;
+        }
+        SkyDome{directions}
+    }
+}
+
+impl LightModel for SkyDome {
+    fn get_cell_light(&self, cells: &LightCells, id: impl Into<Vector3Int>) -> f32 {
+        cells.get_directional_light(id, &self.directions)
+    }
+}
+
+
+
+#[cfg(test)]
+mod sky_dome_tests {
+    use super::*;
+
+    #[test]
+    fn sky_dome_test() {
+        let sky = SkyDome::new(3, 8);
+        assert_eq!(sky.directions.len(), 24);
+        assert!(sky.directions.iter().all(|(direction, _)| direction.y > 0.0));
+        assert!((sky.directions.iter().map(|(_, weight)| weight).sum::<f32>() - 1.0).abs() < 0.0001);
+
+        // a cell under a wide canopy gets less light than one at its edge, which gets less than one in the open
+        let mut cells = LightCells::new(4, 1.0);
+        for x in -3..=3 {
+            for z in -3..=3 {
+                cells.add_volume_to_cell([x, 3, z], 1.0);
+            }
+        }
+        let (under, edge, open) = (sky.get_cell_light(&cells, [0, 0, 0]), sky.get_cell_light(&cells, [4, 0, 0]), sky.get_cell_light(&cells, [20, 0, 0]));
+        assert!(under < edge && edge < open);
+        assert!((open - 1.0).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, fmt::Debug};
+use super::{
+    super::maths::{vector_three::Vector3, vector_three_int::Vector3Int},
+    soil_grid::SoilGrid,
+};
+use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+
+
+#[derive(Resource, Serialize, Deserialize)]
+/// SoilCells stores the root volume below the terrain in a grid of cube units, which roots compete through for the soil's resources
+///
+/// Roots compete for resources the way branches compete for light in the light cells,
+/// the resource available in a cell is the fertility of the soil grid above it, falling off exponentially with the root volume in it
+///
+/// Components:
+///     - cells: a hash map using cell coordinates as a key and storing the root volume in the cell
+///     - cell_size: The side length of each cell in m
+pub struct SoilCells {
+    cells: HashMap<Vector3Int, f32>,
+    cell_size: f32,
+}
+
+
+impl SoilCells {
+
+    /// creates a new soil cells resource without any roots
+    pub fn new(cell_size: f32) -> Self {
+        SoilCells {
+            cells: HashMap::new(),
+            cell_size: cell_size.abs(),
+        }
+    }
+
+    pub fn size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// sets all the cell root volumes to 0
+    pub fn set_all_zero(&mut self) {
+        for cell in self.cells.values_mut() {
+            *cell = 0.0;
+        }
+    }
+
+    /// increases the root volume of a given cell, or creates one if it does not exist
+    pub fn add_volume_to_cell(&mut self, id: impl Into<Vector3Int>, additional_volume: f32) {
+        *self.cells.entry(id.into()).or_insert(0.0) += additional_volume;
+    }
+
+    /// returns the root volume in a given cell
+    pub fn get_root_volume(&self, id: impl Into<Vector3Int>) -> f32 {
+        *self.cells.get(&id.into()).unwrap_or(&0.0)
+    }
+
+    /// returns the resource left in a given cell for a root with own_volume of its volume in the cell, which it doesn't compete with
+    ///
+    /// available_resource = fertility * exp(-(cell_root_volume - own_volume))
+    pub fn get_available_resource(&self, id: impl Into<Vector3Int>, soil_grid: &SoilGrid, own_volume: f32) -> f32 {
+        let id: Vector3Int = id.into();
+        let centre = (Vector3::from([id.x, id.y, id.z]) + Vector3::new(0.5, 0.5, 0.5)) * self.cell_size;
+        let competing_volume = (self.get_root_volume(id) - own_volume).max(0.0);
+        soil_grid.get_smooth_fertility(centre) * (-competing_volume).exp()
+    }
+
+    /// returns the direction of increasing available resource around a given cell, found from the cells either side on each axis
+    pub fn get_resource_gradient(&self, id: impl Into<Vector3Int>, soil_grid: &SoilGrid) -> Vector3 {
+        let id: Vector3Int = id.into();
+        let difference = |offset: [i32; 3]| {
+            let offset = Vector3Int::from(offset);
+            (self.get_available_resource(id + offset, soil_grid, 0.0) - self.get_available_resource(id - offset, soil_grid, 0.0)) / 2.0
+        };
+        Vector3::new(difference([1, 0, 0]), difference([0, 1, 0]), difference([0, 0, 1]))
+    }
+}
+
+impl Debug for SoilCells {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Soil Cells")
+            .field("Cells", &self.cells)
+            .finish()
+    }
+}
+
+
+
+#[cfg(test)]
+mod soil_cells_tests {
+    use super::*;
+
+    #[test]
+    fn competition_test() {
+        let grid = SoilGrid::new((-5.0, 5.0), (-5.0, 5.0), 1.0, 100.0, 0.5, 1.0);
+        let mut cells = SoilCells::new(1.0);
+        assert_eq!(cells.get_available_resource([0, -1, 0], &grid, 0.0), 1.0);
+
+        cells.add_volume_to_cell([0, -1, 0], 0.5);
+        cells.add_volume_to_cell([0, -1, 0], 1.5);
+        assert_eq!(cells.get_available_resource([0, -1, 0], &grid, 0.0), (-2.0_f32).exp());
+        // a root doesn't compete with its own volume
+        assert_eq!(cells.get_available_resource([0, -1, 0], &grid, 0.5), (-1.5_f32).exp());
+
+        cells.set_all_zero();
+        assert_eq!(cells.get_available_resource([0, -1, 0], &grid, 0.0), 1.0);
+    }
+
+    #[test]
+    fn gradient_test() {
+        // the soil is worn out on the -x side, and there are roots below
+        let mut grid = SoilGrid::new((-5.0, 5.0), (-5.0, 5.0), 1.0, 100.0, 0.5, 1.0);
+        grid.deplete([-0.5, 0.0, 0.5], 0.1, 50.0, 1.0);
+        let mut cells = SoilCells::new(1.0);
+        cells.add_volume_to_cell([0, -2, 0], 10.0);
+
+        let gradient = cells.get_resource_gradient([0, -1, 0], &grid);
+        assert!(gradient.x > 0.0);
+        assert!(gradient.y > 0.0);
+        assert_eq!(gradient.z, 0.0);
+    }
+}
@@ -0,0 +1,191 @@
+use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+use super::super::maths::{vector_three::Vector3, lerp};
+
+
+/// how much slower nutrients recover than moisture
+const NUTRIENT_RECOVERY_FRACTION: f32 = 0.2;
+
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+/// SoilGrid stores the moisture and nutrients of the soil in a grid of square cells over the terrain
+///
+/// Moisture is in cm, like the precipitation it recharges towards, and nutrients go from 0 to 1.
+/// Positions outside the grid use the nearest cell on its edge
+///
+/// Components:
+///     - origin: the x and z of the corner of the grid with the lowest coordinates
+///     - cell_size: the side length of each cell in m
+///     - width and depth: the number of cells along x and z
+///     - recharge_rate: the fraction of the difference between the moisture and the precipitation that is recharged each year
+///     - uptake_rate: the cm of moisture taken from the soil each year for every m^3 of plant
+pub struct SoilGrid {
+    origin: (f32, f32),
+    cell_size: f32,
+    width: usize,
+    depth: usize,
+    moisture: Vec<f32>,
+    nutrients: Vec<f32>,
+    precipitation: f32,
+    pub recharge_rate: f32,
+    pub uptake_rate: f32,
+}
+
+
+impl SoilGrid {
+
+    /// creates a grid covering the given x and z ranges, with every cell at the precipitation level and full of nutrients
+    ///
+    /// The cell size must be positive, the app builders make sure of this
+    pub fn new(x_range: (f32, f32), z_range: (f32, f32), cell_size: f32, precipitation: f32, recharge_rate: f32, uptake_rate: f32) -> Self {
+        let width = (((x_range.1 - x_range.0) / cell_size).ceil() as usize).max(1);
+        let depth = (((z_range.1 - z_range.0) / cell_size).ceil() as usize).max(1);
+        let precipitation = precipitation.max(0.0);
+
+        SoilGrid {
+            origin: (x_range.0, z_range.0),
+            cell_size,
+            width,
+            depth,
+            moisture: vec![precipitation; width * depth],
+            nutrients: vec![1.0; width * depth],
+            precipitation,
+            recharge_rate: recharge_rate.max(0.0),
+            uptake_rate: uptake_rate.max(0.0),
+        }
+    }
+
+    /// returns the number of cells along x and z
+    #[allow(dead_code)]
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.depth)
+    }
+
+    /// returns the coordinates of the cell the position is over, clamped to the grid
+    fn cell_coords(&self, position: Vector3) -> (usize, usize) {
+        let x = ((position.x - self.origin.0) / self.cell_size).floor().clamp(0.0, (self.width - 1) as f32);
+        let z = ((position.z - self.origin.1) / self.cell_size).floor().clamp(0.0, (self.depth - 1) as f32);
+        (x as usize, z as usize)
+    }
+
+    /// returns the moisture of the cell the position is over, in cm
+    pub fn get_moisture(&self, position: impl Into<Vector3>) -> f32 {
+        let (x, z) = self.cell_coords(position.into());
+        self.moisture[z * self.width + x]
+    }
+
+    /// returns the nutrients of the cell the position is over, from 0 to 1
+    #[allow(dead_code)]
+    pub fn get_nutrients(&self, position: impl Into<Vector3>) -> f32 {
+        let (x, z) = self.cell_coords(position.into());
+        self.nutrients[z * self.width + x]
+    }
+
+    /// returns how much of the soil's resources are left at the position, from 0 to 1,
+    /// blended between the centres of the nearest cells so that it changes smoothly across the grid
+    ///
+    /// fertility = min(moisture / precipitation, 1) * nutrients
+    pub fn get_smooth_fertility(&self, position: impl Into<Vector3>) -> f32 {
+        let position: Vector3 = position.into();
+        let x = ((position.x - self.origin.0) / self.cell_size - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let z = ((position.z - self.origin.1) / self.cell_size - 0.5).clamp(0.0, (self.depth - 1) as f32);
+        let (x0, z0) = (x.floor() as usize, z.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.width - 1), (z0 + 1).min(self.depth - 1));
+        let (blend_x, blend_z) = (x - x0 as f32, z - z0 as f32);
+
+        let fertility = |x: usize, z: usize| self.cell_fertility(z * self.width + x);
+        let near = lerp(fertility(x0, z0), fertility(x1, z0), blend_x);
+        let far = lerp(fertility(x0, z1), fertility(x1, z1), blend_x);
+        lerp(near, far, blend_z)
+    }
+
+    fn cell_fertility(&self, index: usize) -> f32 {
+        let moisture = if self.precipitation > 0.0 {(self.moisture[index] / self.precipitation).min(1.0)} else {0.0};
+        moisture * self.nutrients[index]
+    }
+
+    /// moves every cell's moisture towards the precipitation and its nutrients towards 1, nutrients recover more slowly
+    pub fn recharge(&mut self, precipitation: f32, years: f32) {
+        self.precipitation = precipitation.max(0.0);
+        let moisture_recovery = 1.0 - (-self.recharge_rate * years).exp();
+        let nutrient_recovery = 1.0 - (-self.recharge_rate * NUTRIENT_RECOVERY_FRACTION * years).exp();
+
+        for moisture in self.moisture.iter_mut() {
+            *moisture += (self.precipitation - *moisture) * moisture_recovery;
+        }
+        for nutrients in self.nutrients.iter_mut() {
+            *nutrients += (1.0 - *nutrients) * nutrient_recovery;
+        }
+    }
+
+    /// takes the moisture and nutrients used by a plant from the cells within the radius of its position, or just its own cell if there are none
+    ///
+    /// The moisture used is the uptake rate times the biomass and the years, and the nutrients used are in the same proportion to the precipitation
+    pub fn deplete(&mut self, position: impl Into<Vector3>, radius: f32, biomass: f32, years: f32) {
+        let position: Vector3 = position.into();
+        let mut cells: Vec<usize> = Vec::new();
+        // only the cells in the box around the radius can be inside it
+        let (min_x, min_z) = self.cell_coords(Vector3::new(position.x - radius, 0.0, position.z - radius));
+        let (max_x, max_z) = self.cell_coords(Vector3::new(position.x + radius, 0.0, position.z + radius));
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                let centre = (self.origin.0 + (x as f32 + 0.5) * self.cell_size, self.origin.1 + (z as f32 + 0.5) * self.cell_size);
+                if (centre.0 - position.x).powi(2) + (centre.1 - position.z).powi(2) <= radius * radius {
+                    cells.push(z * self.width + x);
+                }
+            }
+        }
+        if cells.is_empty() {
+            let (x, z) = self.cell_coords(position);
+            cells.push(z * self.width + x);
+        }
+
+        let moisture_used = self.uptake_rate * biomass * years / cells.len() as f32;
+        let nutrients_used = if self.precipitation > 0.0 {moisture_used / self.precipitation} else {0.0};
+        for i in cells {
+            self.moisture[i] = (self.moisture[i] - moisture_used).max(0.0);
+            self.nutrients[i] = (self.nutrients[i] - nutrients_used).max(0.0);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod soil_grid_tests {
+    use super::*;
+
+    #[test]
+    fn depletion_and_recharge_test() {
+        let mut grid = SoilGrid::new((-5.0, 5.0), (-5.0, 5.0), 1.0, 100.0, 0.5, 1.0);
+        assert_eq!(grid.dimensions(), (10, 10));
+
+        // a plant over a single cell
+        grid.deplete([0.5, 0.0, 0.5], 0.1, 20.0, 1.0);
+        assert_eq!(grid.get_moisture([0.5, 0.0, 0.5]), 80.0);
+        assert_eq!(grid.get_nutrients([0.5, 0.0, 0.5]), 0.8);
+        assert_eq!(grid.get_smooth_fertility([0.5, 0.0, 0.5]), 0.8 * 0.8);
+        assert_eq!(grid.get_moisture([3.5, 0.0, 0.5]), 100.0);
+
+        // positions outside the grid use the edge cells
+        assert_eq!(grid.get_moisture([50.0, 0.0, 50.0]), 100.0);
+
+        grid.recharge(100.0, 1.0);
+        let moisture = grid.get_moisture([0.5, 0.0, 0.5]);
+        let nutrients = grid.get_nutrients([0.5, 0.0, 0.5]);
+        assert!(moisture > 80.0 && moisture < 100.0);
+        assert!(nutrients > 0.8 && nutrients < 1.0);
+        assert!(100.0 - moisture < (1.0 - nutrients) * 100.0);
+    }
+
+    #[test]
+    fn spread_depletion_test() {
+        let mut grid = SoilGrid::new((0.0, 4.0), (0.0, 4.0), 1.0, 100.0, 0.5, 1.0);
+
+        // the plant covers the four middle cells
+        grid.deplete([2.0, 0.0, 2.0], 1.0, 40.0, 1.0);
+        assert_eq!(grid.get_moisture([1.5, 0.0, 1.5]), 90.0);
+        assert_eq!(grid.get_moisture([2.5, 0.0, 2.5]), 90.0);
+        assert_eq!(grid.get_moisture([0.5, 0.0, 0.5]), 100.0);
+    }
+}
@@ -0,0 +1,119 @@
+use std::f32::consts::PI;
+use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
+use super::{
+    super::maths::{vector_three::Vector3, vector_three_int::Vector3Int},
+    light_cells::{LightCells, LightModel},
+};
+
+
+/// the axial tilt of the earth in radians
+const AXIAL_TILT: f32 = 0.4091;
+/// the hours of the day the sun is sampled at, every two hours so no sample falls at sunrise or sunset on the equinox
+const SAMPLE_HOURS: [f32; 12] = [1.0, 3.0, 5.0, 7.0, 9.0, 11.0, 13.0, 15.0, 17.0, 19.0, 21.0, 23.0];
+/// the days of the year the sun is sampled on when the whole year is used, the equinoxes and solstices
+const SAMPLE_DAYS: [f32; 4] = [80.0, 172.0, 266.0, 355.0];
+
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+/// SunPath lights the light cells from a set of weighted directions towards the sun, instead of straight down
+///
+/// The light in a cell is the weighted mean of the light reaching it from each direction,
+/// where the light from a direction is exp(-shadow_volume) of the cells along it.
+/// Directions use +x as east, +y as up and +z as south
+///
+/// Components:
+///     - directions: the normalised directions towards the light and their weights, which add up to 1
+pub struct SunPath {
+    directions: Vec<(Vector3, f32)>,
+}
+
+
+impl SunPath {
+
+    /// creates a sun path from directions towards the light and their weights, any that don't point up or have no weight are left out
+    ///
+    /// Without any directions left the light comes from straight up
+    pub fn from_directions(directions: Vec<(Vector3, f32)>) -> Self {
+        let mut directions: Vec<(Vector3, f32)> = directions.into_iter()
+            .filter(|(direction, weight)| direction.y > 0.0 && *weight > 0.0)
+            .map(|(direction, weight)| (direction.normalised(), weight))
+            .collect();
+        if directions.is_empty() {directions.push((Vector3::Y(), 1.0));}
+
+        let total_weight: f32 = directions.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in directions.iter_mut() {
+            *weight /= total_weight;
+        }
+        SunPath{directions}
+    }
+
+    /// creates a sun path from the position of the sun every two hours at a latitude in degrees, north is positive
+    ///
+    /// Each direction is weighted by the height of the sun, which gives the light falling on flat ground.
+    /// Without a day of the year, the equinoxes and solstices are used for the light over a whole year
+    pub fn from_location(latitude: f32, day_of_year: Option<f32>) -> Self {
+        let latitude = latitude.clamp(-90.0, 90.0).to_radians();
+        let days = match day_of_year {
+            Some(day) => vec![day],
+            None => SAMPLE_DAYS.to_vec(),
+        };
+
+        let mut directions = Vec::new();
+        for day in days {
+            let declination = -AXIAL_TILT * (2.0 * PI * (day + 10.0) / 365.0).cos();
+            for hour in SAMPLE_HOURS {
+                let hour_angle = (hour - 12.0) / 24.0 * 2.0 * PI;
+                let up = latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+                let east = -declination.cos() * hour_angle.sin();
+                let north = latitude.cos() * declination.sin() - latitude.sin() * declination.cos() * hour_angle.cos();
+                directions.push((Vector3::new(east, up, -north), up));
+            }
+        }
+        SunPath::from_directions(directions)
+    }
+}
+
+impl LightModel for SunPath {
+    fn get_cell_light(&self, cells: &LightCells, id: impl Into<Vector3Int>) -> f32 {
+        cells.get_directional_light(id, &self.directions)
+    }
+}
+
+
+
+#[cfg(test)]
+mod sun_path_tests {
+    use super::*;
+
+    #[test]
+    fn location_test() {
+        // at midsummer in the north the sun is always in the south at midday, and higher than at midwinter
+        let summer = SunPath::from_location(52.0, Some(172.0));
+        let winter = SunPath::from_location(52.0, Some(355.0));
+        let highest = |sun: &SunPath| sun.directions.iter().map(|(direction, _)| direction.y).fold(0.0, f32::max);
+        assert!(highest(&summer) > highest(&winter));
+        assert!(summer.directions.iter().all(|(direction, _)| direction.y > 0.0));
+        assert!((summer.directions.iter().map(|(_, weight)| weight).sum::<f32>() - 1.0).abs() < 0.0001);
+
+        let noon = summer.directions.iter().max_by(|a, b| a.0.y.total_cmp(&b.0.y)).unwrap();
+        assert!(noon.0.z > 0.0);
+    }
+
+    #[test]
+    fn directional_shade_test() {
+        let mut cells = LightCells::new(4, 1.0);
+        cells.add_volume_to_cell([0, 0, 0], 0.0);
+        cells.add_volume_to_cell([2, 2, 0], 2.0);
+
+        // the column model doesn't see the shade to the side, but light from that side does
+        assert_eq!(cells.get_cell_light([0, 0, 0]), 1.0);
+        let from_east = SunPath::from_directions(vec![(Vector3::new(1.0, 1.0, 0.0), 1.0)]);
+        let from_west = SunPath::from_directions(vec![(Vector3::new(-1.0, 1.0, 0.0), 1.0)]);
+        assert_eq!(from_east.get_cell_light(&cells, [0, 0, 0]), (-2.0_f32).exp());
+        assert_eq!(from_west.get_cell_light(&cells, [0, 0, 0]), 1.0);
+
+        let both = SunPath::from_directions(vec![(Vector3::new(1.0, 1.0, 0.0), 1.0), (Vector3::new(-1.0, 1.0, 0.0), 3.0)]);
+        assert!((both.get_cell_light(&cells, [0, 0, 0]) - (0.25 * (-2.0_f32).exp() + 0.75)).abs() < 0.0001);
+    }
+}
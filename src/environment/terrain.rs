@@ -1,6 +1,7 @@
 use bevy_ecs::prelude::*;
 use image::{DynamicImage, GenericImageView};
 use std::ops::RangeInclusive;
+use serde::{Serialize, Deserialize};
 use super::super::{
     maths::{
         colliders::{Collider, plane_collider::PlaneCollider, mesh_collider::MeshCollider},
@@ -23,11 +24,19 @@ pub struct TerrainCollider {
     pub max_height: f32,
 }
 
+/// The vertices and indices the terrain was built from, kept so the terrain can be exported without graphics
+#[derive(Component, Clone, Serialize, Deserialize)]
+pub struct TerrainMeshData {
+    pub vertices: Vec<Vector3>,
+    pub indices: Vec<u32>,
+}
+
 #[cfg(feature="vulkan_graphics")]
 #[derive(Bundle)]
 pub struct TerrainBundle {
     tag: TerrainTag,
     collider: TerrainCollider,
+    mesh_data: TerrainMeshData,
     mesh: Mesh
 }
 
@@ -36,6 +45,7 @@ pub struct TerrainBundle {
 pub struct TerrainBundle {
     tag: TerrainTag,
     collider: TerrainCollider,
+    mesh_data: TerrainMeshData,
 }
 
 
@@ -82,19 +92,7 @@ pub fn spawn_heightmap_terrain(
         }
     }
 
-    let collider = MeshCollider::new(vertices.clone(), indices.clone());
-
-    
-    #[cfg(feature="vulkan_graphics")]
-    let mesh: Mesh = Mesh::from((vertices.clone(), indices.clone())).recalculate_normals().clone();
-    world.spawn(
-        TerrainBundle{
-            tag: TerrainTag,
-            collider: TerrainCollider{max_height: max_height, collider: collider.clone()},
-            #[cfg(feature="vulkan_graphics")]
-            mesh
-        }
-    );
+    let collider = spawn_terrain_from_mesh_data(TerrainMeshData{vertices, indices}, max_height, world);
 
     ((max_height, (centre.x - half_size)..=(centre.x + half_size - tri_size), (centre.z - half_size)..=(centre.z + half_size - tri_size)), collider)
 }
@@ -119,18 +117,31 @@ pub fn spawn_flat_terrain(
         0, 3, 2, 3, 0, 1
     ];
 
-    #[cfg(feature="vulkan_graphics")]
-    let mesh: Mesh = Mesh::from((vertices.clone(), indices.clone())).recalculate_normals().clone();
-    let collider = MeshCollider::new(vertices.clone(), indices.clone());
+    let collider = spawn_terrain_from_mesh_data(TerrainMeshData{vertices, indices}, centre.y, world);
+
+    ((centre.y, (centre.x - half_size)..=(centre.x + half_size), (centre.z - half_size)..=(centre.z + half_size)), collider)
+}
+
 
+/// spawns terrain made from the given vertices and indices, returns the collider of the terrain
+pub fn spawn_terrain_from_mesh_data(
+    mesh_data: TerrainMeshData,
+    max_height: f32,
+    world: &mut World,
+) -> MeshCollider {
+    let collider = MeshCollider::new(mesh_data.vertices.clone(), mesh_data.indices.clone());
+
+    #[cfg(feature="vulkan_graphics")]
+    let mesh: Mesh = Mesh::from((mesh_data.vertices.clone(), mesh_data.indices.clone())).recalculate_normals().clone();
     world.spawn(
         TerrainBundle{
             tag: TerrainTag,
-            collider: TerrainCollider {max_height: centre.y, collider: collider.clone()},
+            collider: TerrainCollider {max_height, collider: collider.clone()},
+            mesh_data,
             #[cfg(feature="vulkan_graphics")]
             mesh
         }
     );
 
-    ((centre.y, (centre.x - half_size)..=(centre.x + half_size), (centre.z - half_size)..=(centre.z + half_size)), collider)
-}
\ No newline at end of file
+    collider
+}
@@ -21,7 +21,7 @@ use super::{
     general_graphics::{Normal, PositionVertex, get_generic_uniforms, basic_frag_shader},
     gui::GUIData,
     super::{
-        branches::branch::*,
+        branches::{branch::*, branch_mesh::create_vector_polygon},
         maths::{vector_three::Vector3, matrix_three::Matrix3},
     }
 };
@@ -121,31 +121,6 @@ pub fn init_branch_mesh_buffers_res(
 }
 
 
-/// Creates a polygon of vectors,
-/// each of the vectors is a direction 
-/// so can be used to generate a polygon from a central point. 
-/// By default, the first direction will go along the x axis. 
-/// A rotation will rotate by that many radians anticlockwise about the y axis
-fn create_vector_polygon(sides: u32, rotation: Option<f32>) -> Vec<Vector3> {
-    
-    let inner_rotation_matrix = Matrix3::from_angle_y(2.0 * PI / sides as f32);
-
-    let initial_direction = if rotation.is_none() {
-        Vector3::X()
-    } else {
-        Vector3::X().transform(Matrix3::from_angle_y(-rotation.unwrap()))
-    };
-
-    let mut vectors = vec![initial_direction];
-    for i in 0..(sides-1) as usize {
-        vectors.push(vectors[i].clone().transform(inner_rotation_matrix));
-    }
-
-    vectors
-}
-
-
-
 //////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////// Gui ///////////////////////////////////////////
 //////////////////////////////////////////////////////////////////////////////////
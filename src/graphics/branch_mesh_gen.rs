@@ -8,7 +8,7 @@ use super::{
     super::{
         maths::{vector_three::{self, Vector3}, matrix_three::Matrix3},
         plants::plant::*,
-        branches::{branch::*, branch_node::*},
+        branches::{branch::*, branch_node::*, branch_mesh::create_branch_mesh_components},
     }
 };
 
@@ -143,67 +143,12 @@ fn update_branch_mesh(
 fn create_branch_mesh(
     branch_normal: Vector3,
     root_pos: Vector3,
-    mut node_pos: Vec<Vector3>,
+    node_pos: Vec<Vector3>,
     node_thicknesses: Vec<f32>,
     node_pairs:  Vec<(usize, usize)>,
     polygon_directions: &Vec<Vector3>,
 ) -> Mesh {
+    let components = create_branch_mesh_components(branch_normal, root_pos, node_pos, node_thicknesses, node_pairs, polygon_directions);
 
-    let branch_rotation_matrix = {
-        let rotation_axis = branch_normal.cross(Vector3::Y());
-        let rotation_angle = branch_normal.angle_to(Vector3::Y());
-        Matrix3::from_angle_and_axis(-rotation_angle, rotation_axis)
-    };
-    
-    for node in node_pos.iter_mut() {
-        node.mut_transform(branch_rotation_matrix);
-    }   
-
-    let mut vertices: Vec<PositionVertex> = Vec::new();
-    let mut indices: Vec<u32> = Vec::new();
-
-    let num_indices = polygon_directions.len() as u32 * 2;
-
-    for pair in node_pairs.iter() {
-        let (node_1, node_2) = (node_pos[pair.0], node_pos[pair.1]);
-        let (thick_1, thick_2) = (node_thicknesses[pair.0], node_thicknesses[pair.1]);
-
-        let mut branch_line = node_2 - node_1;
-        branch_line.normalise();
-
-        let allignment_mat = {
-            let mut rotation_axis = branch_line.cross(Vector3::Y());
-            rotation_axis.normalise();
-            let rotation_angle = Vector3::Y().angle_to(branch_line);
-            Matrix3::from_angle_and_axis(-rotation_angle, rotation_axis)
-        };
-
-        // add vertices and indices
-        let vert_index = vertices.len() as u32;
-        let mut incr: u32 = 0;
-        for direction in polygon_directions.iter() {
-            let new_dir = direction.transform(allignment_mat);
-            vertices.push(PositionVertex{position: (node_1 + (new_dir * thick_1)).into()});
-            vertices.push(PositionVertex{position: (node_2 + (new_dir * thick_2)).into()});
-
-            // magic index stuff, this is just how it works, idk how else to explain it
-            // it needed to loop round so that's where the mod comes in
-            indices.push(vert_index + incr);
-            indices.push(vert_index + 1 + (incr + 2) % num_indices);
-            indices.push(vert_index + (incr + 2) % num_indices);
-            indices.push(vert_index + 1 + (incr + 2) % num_indices);
-            indices.push(vert_index + incr);
-            indices.push(vert_index + incr + 1);
-            incr += 2;
-        }
-
-        
-    }
-
-    // move mesh to root
-    for vertex in vertices.iter_mut() {
-        *vertex += root_pos;
-    }
-
-    Mesh::new(vertices, indices).recalculate_normals().clone()
+    Mesh::from(components).recalculate_normals().clone()
 }
\ No newline at end of file
@@ -4,14 +4,19 @@
 mod maths;
 mod plants;
 mod branches;
+mod roots;
 
+#[allow(dead_code)]
 mod fixed_schedule;
 mod environment;
+#[allow(dead_code)]
 mod debug;
 
 
 pub mod apps;
-pub use plants::plant::{GrowthControlSettingParams, PlasticitySettingParams};
+pub use plants::plant::{GrowthControlSettingParams, PlasticitySettingParams, SpeciesPreset};
+pub use plants::plant_statistics::SpeciesStatistics;
+pub use branches::{branch_prototypes::{BranchPrototypeData, PrototypeError, PrototypePreset, PrototypeConditions}, prototype_generator::PrototypeGenerator, lsystem::{LSystem, LSystemError}};
 
 #[cfg(feature = "vulkan_graphics")]
 mod graphics;
\ No newline at end of file
@@ -1,8 +1,9 @@
 #![allow(dead_code, unused_variables, unused_imports)]
 use super::{vector_three::Vector3, bounding_sphere::BoundingSphere, colliders::{Collider, RayHitInfo}};
+use serde::{Serialize, Deserialize};
 
 
-#[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct BoundingBox {
     pub min_corner: Vector3, // least x y and z
     pub max_corner: Vector3,
@@ -66,7 +67,7 @@ impl BoundingBox {
     }
 
     pub fn from_spheres(spheres: Vec<BoundingSphere>) -> BoundingBox {
-        if spheres.len() == 0 {
+        if spheres.is_empty() {
             return BoundingBox::ZERO();
         }
 
@@ -107,7 +108,7 @@ impl BoundingBox {
     }
 
     pub fn from_points(points: Vec<Vector3>) -> BoundingBox{
-        if points.len() == 0 {return BoundingBox::ZERO()}
+        if points.is_empty() {return BoundingBox::ZERO()}
 
         let mut x_min = points[0].x;
         let mut x_max = points[0].x;
@@ -134,6 +135,11 @@ impl BoundingBox {
         BoundingBox::new(min_corner, max_corner)
     }
 
+    pub fn volume(&self) -> f32 {
+        let size = self.max_corner - self.min_corner;
+        (size.x * size.y * size.z).abs()
+    }
+
     pub fn is_intersecting_box(&self, other: BoundingBox) -> bool {
         self.min_corner.x <= other.max_corner.x &&
         self.max_corner.x >= other.min_corner.x &&
@@ -206,6 +212,7 @@ impl Collider for BoundingBox {
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod bounding_box_tests {
     use super::{Vector3, BoundingBox, BoundingSphere};
 
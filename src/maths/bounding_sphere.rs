@@ -2,10 +2,11 @@
 use super::{vector_three::Vector3, colliders::{Collider, RayHitInfo}};
 use std::f32::consts::PI;
 use std::cmp::{max, min};
+use serde::{Serialize, Deserialize};
 
 const FOUR_THIRDS_PI: f32 = (4.0 / 3.0) * PI;
 
-#[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct BoundingSphere {
     pub centre: Vector3,
     pub radius: f32,
@@ -48,9 +49,7 @@ impl BoundingSphere {
     pub fn furthest_point_from_point(&self, point: Vector3) -> Vector3 {
         let mut dir_vector = self.centre - point;
         dir_vector.normalise();
-        let furthest_point = dir_vector * self.radius + self.centre;
-
-        furthest_point
+        dir_vector * self.radius + self.centre
     }
 
     pub fn contains_points(&self, points: Vec<Vector3>) -> bool {
@@ -66,7 +65,7 @@ impl BoundingSphere {
 
     #[allow(unused_assignments)]
     pub fn from_points(points: Vec<Vector3>) -> Self {
-        if points.len() == 0 {
+        if points.is_empty() {
             return BoundingSphere::new(Vector3::ZERO(), 0.0)
         }
     
@@ -84,14 +83,14 @@ impl BoundingSphere {
             let y = point.y;
             let z = point.z;
     
-            if x < x_min.x {x_min = point.clone()}
-            if x > x_max.x {x_max = point.clone()}
+            if x < x_min.x {x_min = *point}
+            if x > x_max.x {x_max = *point}
     
-            if y < y_min.y {y_min = point.clone()}
-            if y > y_max.y {y_max = point.clone()}
+            if y < y_min.y {y_min = *point}
+            if y > y_max.y {y_max = *point}
     
-            if z < z_min.z {z_min = point.clone()}
-            if z > z_max.z {z_max = point.clone()}
+            if z < z_min.z {z_min = *point}
+            if z > z_max.z {z_max = *point}
         }
     
         // compute x y and z spans
@@ -199,7 +198,7 @@ impl BoundingSphere {
         if volume < 0.0 {
             panic!("bounding sphere intersection volume <0: ]\n- Sphere one: {:?} \n- Sphere two: {:?}", self, other);
         }
-        return volume;
+        volume
     }
 
     pub fn get_volume(&self) -> f32 {
@@ -211,17 +210,17 @@ impl BoundingSphere {
 /// solves a quadratic and returns the smallest positive solution
 fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<f32>{
     let det = b * b - 4.0 * a * c;
-    if det < 0.0 {return None;}
+    if det < 0.0 {None}
     else if det == 0.0 {
         let s0 = -b / (2.0 * a);
-        if s0 < 0.0 {return None;}
-        else {return Some(s0);}
+        if s0 < 0.0 {None}
+        else {Some(s0)}
     }
     else {
         let (s1, s2) = ((-b + det.sqrt()) / (2.0 * a),(-b - det.sqrt()) / (2.0 * a));
-        if s1 > s2 && s2 >= 0.0 {return Some(s2);}
-        else if s1 >= 0.0 {return Some(s1);}
-        else {return None;}
+        if s1 > s2 && s2 >= 0.0 {Some(s2)}
+        else if s1 >= 0.0 {Some(s1)}
+        else {None}
     }
 }
 
@@ -244,13 +243,11 @@ impl Collider for BoundingSphere {
         let a = direction.dot(direction);
         let b = 2.0 * direction.dot(l);
         let c = l.dot(l) - self.radius * self.radius;
-        let dist = solve_quadratic(a, b, c);
+        let dist = solve_quadratic(a, b, c)?;
 
-        if dist.is_none() {return None;}
-        let dist = dist.unwrap();
         if max_distance.is_some() && dist > max_distance.unwrap() {return None;}
 
-        return Some(RayHitInfo::new(root_position + direction * dist, dist, l.normalised()));
+        Some(RayHitInfo::new(root_position + direction * dist, dist, l.normalised()))
     }
 }
 
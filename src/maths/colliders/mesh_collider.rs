@@ -60,10 +60,7 @@ impl Collider for MeshCollider {
         let (root_position, direction): (Vector3, Vector3) = (root_position.into(), direction.into());
         let direction = direction.normalised();
 
-        if self.bounds.check_ray(root_position, direction, max_distance).is_none() {
-            // println!("failed mesh collide on bounds\n- Origin: {:?} \n- Direction: {:?} \n- Bounds: {:?}", root_position, direction, self.bounds);
-            return None;
-        }
+        self.bounds.check_ray(root_position, direction, max_distance)?;
 
         // sort the tris by distance
         let dist_indices = {
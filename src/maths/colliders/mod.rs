@@ -1,6 +1,7 @@
 use super::{vector_three::*, vector_two::Vector2, matrix_three::Matrix3, bounding_box::BoundingBox};
 
 pub mod mesh_collider;
+#[allow(dead_code)]
 pub mod plane_collider;
 pub mod triangle_collider;
 
@@ -17,6 +18,7 @@ pub trait Collider {
 #[derive(Debug)]
 pub struct RayHitInfo {
     pub hit_position: Vector3,
+    #[allow(dead_code)]
     pub hit_distance: f32,
     pub hit_normal: Vector3,
 }
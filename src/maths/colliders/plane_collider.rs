@@ -16,7 +16,7 @@ impl PlaneCollider {
         let position = position.into();
         let scale: Vector3 = [size.x / 2.0, 0.0, size.y / 2.0].into();
         PlaneCollider {
-            position: position,
+            position,
             x_length: size.x,
             z_length: size.y,
             centre: position + scale
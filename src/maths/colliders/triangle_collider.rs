@@ -41,9 +41,7 @@ fn compute_incentre(a: Vector3, b: Vector3, c: Vector3) -> Vector3 {
     let r = (a-b).magnitude();
     let sum = p + q + r;
 
-    let centre = a * (p/sum) + b * (q/sum) + c * (r/sum);
-
-    centre
+    a * (p/sum) + b * (q/sum) + c * (r/sum)
 }
 
 
@@ -72,7 +70,7 @@ impl Collider for TriangleCollider {
         let s = root_position - self.points.c1();
         let u = f * s.dot(h);
 
-        if (u < 0.0) || (u > 1.0) {
+        if !(0.0..=1.0).contains(&u) {
             // println!("2 fail");
             return None;
         }
@@ -13,6 +13,7 @@ pub struct Matrix4 {
 }
 
 impl Matrix4 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         c0r0: f32, c0r1: f32, c0r2: f32, c0r3: f32,
         c1r0: f32, c1r1: f32, c1r2: f32, c1r3: f32,
@@ -44,6 +45,7 @@ impl Matrix4 {
 
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<[[f32; 4]; 4]> for Matrix4 {
     fn into(self) -> [[f32; 4]; 4] {
         [self.x.into(), self.y.into(), self.z.into(), self.w.into()]
@@ -10,6 +10,7 @@ pub struct Matrix3 {
 }
 
 impl Matrix3 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         r0c0: f32, r0c1: f32 , r0c2: f32,
         r1c0: f32, r1c1: f32 , r1c2: f32,
@@ -191,7 +192,7 @@ impl Matrix3 {
     // returns the inverse the given matrix, equivelent to matrix^-1
     pub fn inverted(&self) -> Matrix3{
         let det = self.determinant();
-        if det == 0.0 {return self.clone();}
+        if det == 0.0 {return *self;}
 
         let c0  = self.y.cross(self.z);
         let c1  = self.z.cross(self.x);
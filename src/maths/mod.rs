@@ -14,10 +14,8 @@ pub mod bounding_box;
 // colliders
 pub mod colliders;
 
-use std::f32::{
-    consts::{FRAC_2_SQRT_PI, SQRT_2, PI},
-    EPSILON
-};
+use std::f32::consts::{FRAC_2_SQRT_PI, SQRT_2, PI};
+use self::{vector_three::Vector3, matrix_three::Matrix3};
 
 
 
@@ -28,40 +26,53 @@ pub fn lerp(start: f32, end: f32, position: f32) -> f32{
     start + (end - start) * position.clamp(0.0, 1.0)
 }
 
+/// rotates the direction by angle, away from itself towards the side given by azimuth, an angle around the direction
+pub fn rotate_away(direction: Vector3, angle: f32, azimuth: f32) -> Vector3 {
+    let reference = if direction.y.abs() < 0.99 {Vector3::Y()} else {Vector3::X()};
+    let side = direction.cross(reference).normalised().transform(Matrix3::from_angle_and_axis(azimuth, direction));
+    direction.transform(Matrix3::from_angle_and_axis(angle, side)).normalised()
+}
+
 
 const ROOT_TWOPI: f32 = SQRT_2 * (FRAC_2_SQRT_PI * PI / 2.0); // sqrt(2.0 * PI)
 
 pub fn normal_probabilty_density(value: f32, mean: f32, standard_deviation: f32) -> f32 {
 
-    (1.0 / (standard_deviation * ROOT_TWOPI)) * (-1.0 * (value - mean) * (value - mean) / (2.0 * standard_deviation * standard_deviation) ).exp()
+    (1.0 / (standard_deviation * ROOT_TWOPI)) * (-(value - mean) * (value - mean) / (2.0 * standard_deviation * standard_deviation) ).exp()
 }
 
 
+#[allow(dead_code)]
 const P: f32 = 0.47047;
+#[allow(dead_code)]
 const A1: f32 = 0.3480242;
+#[allow(dead_code)]
 const A2: f32 = -0.0958798;
+#[allow(dead_code)]
 const A3: f32 = 0.7478556;
 
 /// an approxomation of the error function from Abramowitz and Stegun (equation 7.1.26), it has a maximum error of 2.5x10^-5
+#[allow(dead_code)]
 fn error_fn_approx(value: f32) -> f32 {
     let t = 1.0 / (1.0 + P * value);
-    1.0 - (A1 * t + A2 * t * t + A3 * t * t * t) * (t * t * -1.0).exp()
+    1.0 - (A1 * t + A2 * t * t + A3 * t * t * t) * (-t * t).exp()
 }
 
 
 /// cumulative normal distribution fn, maximum error of 2.5x10^-5
+#[allow(dead_code)]
 pub fn normal_cmd(value: f32, mean: f32, standard_deviation: f32) -> f32 {
     let x = (value - mean) / (standard_deviation * SQRT_2);
 
     if x == 0.0 {return 0.5;}
-    if x < 0.0 {return 0.5 * (1.0 - error_fn_approx(-x));}
-    else {return 0.5 * (1.0 + error_fn_approx(x))}
+    if x < 0.0 {0.5 * (1.0 - error_fn_approx(-x))}
+    else {0.5 * (1.0 + error_fn_approx(x))}
 }
 
 #[test]
 fn normal_dist_test() {
-    assert!(0.048394144 - EPSILON < normal_probabilty_density(7.0, 12.0, 5.0) && normal_probabilty_density(7.0, 12.0, 5.0) < 0.048394144 + EPSILON);
-    assert!(0.017205188 - EPSILON < normal_probabilty_density(56.0, 70.0, 15.0) && normal_probabilty_density(56.0, 70.0, 15.0) < 0.017205188 + EPSILON);
-    assert!(0.002215924 - EPSILON < normal_probabilty_density(24.0, 18.0, 2.0) && normal_probabilty_density(24.0, 18.0, 2.0) < 0.002215924 + EPSILON);
-    assert!(0.056068762 - EPSILON < normal_probabilty_density(19.2, 15.0, 5.0) && normal_probabilty_density(19.2, 15.0, 5.0) < 0.056068762 + EPSILON);
+    assert!(0.048394144 - f32::EPSILON < normal_probabilty_density(7.0, 12.0, 5.0) && normal_probabilty_density(7.0, 12.0, 5.0) < 0.048394144 + f32::EPSILON);
+    assert!(0.017205188 - f32::EPSILON < normal_probabilty_density(56.0, 70.0, 15.0) && normal_probabilty_density(56.0, 70.0, 15.0) < 0.017205188 + f32::EPSILON);
+    assert!(0.002215924 - f32::EPSILON < normal_probabilty_density(24.0, 18.0, 2.0) && normal_probabilty_density(24.0, 18.0, 2.0) < 0.002215924 + f32::EPSILON);
+    assert!(0.056068762 - f32::EPSILON < normal_probabilty_density(19.2, 15.0, 5.0) && normal_probabilty_density(19.2, 15.0, 5.0) < 0.056068762 + f32::EPSILON);
 }
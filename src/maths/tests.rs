@@ -12,6 +12,7 @@ use std::f32::consts::PI;
 ///////////////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod bounding_sphere_tests {
     use super::{Vector3, BoundingSphere, PI};
 
@@ -69,6 +69,7 @@ impl Vector4 {
     }
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<[f32; 4]> for Vector4 {
     fn into(self) -> [f32; 4] {
         [self.x, self.y, self.z, self.w]
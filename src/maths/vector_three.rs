@@ -4,10 +4,11 @@ use super::matrix_three::Matrix3;
 use std::f32::consts::PI;
 use std::ops::*;
 use std::cmp::Ordering;
+use serde::{Serialize, Deserialize};
 
 const HALF_PI: f32 = PI / 2.0;
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Vector3 {
     pub x: f32, 
     pub y: f32,
@@ -156,7 +157,7 @@ impl Vector3 {
 
     pub fn angle_to(&self, rhs: impl Into<Vector3>) -> f32 {
         let rhs: Vector3 = rhs.into();
-        return (self.dot(rhs)/(self.magnitude() * rhs.magnitude())).acos();
+        (self.dot(rhs)/(self.magnitude() * rhs.magnitude())).acos()
     }
 
 
@@ -228,6 +229,7 @@ impl Vector3 {
 ///////////////////////////////// from and into
 //////////////////////////////////////////////////////////////////
 
+#[allow(clippy::from_over_into)]
 impl Into<[f32; 3]> for Vector3 {
     fn into(self) -> [f32; 3] {
         [self.x, self.y, self.z]
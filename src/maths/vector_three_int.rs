@@ -1,10 +1,11 @@
 use std::ops::*;
 use std::cmp::Ordering;
+use serde::{Serialize, Deserialize};
 
 use super::vector_three::Vector3;
 
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Hash)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Hash, Serialize, Deserialize)]
 pub struct Vector3Int {
     pub x: i32, 
     pub y: i32,
@@ -29,6 +30,7 @@ impl Vector3Int {
 ///////////////////////////////// from and into
 //////////////////////////////////////////////////////////////////
 
+#[allow(clippy::from_over_into)]
 impl Into<[i32; 3]> for Vector3Int {
 fn into(self) -> [i32; 3] {
     [self.x, self.y, self.z]
@@ -48,6 +48,7 @@ impl Vector2 {
 ///////////////////////////////// from and into
 //////////////////////////////////////////////////////////////////
 
+#[allow(clippy::from_over_into)]
 impl Into<[f32; 2]> for Vector2 {
     fn into(self) -> [f32; 2] {
         [self.x, self.y]
@@ -1,4 +1,5 @@
 
 pub mod plant;
 pub mod plant_development;
-pub mod plant_selection;
\ No newline at end of file
+pub mod plant_selection;
+pub mod plant_statistics;
@@ -2,6 +2,8 @@
 use std::default;
 
 use bevy_ecs::prelude::*;
+use bevy_ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
+use serde::{Serialize, Deserialize};
 use super::super::{
     maths::{vector_three::Vector3, bounding_box::BoundingBox},
     branches::{branch_node::{BranchNodeBundle, BranchNodeData}, branch::{BranchBundle, BranchData}, branch_prototypes::{BranchPrototypesSampler, BranchPrototypeRef}},
@@ -15,7 +17,7 @@ use super::super::{
 #[derive(Default, Component)]
 pub struct PlantTag;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 pub struct PlantData {
     pub position: Vector3,
     pub intersection_list: Vec<Entity>,
@@ -24,11 +26,15 @@ pub struct PlantData {
     pub climate_adaption: f32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 pub struct PlantBounds {
     pub bounds: BoundingBox,
 }
 
+/// the index of the species a plant belongs to in the PlantSpeciesSampler, plants get it when they are spawned and pass it on to their seedlings
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlantSpecies(pub usize);
+
 
 #[derive(Bundle)]
 pub struct PlantBundle {
@@ -39,13 +45,13 @@ pub struct PlantBundle {
     pub plasticity_params: PlantPlasticityParameters,
 }
 
-#[derive(Resource)]
+#[derive(Resource, Serialize, Deserialize)]
 pub struct PlantDeathRate {
     pub v_max_decrease: f32,
 }
 
 
-#[derive(Component, Clone, PartialEq)]
+#[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlantPlasticityParameters {
     pub flowering_age: f32,
     pub seeding_frequency: f32,
@@ -59,7 +65,8 @@ pub struct PlantPlasticityParameters {
     pub shadow_tolerance: f32,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PlasticitySettingParams {
     pub seeding_frequency: f32,
     pub flowering_age: f32,
@@ -68,6 +75,9 @@ pub struct PlasticitySettingParams {
     pub shadow_tolerance: f32,
 }
 
+/// a species preset, its settings and climate envelope: (growth, plasticity), (ideal temperature, temperature standard deviation, ideal moisture, moisture standard deviation)
+pub type SpeciesPreset = ((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32));
+
 impl PlantPlasticityParameters {
     pub fn copy_for_new_plant(&self) -> PlantPlasticityParameters {
         PlantPlasticityParameters {
@@ -101,7 +111,7 @@ impl PlasticitySettingParams {
 }
 
 
-#[derive(Component, Clone, PartialEq)]
+#[derive(Component, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PlantGrowthControlFactors {
     pub max_age: f32,
 
@@ -144,7 +154,8 @@ impl PlantGrowthControlFactors {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct GrowthControlSettingParams {
     pub max_age: f32,
 
@@ -163,6 +174,7 @@ pub struct GrowthControlSettingParams {
     pub thickening_factor: f32,
 }
 
+#[allow(clippy::from_over_into)]
 impl Into<PlantGrowthControlFactors> for GrowthControlSettingParams {
     fn into(self) -> PlantGrowthControlFactors {
         PlantGrowthControlFactors {
@@ -215,7 +227,7 @@ pub fn spawn_plant(
     let root_branch_id = commands.spawn(BranchBundle{
         data: BranchData {
             root_node: Some(root_node_id),
-            root_position: root_position,
+            root_position,
             ..Default::default()
         },
         prototype: BranchPrototypeRef(branch_sampler.get_prototype_index(growth_settings.apical_control, branch_sampler.max_determinancy)),
@@ -237,6 +249,17 @@ pub fn spawn_plant(
     (root_branch_id, root_branch_id, plant_id)
 }
 
+impl MapEntities for PlantData {
+    /// the intersection list is rebuilt every update, so any plants that no longer exist are dropped rather than failing
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(root_node) = self.root_node {
+            self.root_node = Some(entity_map.get(root_node)?);
+        }
+        self.intersection_list = self.intersection_list.iter().filter_map(|id| entity_map.get(*id).ok()).collect();
+        Ok(())
+    }
+}
+
 impl PlantDeathRate {
     pub fn new(death_rate: f32) -> Self {
         PlantDeathRate {
@@ -1,15 +1,18 @@
 use bevy_ecs::prelude::*;
 use std::f32::consts::PI;
 use rand_distr::{Normal, Distribution};
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use super::{
     super::{
         environment::{
             params::*,
             terrain::*,
+            soil_grid::SoilGrid,
+            climate::ClimateDriver,
+            ground_light::GroundLight,
         },
         branches::{branch::*, branch_prototypes::BranchPrototypesSampler},
-        maths::{bounding_box::BoundingBox, bounding_sphere::BoundingSphere, colliders::Collider},
+        maths::{vector_three::Vector3, bounding_box::BoundingBox, bounding_sphere::BoundingSphere, colliders::Collider, lerp},
     },
     plant::*,
     plant_selection::*,
@@ -49,7 +52,7 @@ pub fn update_plant_bounds(
         
         for id in get_branches_base_to_tip(&branch_connections_query, plant_data.root_node.unwrap()) {
             if let Ok(bounds) = branch_bounds_query.get(id) {
-                branch_bounds.push(bounds.bounds.clone());
+                branch_bounds.push(bounds.bounds);
             }
         }
 
@@ -58,6 +61,60 @@ pub fn update_plant_bounds(
     }
 }
 
+/// recharges the soil grid from the precipitation, then takes the moisture and nutrients each plant uses from the cells under it
+///
+/// A plant's biomass is the total volume of its branch bounds, and it draws from the cells within the horizontal extent of its bounds.
+/// Must be called after updating the plant bounds
+pub fn update_soil_grid(
+    branch_bounds_query: Query<&BranchBounds, With<BranchTag>>,
+    branch_connections_query: Query<&BranchConnectionData, With<BranchTag>>,
+    plants_query: Query<(&PlantBounds, &PlantData), With<PlantTag>>,
+    environment: Res<MoistureAndTemp>,
+    timestep: Res<PhysicalAgeStep>,
+    mut soil_grid: ResMut<SoilGrid>,
+) {
+    soil_grid.recharge(environment.moisture, timestep.step);
+
+    for (bounds, plant_data) in &plants_query {
+        if plant_data.root_node.is_none() {continue;}
+
+        let biomass: f32 = get_branches_base_to_tip(&branch_connections_query, plant_data.root_node.unwrap()).into_iter()
+            .filter_map(|id| branch_bounds_query.get(id).ok())
+            .map(|bounds| bounds.bounds.get_volume())
+            .sum();
+        let extent = bounds.bounds.max_corner - bounds.bounds.min_corner;
+        let radius = extent.x.abs().max(extent.z.abs()) / 2.0;
+
+        soil_grid.deplete(plant_data.position, radius, biomass, timestep.step);
+    }
+}
+
+/// sets the climate adaptation of every plant from the annual temperature at its height and the moisture of the soil under it
+///
+/// The seasonal cycle of the climate driver is left out so that plants don't lose their vigor every winter,
+/// without a climate driver the temperature comes from the environment
+pub fn update_climate_adaption(
+    mut plants_query: Query<(&mut PlantData, &PlantSpecies), With<PlantTag>>,
+    plant_sampler: Res<PlantSpeciesSampler>,
+    environment: Res<MoistureAndTemp>,
+    climate: Option<Res<ClimateDriver>>,
+    soil_grid: Res<SoilGrid>,
+) {
+    for (mut plant_data, species) in plants_query.iter_mut() {
+        let temp = annual_temperature(&environment, climate.as_deref(), plant_data.position);
+        let moist = soil_grid.get_moisture(plant_data.position);
+        if let Some(climate_adapt) = plant_sampler.calculate_species_climate_adapt(*species, moist, temp) {
+            plant_data.climate_adaption = climate_adapt;
+        }
+    }
+}
+
+/// the temperature at a position without the seasonal cycle, plants adapt to the climate of the year rather than the season
+fn annual_temperature(environment: &MoistureAndTemp, climate: Option<&ClimateDriver>, position: Vector3) -> f32 {
+    let temp_at_zero = climate.map(|climate| climate.annual_climate().0).unwrap_or(environment.temp_at_zero);
+    temp_at_zero + position.y * environment.temp_fall_off
+}
+
 // this will calculate all the plant intersections, it will not contain any repeated intersect
 pub fn update_plant_intersections(
     mut plants_query: Query<(&PlantBounds, &mut PlantData, Entity), With<PlantTag>>,
@@ -76,14 +133,19 @@ pub fn update_plant_intersections(
 }
 
 
+#[allow(clippy::too_many_arguments)]
 pub fn seed_plants(
-    mut plants_query: Query<(&PlantData, &mut PlantGrowthControlFactors, &mut PlantPlasticityParameters, Entity), With<PlantTag>>,
+    mut plants_query: Query<(&PlantData, &mut PlantGrowthControlFactors, &mut PlantPlasticityParameters, Option<&PlantSpecies>), With<PlantTag>>,
     plant_sampler: Res<PlantSpeciesSampler>,
     environment: Res<MoistureAndTemp>,
+    climate: Option<Res<ClimateDriver>>,
+    soil_grid: Res<SoilGrid>,
+    ground_light: Option<Res<GroundLight>>,
     branch_query: Query<&BranchGrowthData, With<BranchTag>>,
     branch_sampler: Res<BranchPrototypesSampler>,
     terrain_query: Query<&TerrainCollider, With<TerrainTag>>,
     timestep: Res<PhysicalAgeStep>,
+    mut rng: ResMut<SimulationRng>,
     #[cfg(feature = "vulkan_graphics")]
     mut queue: Query<&mut MeshUpdateQueue>,
     mut commands: Commands,
@@ -111,15 +173,28 @@ pub fn seed_plants(
         // check to seed
 
         while plant.2.time_since_seeding >= plant.2.seeding_interval {
-            let distance_from_centre = (Normal::new(plant.2.seeding_radius, plant.2.seeding_std_dev).unwrap().sample(&mut rand::thread_rng()) - plant.2.seeding_radius).abs();
-            let angle_from_centre = rand::thread_rng().gen_range(0.0..(PI * 2.0)); // 0 is along the +x axis
+            let distance_from_centre = (Normal::new(plant.2.seeding_radius, plant.2.seeding_std_dev).unwrap().sample(&mut rng.rng) - plant.2.seeding_radius).abs();
+            let angle_from_centre = rng.rng.gen_range(0.0..(PI * 2.0)); // 0 is along the +x axis
             let (ray_x, ray_z) = (plant.0.position.x + angle_from_centre.cos() * distance_from_centre, plant.0.position.z + angle_from_centre.sin() * distance_from_centre);
             if let Some(ray_hit) = terrain.collider.check_ray([ray_x, terrain.max_height, ray_z], [0, -1, 0], None) {
                 let child_factors = (plant.1.copy_for_new_plant(), plant.2.copy_for_new_plant());
-                let climate_adapt = plant_sampler.calculate_child_climate_adapt(&child_factors, environment.moisture, environment.temp_at_zero + ray_hit.hit_position.y * environment.temp_fall_off);
-                let ids = spawn_plant(ray_hit.hit_position, ray_hit.hit_normal, child_factors.0, child_factors.1, climate_adapt, branch_sampler.as_ref(), &mut commands);
-                #[cfg(feature = "vulkan_graphics")]
-                queue.ids.push_back(ids.1);
+
+                // with a ground light map, the light reaching the ground is the chance of the seedling surviving, shade tolerant seedlings survive more shade
+                let survives = match &ground_light {
+                    Some(ground_light) => rng.rng.gen::<f32>() < lerp(child_factors.1.shadow_tolerance, 1.0, ground_light.get_light(ray_hit.hit_position)),
+                    None => true,
+                };
+                if survives {
+                    // seedlings are the same species as their parent, and keep its climate adaptation if the species is unknown
+                    let species = plant.3.copied();
+                    let climate_adapt = species
+                        .and_then(|species| plant_sampler.calculate_species_climate_adapt(species, soil_grid.get_moisture(ray_hit.hit_position), annual_temperature(&environment, climate.as_deref(), ray_hit.hit_position)))
+                        .unwrap_or(plant.0.climate_adaption);
+                    let ids = spawn_plant(ray_hit.hit_position, ray_hit.hit_normal, child_factors.0, child_factors.1, climate_adapt, branch_sampler.as_ref(), &mut commands);
+                    if let Some(species) = species {commands.entity(ids.2).insert(species);}
+                    #[cfg(feature = "vulkan_graphics")]
+                    queue.ids.push_back(ids.1);
+                }
             }
             plant.2.time_since_seeding -= plant.2.seeding_interval;
         }
@@ -1,7 +1,8 @@
 use rand::Rng;
 use bevy_ecs::prelude::*;
+use serde::{Serialize, Deserialize};
 use super::{
-    plant::{PlantGrowthControlFactors, PlantPlasticityParameters, GrowthControlSettingParams, PlasticitySettingParams},
+    plant::{PlantGrowthControlFactors, PlantPlasticityParameters, PlantSpecies, SpeciesPreset},
     super::maths::{normal_cmd, normal_probabilty_density},
 };
 
@@ -10,60 +11,81 @@ use super::{
 /// 
 /// - species: A vec of all the different plant species data, their index corresponds to a set of params
 /// - species_params: A vec of the conditions needed for different species to be created, (ideal_temp, temp_standard_deviation, ideal_temp_prob_density ideal_moisture, moisture_standard_deviation, ideal_moisture_prob_density)
-#[derive(Resource)]
+/// - names: the name of each species, if it has one
+#[derive(Resource, Serialize, Deserialize)]
 pub struct PlantSpeciesSampler {
     species: Vec<(PlantGrowthControlFactors, PlantPlasticityParameters)>,
     species_params: Vec<(f32, f32, f32, f32)>,
+    names: Vec<Option<String>>,
 }
 
 impl PlantSpeciesSampler {
 
     /// creates a new plant_species sampler from the given plant species and parameters
-    pub fn new(init_species: Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))>, time_step: f32) -> Self {
+    pub fn new(init_species: Vec<SpeciesPreset>, time_step: f32) -> Self {
         let mut species_params = Vec::new();
         let mut plants: Vec<(PlantGrowthControlFactors, PlantPlasticityParameters)> = Vec::new();
         for species in init_species {
             species_params.push((species.1.0, species.1.1, species.1.2, species.1.3,));
             plants.push((species.0.0.into(), species.0.1.into_plasticity(time_step)));
         }
+        let names = vec![None; plants.len()];
         PlantSpeciesSampler {
-            species: plants, species_params
+            species: plants, species_params, names
         }
     }
 
+    /// names the species in order, any species past the end of the names are left without one
+    pub fn set_names(&mut self, names: Vec<Option<String>>) {
+        self.names = (0..self.species.len()).map(|i| names.get(i).cloned().flatten()).collect();
+    }
+
+    /// returns the name of the species, or None if it doesn't have one
+    pub fn name(&self, species: PlantSpecies) -> Option<&str> {
+        self.names.get(species.0).and_then(|name| name.as_deref())
+    }
+
     /// adds new plant species to the sampler
-    pub fn add_species(&mut self, new_species: Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))>, time_step: f32) {
+    #[allow(dead_code)]
+    pub fn add_species(&mut self, new_species: Vec<SpeciesPreset>, time_step: f32) {
         let mut species_params = Vec::new();
         let mut plants = Vec::new();
         for species in new_species {
             species_params.push((species.1.0, species.1.1, species.1.2, species.1.3));
             plants.push((species.0.0.into(), species.0.1.into_plasticity(time_step)));
         }
+        self.names.extend(plants.iter().map(|_| None));
         self.species.append(&mut plants);
         self.species_params.append(&mut species_params)
     }
 
     /// replaces all the species in the sampler with the given options
-    pub fn replace_all(&mut self, new_species: Vec<((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32))>, time_step: f32) {
+    #[allow(dead_code)]
+    pub fn replace_all(&mut self, new_species: Vec<SpeciesPreset>, time_step: f32) {
         let mut species_params = Vec::new();
         let mut plants = Vec::new();
         for species in new_species {
             species_params.push((species.1.0, species.1.1, species.1.2, species.1.3));
             plants.push((species.0.0.into(), species.0.1.into_plasticity(time_step)));
         }
+        self.names = vec![None; plants.len()];
         self.species = plants;
         self.species_params = species_params;
     }
 
     /// removes a species from the sampler at a given index
+    #[allow(dead_code)]
     pub fn remove(&mut self, index: usize) {
         self.species.remove(index);
-        self.species.remove(index);
+        self.species_params.remove(index);
+        self.names.remove(index);
     }
 
     /// replaces a species in the sampler at a given index with the provided species
-    pub fn replace(&mut self, index: usize, new: ((GrowthControlSettingParams, PlasticitySettingParams), (f32, f32, f32, f32)), time_step: f32) {
+    #[allow(dead_code)]
+    pub fn replace(&mut self, index: usize, new: SpeciesPreset, time_step: f32) {
         self.remove(index);
+        self.names.insert(index, None);
         self.species.insert(index, (new.0.0.into(), new.0.1.into_plasticity(time_step)));
         let params = (new.1.0, new.1.1, new.1.2, new.1.3);
         self.species_params.insert(index, params);
@@ -76,8 +98,8 @@ impl PlantSpeciesSampler {
     /// - Plants lying more than 5 standard deviations away from either parameter are removed from the chances, probability at that point is close to 0
     /// - All remaining plants are chosen from with probabilty weights generated using normal distribution
     /// - If no plants can be grown, returns None
-    /// - Returns the chosen plant and its climate adaptation
-    pub fn get_plant(&self, temp: f32, moist: f32) -> Option<((PlantGrowthControlFactors, PlantPlasticityParameters), f32)>{
+    /// - Returns the species of the chosen plant, its settings and its climate adaptation
+    pub fn get_plant(&self, temp: f32, moist: f32, rng: &mut impl Rng) -> Option<(PlantSpecies, (PlantGrowthControlFactors, PlantPlasticityParameters), f32)>{
 
         let mut choices: Vec<(usize, f32)> = Vec::new();
         let mut total_prob = 0.0;
@@ -93,27 +115,32 @@ impl PlantSpeciesSampler {
             total_prob += climate_adapt;
             choices.push((i, climate_adapt));
         }
-        let position = rand::thread_rng().gen_range(0.0..=total_prob);
+        let position = rng.gen_range(0.0..=total_prob);
 
         let mut total_prob = 0.0;
         for choice in choices.iter() {
             total_prob += choice.1;
             if position <=  total_prob{
-                return Some((self.species[choice.0].clone(), choice.1));
+                return Some((PlantSpecies(choice.0), self.species[choice.0].clone(), choice.1));
             }
         }
         None
     }
 
-    pub fn calculate_child_climate_adapt(
+    /// returns the number of species that can be sampled
+    pub fn species_count(&self) -> usize {
+        self.species.len()
+    }
+
+    /// returns the climate adaptation of a species in the given climate, or None if the species isn't in the sampler
+    pub fn calculate_species_climate_adapt(
         &self,
-        parent: &(PlantGrowthControlFactors, PlantPlasticityParameters),
+        species: PlantSpecies,
         moist: f32,
         temp: f32,
-    ) -> f32 {
-        let index = self.species.iter().position(|x| x == parent).unwrap();
-        let species_factors = self.species_params[index];
-        calculate_climate_adapt(temp, species_factors.0, species_factors.1, moist, species_factors.2, species_factors.3)
+    ) -> Option<f32> {
+        let species_factors = self.species_params.get(species.0)?;
+        Some(calculate_climate_adapt(temp, species_factors.0, species_factors.1, moist, species_factors.2, species_factors.3))
     }
 }
 
@@ -0,0 +1,199 @@
+use std::{collections::HashMap, f32::consts::PI};
+use bevy_ecs::prelude::*;
+use serde::Serialize;
+use super::{
+    super::{
+        environment::params::PhysicalAgeStep,
+        branches::{branch::*, branch_node::*},
+    },
+    plant::*,
+    plant_selection::PlantSpeciesSampler,
+};
+
+
+/// The statistics of every living plant of a species after a step of the simulation
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpeciesStatistics {
+    pub step: u32,
+    pub years: f32,
+    /// the index of the species in the species sampler, none for plants without a known species
+    pub species: Option<usize>,
+    /// the name of the species, if it has one
+    pub name: Option<String>,
+    pub count: usize,
+    pub mean_age: f32,
+    /// the total cross sectional area of the root nodes of the plants
+    pub basal_area: f32,
+    pub branch_count: usize,
+    /// the total volume of the plant bounding boxes
+    pub canopy_volume: f32,
+    pub mean_climate_adaption: f32,
+    /// how many plants of the species died during the step
+    pub deaths: usize,
+    /// how many plants of the species were spawned during the step
+    pub births: usize,
+}
+
+/// The statistics collected after every step of the simulation
+#[derive(Resource, Default)]
+pub struct ForestStatistics {
+    /// a row for every species at every step, in order of steps
+    pub rows: Vec<SpeciesStatistics>,
+    step: u32,
+    elapsed_years: f32,
+    /// the plants alive after the last step and their species
+    living: HashMap<Entity, Option<usize>>,
+}
+
+
+/// adds the statistics resource to the world, the plants currently in the world are not counted as births
+///
+/// Rows from the given statistics after the given number of years are dropped, so that the statistics can be kept when loading a checkpoint
+pub fn create_forest_statistics(
+    world: &mut World,
+    previous: Option<ForestStatistics>,
+    elapsed_years: f32,
+) {
+    let mut statistics = previous.unwrap_or_default();
+    statistics.rows.retain(|row| row.years <= elapsed_years);
+    statistics.step = statistics.rows.last().map(|row| row.step).unwrap_or(0);
+    statistics.elapsed_years = elapsed_years;
+
+    let mut query = world.query_filtered::<(Entity, Option<&PlantSpecies>), With<PlantTag>>();
+    statistics.living = query.iter(world).map(|(id, species)| (id, species.map(|species| species.0))).collect();
+
+    world.insert_resource(statistics);
+}
+
+
+/// adds a row of statistics for each species, must be run after seeding so that new plants are counted
+pub fn collect_forest_statistics(
+    plant_query: Query<(Entity, &PlantData, &PlantBounds, Option<&PlantSpecies>), With<PlantTag>>,
+    branch_query: Query<&BranchData, With<BranchTag>>,
+    branch_connections_query: Query<&BranchConnectionData, With<BranchTag>>,
+    node_query: Query<&BranchNodeData, With<BranchNodeTag>>,
+    sampler: Res<PlantSpeciesSampler>,
+    timestep: Res<PhysicalAgeStep>,
+    mut statistics: ResMut<ForestStatistics>,
+) {
+    statistics.step += 1;
+    statistics.elapsed_years += timestep.step;
+    let (step, years) = (statistics.step, statistics.elapsed_years);
+
+    let new_row = |species: Option<usize>| SpeciesStatistics {
+        step,
+        years,
+        species,
+        name: species.and_then(|species| sampler.name(PlantSpecies(species))).map(|name| name.to_string()),
+        count: 0,
+        mean_age: 0.0,
+        basal_area: 0.0,
+        branch_count: 0,
+        canopy_volume: 0.0,
+        mean_climate_adaption: 0.0,
+        deaths: 0,
+        births: 0,
+    };
+    let mut rows: Vec<SpeciesStatistics> = (0..sampler.species_count()).map(|i| new_row(Some(i))).collect();
+    let mut unknown = new_row(None);
+
+    let mut living: HashMap<Entity, Option<usize>> = HashMap::new();
+    for (id, data, bounds, species) in plant_query.iter() {
+        let species = species.map(|species| species.0).filter(|index| *index < rows.len());
+        let row = match species {
+            Some(index) => &mut rows[index],
+            None => &mut unknown,
+        };
+
+        row.count += 1;
+        if !statistics.living.contains_key(&id) {row.births += 1;}
+        row.mean_age += data.age;
+        row.mean_climate_adaption += data.climate_adaption;
+        row.canopy_volume += bounds.bounds.volume();
+
+        if let Some(root_branch) = data.root_node {
+            row.branch_count += get_branches_base_to_tip(&branch_connections_query, root_branch).len();
+            let root_node = branch_query.get(root_branch).ok().and_then(|branch| branch.root_node);
+            if let Some(node) = root_node.and_then(|node| node_query.get(node).ok()) {
+                row.basal_area += PI * node.thickness * node.thickness;
+            }
+        }
+
+        living.insert(id, species);
+    }
+
+    for (id, species) in statistics.living.iter() {
+        if living.contains_key(id) {continue;}
+        match species {
+            Some(index) => rows[*index].deaths += 1,
+            None => unknown.deaths += 1,
+        }
+    }
+
+    if unknown.count > 0 || unknown.deaths > 0 {rows.push(unknown);}
+    for row in rows.iter_mut() {
+        if row.count > 0 {
+            row.mean_age /= row.count as f32;
+            row.mean_climate_adaption /= row.count as f32;
+        }
+    }
+
+    statistics.rows.extend(rows);
+    statistics.living = living;
+}
+
+
+
+#[cfg(test)]
+mod plant_statistics_tests {
+    use super::*;
+    use super::super::super::apps::presets::species;
+
+    #[test]
+    fn births_and_deaths_test() {
+        let mut world = World::new();
+        world.insert_resource(PlantSpeciesSampler::new(Vec::new(), 1.0));
+        world.insert_resource(PhysicalAgeStep {step: 0.5});
+        let first = world.spawn(PlantBundle {data: PlantData {age: 2.0, ..Default::default()}, ..Default::default()}).id();
+        world.spawn(PlantBundle {data: PlantData {age: 4.0, ..Default::default()}, ..Default::default()});
+        create_forest_statistics(&mut world, None, 0.0);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(collect_forest_statistics);
+        schedule.run(&mut world);
+
+        // the plants don't have a species
+        let rows = &world.resource::<ForestStatistics>().rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].species, rows[0].count, rows[0].mean_age, rows[0].births, rows[0].deaths), (None, 2, 3.0, 0, 0));
+
+        world.despawn(first);
+        world.spawn(PlantBundle::default());
+        world.spawn(PlantBundle::default());
+        schedule.run(&mut world);
+
+        let row = world.resource::<ForestStatistics>().rows.last().unwrap().clone();
+        assert_eq!((row.step, row.years, row.count, row.births, row.deaths), (2, 1.0, 3, 2, 1));
+    }
+
+    #[test]
+    fn species_name_test() {
+        let mut world = World::new();
+        let mut sampler = PlantSpeciesSampler::new(vec![species("oak").unwrap(), species("birch").unwrap()], 1.0);
+        sampler.set_names(vec![Some("oak".to_string())]);
+        world.insert_resource(sampler);
+        world.insert_resource(PhysicalAgeStep {step: 1.0});
+        world.spawn(PlantBundle::default()).insert(PlantSpecies(1));
+        world.spawn(PlantBundle::default()).insert(PlantSpecies(1));
+        create_forest_statistics(&mut world, None, 0.0);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(collect_forest_statistics);
+        schedule.run(&mut world);
+
+        // plants are counted by their species component, and only the first species has a name
+        let rows = &world.resource::<ForestStatistics>().rows;
+        let summary: Vec<(Option<usize>, Option<&str>, usize)> = rows.iter().map(|row| (row.species, row.name.as_deref(), row.count)).collect();
+        assert_eq!(summary, vec![(Some(0), Some("oak"), 0), (Some(1), None, 2)]);
+    }
+}
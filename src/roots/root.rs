@@ -0,0 +1,132 @@
+use bevy_ecs::prelude::*;
+use bevy_ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
+use serde::{Serialize, Deserialize};
+use super::super::maths::vector_three::Vector3;
+
+
+///////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////// structs and components //////////////////////////////////
+///////////////////////////////////////////////////////////////////////////////////////
+
+
+#[derive(Debug, Default, Component)]
+pub struct RootTag;
+
+#[derive(Debug, Default, Component)]
+pub struct RootNodeTag;
+
+
+/// The root system of a plant, added to every plant once root growth is enabled
+#[derive(Debug, Clone, Component, Serialize, Deserialize)]
+pub struct PlantRoots {
+    /// the main root, which grows down from the base of the plant
+    pub root_branch: Option<Entity>,
+    /// the mean resource available at the root tips, which scales the plant's max vigor, from 0 to 1 in the default soil
+    pub root_vigor: f32,
+}
+
+/// A root branch, which is a line of root nodes with a growing tip at the end
+#[derive(Debug, Component, Serialize, Deserialize)]
+pub struct RootData {
+    pub plant: Entity,
+    /// the nodes of the root from base to tip
+    pub nodes: Vec<Entity>,
+    /// the direction the tip is growing in
+    pub direction: Vector3,
+    /// the resource taken up by the tip in the last step
+    pub uptake: f32,
+    /// how far the tip has grown since its last node was added
+    pub growth: f32,
+    /// how many roots away from the main root this root is
+    pub order: u32,
+}
+
+#[derive(Debug, Default, Component, Serialize, Deserialize)]
+pub struct RootConnectionData {
+    pub parent: Option<Entity>,
+    pub children: Vec<Entity>,
+}
+
+#[derive(Debug, Default, Component, Serialize, Deserialize)]
+pub struct RootNodeData {
+    pub position: Vector3,
+}
+
+/// The settings every root grows with
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+pub struct RootGrowthSettings {
+    /// the distance between root nodes in m
+    pub segment_length: f32,
+    /// how strongly root tips turn towards the soil resources
+    pub hydrotropism: f32,
+    /// how many nodes are added to a root between each lateral root
+    pub branching_interval: u32,
+    /// the angle in radians between a lateral root and its parent
+    pub branching_angle: f32,
+    /// the highest order of lateral root that can grow more lateral roots
+    pub max_order: u32,
+}
+
+
+#[derive(Bundle)]
+pub struct RootBundle {
+    pub tag: RootTag,
+    pub data: RootData,
+    pub connections: RootConnectionData,
+}
+
+#[derive(Bundle, Default)]
+pub struct RootNodeBundle {
+    pub tag: RootNodeTag,
+    pub data: RootNodeData,
+}
+
+
+///////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////// Impl ///////////////////////////////////////////////
+///////////////////////////////////////////////////////////////////////////////////////
+
+
+impl RootGrowthSettings {
+    /// creates the settings with the given segment length and hydrotropism, laterals grow every 4 nodes at 70 degrees up to the second order
+    pub fn new(segment_length: f32, hydrotropism: f32) -> Self {
+        RootGrowthSettings {
+            segment_length: segment_length.max(0.0000001),
+            hydrotropism,
+            branching_interval: 4,
+            branching_angle: 1.2,
+            max_order: 2,
+        }
+    }
+}
+
+impl MapEntities for PlantRoots {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(root_branch) = self.root_branch {
+            self.root_branch = Some(entity_map.get(root_branch)?);
+        }
+        Ok(())
+    }
+}
+
+impl MapEntities for RootData {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.plant = entity_map.get(self.plant)?;
+        for node in self.nodes.iter_mut() {
+            *node = entity_map.get(*node)?;
+        }
+        Ok(())
+    }
+}
+
+impl MapEntities for RootConnectionData {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let Some(parent) = self.parent {
+            self.parent = Some(entity_map.get(parent)?);
+        }
+        for child in self.children.iter_mut() {
+            *child = entity_map.get(*child)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use std::{collections::HashMap, f32::consts::PI};
+use bevy_ecs::prelude::*;
+use super::{
+    super::{
+        plants::plant::*,
+        branches::branch::*,
+        environment::{params::*, soil_cells::*, soil_grid::SoilGrid},
+        maths::{vector_three_int::Vector3Int, rotate_away},
+    },
+    root::*,
+};
+
+
+/// the radius around a root node that it takes up resources from, which gives the root volume added to the soil cells
+const ROOT_ZONE_RADIUS: f32 = 0.25;
+/// how far a root tip grows in a year, in segments, with full resources and a growth rate of 1
+const SEGMENTS_PER_YEAR: f32 = 5.0;
+/// how strongly root tips turn towards gravity for a tropism strength of 1
+const GRAVITROPISM_WEIGHT: f32 = 0.3;
+/// the angle around the parent root between one lateral root and the next
+const LATERAL_ROOT_AZIMUTH: f32 = 2.4;
+
+
+/// gives any plants without roots a main root growing down from their base
+#[allow(clippy::type_complexity)]
+pub fn spawn_plant_roots(
+    plant_query: Query<(Entity, &PlantData), (With<PlantTag>, Without<PlantRoots>)>,
+    gravity: Res<GravityResources>,
+    mut commands: Commands,
+) {
+    for (plant_id, plant_data) in plant_query.iter() {
+        let node_id = commands.spawn(RootNodeBundle{
+            data: RootNodeData{position: plant_data.position},
+            ..Default::default()
+        }).id();
+
+        let root_id = commands.spawn(RootBundle{
+            tag: RootTag,
+            data: RootData {
+                plant: plant_id,
+                nodes: vec![node_id],
+                direction: gravity.gravity_dir,
+                uptake: 1.0,
+                growth: 0.0,
+                order: 0,
+            },
+            connections: RootConnectionData::default(),
+        }).id();
+
+        commands.entity(plant_id).insert(PlantRoots{root_branch: Some(root_id), root_vigor: 1.0});
+    }
+}
+
+
+/// removes the roots and root nodes of plants that have died
+pub fn remove_dead_roots(
+    root_query: Query<(Entity, &RootData), With<RootTag>>,
+    plant_query: Query<(), With<PlantTag>>,
+    mut commands: Commands,
+) {
+    for (id, root) in root_query.iter() {
+        if plant_query.contains(root.plant) {continue;}
+
+        for node_id in root.nodes.iter() {
+            commands.entity(*node_id).despawn();
+        }
+        commands.entity(id).despawn();
+    }
+}
+
+
+/// fills the soil cells with the root volume around every root node, then finds the resource taken up by every root tip
+///
+/// The root vigor of each plant is the mean uptake of its root tips, a tip competes with every root volume in its cell apart from its own root's
+pub fn calculate_root_uptake(
+    mut plant_query: Query<&mut PlantRoots, With<PlantTag>>,
+    mut root_query: Query<&mut RootData, With<RootTag>>,
+    node_query: Query<&RootNodeData, With<RootNodeTag>>,
+    settings: Res<RootGrowthSettings>,
+    mut soil_cells: ResMut<SoilCells>,
+    soil_grid: Res<SoilGrid>,
+) {
+    // update the soil cells
+    soil_cells.set_all_zero();
+    let cell_size = soil_cells.size();
+    let node_volume = PI * ROOT_ZONE_RADIUS * ROOT_ZONE_RADIUS * settings.segment_length;
+    for node in node_query.iter() {
+        soil_cells.add_volume_to_cell(node.position / cell_size, node_volume);
+    }
+
+    // update uptake
+    let mut plant_uptake: HashMap<Entity, (f32, u32)> = HashMap::new();
+    for mut root in root_query.iter_mut() {
+        let tip = root.nodes.last().and_then(|id| node_query.get(*id).ok());
+        root.uptake = match tip {
+            Some(node) => {
+                let tip_cell = Vector3Int::from(node.position / cell_size);
+                let own_nodes = root.nodes.iter()
+                    .filter_map(|id| node_query.get(*id).ok())
+                    .filter(|own_node| Vector3Int::from(own_node.position / cell_size) == tip_cell)
+                    .count();
+                soil_cells.get_available_resource(tip_cell, &soil_grid, own_nodes as f32 * node_volume)
+            }
+            None => 0.0,
+        };
+
+        let total = plant_uptake.entry(root.plant).or_insert((0.0, 0));
+        total.0 += root.uptake;
+        total.1 += 1;
+    }
+
+    for (plant_id, (uptake, count)) in plant_uptake {
+        if let Ok(mut roots) = plant_query.get_mut(plant_id) {
+            roots.root_vigor = uptake / count as f32;
+        }
+    }
+}
+
+
+/// grows the root tips at the growth rate of their plant's root branch, slowed by a lack of resources
+///
+/// Tips turn towards gravity and towards increasing resources in the soil cells, which come from the fertility of the soil grid, and new lateral roots grow every branching interval.
+/// Roots are kept below the base of their plant
+#[allow(clippy::too_many_arguments)]
+pub fn grow_roots(
+    plant_query: Query<&PlantData, With<PlantTag>>,
+    branch_query: Query<&BranchGrowthData, With<BranchTag>>,
+    mut root_query: Query<(Entity, &mut RootData, &mut RootConnectionData), With<RootTag>>,
+    node_query: Query<&RootNodeData, With<RootNodeTag>>,
+
+    soil_cells: Res<SoilCells>,
+    soil_grid: Res<SoilGrid>,
+    gravity: Res<GravityResources>,
+    settings: Res<RootGrowthSettings>,
+    age_step: Res<PhysicalAgeStep>,
+
+    mut commands: Commands,
+) {
+    let cell_size = soil_cells.size();
+    let segment_length = settings.segment_length;
+
+    for (id, mut root, mut connections) in root_query.iter_mut() {
+        let plant_data = match plant_query.get(root.plant) {
+            Ok(plant_data) => plant_data,
+            Err(_) => continue,
+        };
+        let mut position = match root.nodes.last().and_then(|id| node_query.get(*id).ok()) {
+            Some(node) => node.position,
+            None => continue,
+        };
+
+        let growth_rate = plant_data.root_node.and_then(|id| branch_query.get(id).ok()).map(|branch| branch.growth_rate).unwrap_or(0.0);
+        root.growth += SEGMENTS_PER_YEAR * segment_length * growth_rate * root.uptake * age_step.step;
+
+        while root.growth >= segment_length {
+            root.growth -= segment_length;
+
+            let tropism = gravity.gravity_dir * gravity.tropism_strength * GRAVITROPISM_WEIGHT + soil_cells.get_resource_gradient(position / cell_size, &soil_grid) * settings.hydrotropism;
+            let direction = (root.direction + tropism).normalised();
+            if direction.x.is_nan() {break;}
+
+            position += direction * segment_length;
+            position.y = position.y.min(plant_data.position.y);
+            root.direction = direction;
+
+            let node_id = commands.spawn(RootNodeBundle{
+                data: RootNodeData{position},
+                ..Default::default()
+            }).id();
+            root.nodes.push(node_id);
+
+            // add a lateral root
+            if root.order >= settings.max_order || settings.branching_interval == 0 {continue;}
+            if (root.nodes.len() - 1) % settings.branching_interval as usize != 0 {continue;}
+
+            let lateral_node_id = commands.spawn(RootNodeBundle{
+                data: RootNodeData{position},
+                ..Default::default()
+            }).id();
+            let lateral_id = commands.spawn(RootBundle{
+                tag: RootTag,
+                data: RootData {
+                    plant: root.plant,
+                    nodes: vec![lateral_node_id],
+                    direction: rotate_away(direction, settings.branching_angle, LATERAL_ROOT_AZIMUTH * connections.children.len() as f32),
+                    uptake: root.uptake,
+                    growth: 0.0,
+                    order: root.order + 1,
+                },
+                connections: RootConnectionData{parent: Some(id), children: Vec::new()},
+            }).id();
+            connections.children.push(lateral_id);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod root_development_tests {
+    use super::*;
+    use crate::maths::vector_three::Vector3;
+
+    #[test]
+    fn root_growth_test() {
+        let mut world = World::new();
+        create_gravity_resource(&mut world, [0, -1, 0], 1.0);
+        create_physical_age_time_step(&mut world, 1.0);
+        world.insert_resource(SoilCells::new(1.0));
+        world.insert_resource(SoilGrid::new((-5.0, 5.0), (-5.0, 5.0), 1.0, 100.0, 0.5, 0.2));
+        world.insert_resource(RootGrowthSettings::new(0.2, 1.0));
+
+        let branch_id = world.spawn(BranchBundle{growth_data: BranchGrowthData{growth_rate: 1.0, ..Default::default()}, ..Default::default()}).id();
+        let plant_id = world.spawn(PlantBundle{data: PlantData{root_node: Some(branch_id), ..Default::default()}, ..Default::default()}).id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((spawn_plant_roots, apply_system_buffers, calculate_root_uptake, grow_roots).chain());
+        for _ in 0..3 {schedule.run(&mut world);}
+
+        // every node is below the plant, and the roots compete with each other for resources
+        let nodes: Vec<Vector3> = world.query::<&RootNodeData>().iter(&world).map(|node| node.position).collect();
+        assert!(nodes.len() > 10);
+        assert!(nodes.iter().all(|position| position.y <= 0.0));
+        assert!(nodes.iter().any(|position| position.y < -0.5));
+        assert!(world.query::<&RootData>().iter(&world).any(|root| root.order == 1));
+
+        let root_vigor = world.get::<PlantRoots>(plant_id).unwrap().root_vigor;
+        assert!(root_vigor > 0.0 && root_vigor <= 1.0);
+
+        // the roots go when the plant dies
+        world.despawn(plant_id);
+        let mut schedule = Schedule::new();
+        schedule.add_system(remove_dead_roots);
+        schedule.run(&mut world);
+        assert_eq!(world.query::<&RootNodeData>().iter(&world).count(), 0);
+    }
+
+    #[test]
+    fn root_steering_test() {
+        // the soil on the -x side of the plant is worn out, so a single root turns towards +x
+        let mut soil_grid = SoilGrid::new((-5.0, 5.0), (-5.0, 5.0), 1.0, 100.0, 0.5, 1.0);
+        soil_grid.deplete([-2.5, 0.0, 0.0], 2.6, 1000.0, 1.0);
+        let mut settings = RootGrowthSettings::new(0.2, 5.0);
+        settings.max_order = 0;
+
+        let mut world = World::new();
+        create_gravity_resource(&mut world, [0, -1, 0], 1.0);
+        create_physical_age_time_step(&mut world, 1.0);
+        world.insert_resource(SoilCells::new(1.0));
+        world.insert_resource(soil_grid);
+        world.insert_resource(settings);
+
+        let branch_id = world.spawn(BranchBundle{growth_data: BranchGrowthData{growth_rate: 1.0, ..Default::default()}, ..Default::default()}).id();
+        let plant_id = world.spawn(PlantBundle{data: PlantData{root_node: Some(branch_id), ..Default::default()}, ..Default::default()}).id();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems((spawn_plant_roots, apply_system_buffers, calculate_root_uptake, grow_roots).chain());
+        for _ in 0..3 {schedule.run(&mut world);}
+
+        let nodes: Vec<Vector3> = world.query::<&RootNodeData>().iter(&world).map(|node| node.position).collect();
+        assert!(nodes.len() > 5);
+        assert!(nodes.iter().all(|position| position.x >= 0.0));
+        assert!(nodes.iter().any(|position| position.x > 0.2));
+
+        // without any other roots, a root in fertile soil takes up everything
+        assert_eq!(world.get::<PlantRoots>(plant_id).unwrap().root_vigor, 1.0);
+    }
+}